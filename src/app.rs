@@ -1,7 +1,10 @@
 use crate::cfg::Config;
+use crate::connection::arbiter::LEG_NAMES;
 use crate::connection::derivatives_metrics_manager::DerivativesMetricsDataConnectionManager;
+use crate::connection::health::HealthSummary;
 use crate::connection::kline_manager::KlineDataConnectionManager;
 use crate::connection::mkt_manager::MktDataConnectionManager;
+use crate::connection::registry::ConnectionRegistry;
 use crate::forwarder::ZmqForwarder;
 use crate::proxy::Proxy;
 use crate::rest_fetcher::{run_bar_close_timer, run_rest_fetcher_with_sender};
@@ -9,11 +12,13 @@ use crate::restart_checker::RestartChecker;
 
 use anyhow::Result;
 use bytes::Bytes;
-use log::{error, info};
+use log::{error, info, warn};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use tokio::signal;
 use tokio::sync::{broadcast, watch};
 use tokio::task::JoinHandle;
-use tokio::time::{interval, Duration, Instant};
+use tokio::time::{interval, interval_at, Duration, Instant};
 use tokio_util::sync::CancellationToken;
 
 pub struct CryptoProxyApp {
@@ -37,6 +42,9 @@ pub struct CryptoProxyApp {
     // 重启检查器
     restart_checker: RestartChecker,
 
+    // 所有连接的熔断状态
+    registry: Arc<ConnectionRegistry>,
+
     // 配置
     config: &'static Config,
 }
@@ -57,15 +65,30 @@ impl CryptoProxyApp {
         // 创建重启检查器
         let restart_checker = RestartChecker::new(config.is_primary, config.restart_duration_secs);
 
+        // 连接状态表，由各连接写入、app读取
+        let registry = Arc::new(ConnectionRegistry::new());
+
         // 所有管理器都强制启动，直接传递统一的广播发送器
         info!("Initializing market data manager");
         let mkt_manager = Some(
-            MktDataConnectionManager::new(config, &global_shutdown_tx, unified_tx.clone()).await,
+            MktDataConnectionManager::new(
+                config,
+                &global_shutdown_tx,
+                unified_tx.clone(),
+                registry.clone(),
+            )
+            .await,
         );
 
         info!("Initializing kline data manager");
         let kline_manager = Some(
-            KlineDataConnectionManager::new(config, &global_shutdown_tx, unified_tx.clone()).await,
+            KlineDataConnectionManager::new(
+                config,
+                &global_shutdown_tx,
+                unified_tx.clone(),
+                registry.clone(),
+            )
+            .await,
         );
 
         // 只为衍生品交易所初始化 derivatives metrics manager
@@ -80,6 +103,7 @@ impl CryptoProxyApp {
                         config,
                         &global_shutdown_tx,
                         unified_tx.clone(),
+                        registry.clone(),
                     )
                     .await,
                 )
@@ -104,6 +128,7 @@ impl CryptoProxyApp {
             unified_tx,
             _unified_rx_keepalive,
             restart_checker,
+            registry,
             config,
         })
     }
@@ -176,11 +201,23 @@ impl CryptoProxyApp {
 
         let spot_url = self.config.binance_rest.binance_url.clone();
         let futures_url = self.config.binance_rest.binance_futures_url.clone();
+        let sapi_url = self.config.binance_rest.binance_sapi_url.clone();
         let sender = self.unified_tx.clone();
+        let proxy = match self
+            .config
+            .connect_options("rest")
+            .and_then(|options| Ok(options.reqwest_proxy()?))
+        {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                error!("Invalid proxy config for REST Fetcher: {}", e);
+                return;
+            }
+        };
 
         // 启动独立的 tokio 任务，不受 restart 影响
         tokio::spawn(async move {
-            run_rest_fetcher_with_sender(spot_url, futures_url, sender).await;
+            run_rest_fetcher_with_sender(spot_url, futures_url, sapi_url, sender, proxy).await;
         });
 
         info!("REST Fetcher started (independent of restart cycle)");
@@ -246,14 +283,24 @@ impl CryptoProxyApp {
         let mut log_interval = interval(Duration::from_secs(3));
         info!("Exchange: {}", self.config.get_exchange());
 
+        // 定期刷新symbol，在运行中的连接上增减订阅
+        let symbol_refresh_enabled = self.config.symbol_refresh_secs > 0;
+        let symbol_refresh_period = Duration::from_secs(self.config.symbol_refresh_secs.max(1));
+        let mut symbol_refresh_interval =
+            interval_at(Instant::now() + symbol_refresh_period, symbol_refresh_period);
+
         while !self.cancellation_token.is_cancelled() {
             tokio::select! {
                 _ = log_interval.tick() => {
+                    self.check_stale_connections();
                     let now_instant = Instant::now();
                     info!("{}", self.format_status_table(
                         next_restart_instant.duration_since(now_instant).as_secs()
                     ));
                 }
+                _ = symbol_refresh_interval.tick(), if symbol_refresh_enabled => {
+                    self.refresh_all_symbols().await;
+                }
                 _ = tokio::time::sleep_until(next_restart_instant) => {
                     next_restart_instant += Duration::from_secs(self.restart_checker.restart_duration_secs) * 2;
                     if let Err(e) = self.perform_restart("scheduled").await {
@@ -272,7 +319,8 @@ impl CryptoProxyApp {
 
         // --- 关闭阶段 ---
         info!("为重启正在关闭所有服务...");
-        // 1. 关闭所有连接
+        // 1. 关闭所有连接，上一轮重启后没有被重新建立的连接不再保留健康状态
+        self.registry.prune_retired_health();
         let _ = self.global_shutdown_tx.send(true);
         self.shutdown_all_connections().await?;
 
@@ -341,6 +389,20 @@ impl CryptoProxyApp {
         Ok(())
     }
 
+    async fn refresh_all_symbols(&mut self) {
+        if let Some(ref mut manager) = self.mkt_manager {
+            if let Err(e) = manager.refresh_symbols().await {
+                error!("Failed to refresh symbols for MktConnectionManager: {}", e);
+            }
+        }
+
+        if let Some(ref mut manager) = self.kline_manager {
+            if let Err(e) = manager.refresh_symbols().await {
+                error!("Failed to refresh symbols for KlineConnectionManager: {}", e);
+            }
+        }
+    }
+
     async fn update_all_subscribe_msgs(&mut self) -> Result<()> {
         if let Some(ref mut manager) = self.mkt_manager {
             if let Err(e) = manager.update_subscribe_msgs().await {
@@ -408,13 +470,112 @@ impl CryptoProxyApp {
         std::sync::Arc::new(tokio::sync::Notify::new())
     }
 
+    /// 超过分组阈值没有数据的连接，通知其断开重连
+    fn check_stale_connections(&self) {
+        for (name, health) in self.registry.health_entries() {
+            let Some(threshold) = self.config.health.stale_after(health.group()) else {
+                continue;
+            };
+            if let Some(idle) = health.idle().filter(|idle| *idle >= threshold) {
+                if health.mark_stale() {
+                    warn!("[{}] No data for {:?}, requesting reconnect", name, idle);
+                }
+            }
+        }
+    }
+
     fn format_status_table(&self, next_restart: u64) -> String {
-        format!(
+        let mut table = format!(
             "\n\
              |-------------------------|-------------|\n\
              | Next Restart            | {:>10}s     |\n\
              |-------------------------|-------------|",
             next_restart
-        )
+        );
+        // 只列出熔断器未关闭的batch
+        for (name, status) in self.registry.degraded() {
+            table.push_str(&format!(
+                "\n| {:<40} | {:>9} | failures {:>4} (total {}) | {}",
+                name,
+                status.breaker.as_str(),
+                status.consecutive_failures,
+                status.total_failures,
+                status.last_error.as_deref().unwrap_or("-")
+            ));
+        }
+        for (name, status) in self.registry.noticed() {
+            table.push_str(&format!(
+                "\n| {:<40} | notices {:>4} | {}",
+                name,
+                status.notices,
+                status.last_notice.as_deref().unwrap_or("-")
+            ));
+        }
+        // 连接健康状态按分组汇总，batch多时逐个连接输出会刷屏
+        // 未连接或stale的连接单独列出：建连时间、重连次数、速率、距最后一帧的时间、被拒绝的帧数、最近一次断开原因
+        let mut summaries: BTreeMap<String, HealthSummary> = BTreeMap::new();
+        let mut unhealthy = String::new();
+        for (name, health) in self.registry.health_entries() {
+            let snapshot = health.sample();
+            summaries
+                .entry(health.group().to_string())
+                .or_default()
+                .add(&snapshot);
+            if !snapshot.is_unhealthy() {
+                continue;
+            }
+            unhealthy.push_str(&format!(
+                "\n| {:<40} | {} | reconnects {:>3} | {:>8.1} msg/s {:>9.1} KB/s | idle {} | rejected {} | last close: {}{}",
+                name,
+                snapshot.connected_since.map_or("disconnected".to_string(), |since| {
+                    format!("up since {}", since.format("%m-%d %H:%M:%S"))
+                }),
+                snapshot.reconnects,
+                snapshot.msg_rate,
+                snapshot.byte_rate / 1024.0,
+                snapshot
+                    .idle
+                    .map_or("-".to_string(), |idle| format!("{:.1}s", idle.as_secs_f64())),
+                snapshot.rejected_frames,
+                snapshot.last_close_reason.as_deref().unwrap_or("-"),
+                if snapshot.stale { " | STALE" } else { "" }
+            ));
+        }
+        for (group, summary) in &summaries {
+            table.push_str(&format!(
+                "\n| {:<40} | connected {}/{} unhealthy {} | reconnects {:>3} | {:>8.1} msg/s {:>9.1} KB/s | max idle {} | rejected {}",
+                group,
+                summary.connected,
+                summary.connections,
+                summary.unhealthy,
+                summary.reconnects,
+                summary.msg_rate,
+                summary.byte_rate / 1024.0,
+                summary
+                    .max_idle
+                    .map_or("-".to_string(), |idle| format!("{:.1}s", idle.as_secs_f64())),
+                summary.rejected_frames
+            ));
+        }
+        table.push_str(&unhealthy);
+        for (arg, reason) in self.registry.quarantined() {
+            table.push_str(&format!("\n| quarantined {:<28} | {}", arg, reason));
+        }
+        // A/B冗余batch：每条腿先到的比例，以及后到时的平均/最大落后时间
+        for (name, legs) in self.registry.leg_stats() {
+            let total = legs[0].wins + legs[1].wins;
+            let mut row = format!("\n| {:<40} |", name);
+            for (leg, stats) in LEG_NAMES.iter().zip(legs.iter()) {
+                row.push_str(&format!(
+                    " {} win {:>5.1}% lag avg {}us max {}us |",
+                    leg,
+                    stats.win_rate(total) * 100.0,
+                    stats.avg_lag_us(),
+                    stats.lag_us_max
+                ));
+            }
+            table.push_str(&row);
+        }
+        table
     }
 }
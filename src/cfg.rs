@@ -1,3 +1,9 @@
+use crate::connection::heartbeat::HeartbeatPolicy;
+use crate::connection::rate_limit::{ExchangeLimits, RateLimiter};
+use crate::connection::resolver::HostResolver;
+use crate::connection::tls::TlsClient;
+use crate::connection::transport::{ConnectOptions, EgressProxy};
+use crate::sub_msg::SubscribeMsgs;
 use crate::Exchange;
 use anyhow::{Context, Result};
 use chrono::Utc;
@@ -5,10 +11,14 @@ use log::{info, warn};
 use prettytable::{format, Cell, Row, Table};
 use serde::Deserialize;
 use serde_yaml;
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs::{self, File};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use uuid::Uuid;
 
 fn join_url(base: &str, path: &str) -> String {
@@ -22,9 +32,15 @@ pub struct BinanceRestCfg {
     pub binance_url: String,
     #[serde(rename = "binance-futures_url")]
     pub binance_futures_url: String,
+    #[serde(default = "BinanceRestCfg::default_sapi_url")]
+    pub binance_sapi_url: String, // sapi签名接口（杠杆可借库存）
 }
 
 impl BinanceRestCfg {
+    fn default_sapi_url() -> String {
+        "https://api.binance.com".to_string()
+    }
+
     pub fn spot_depth_url(&self) -> String {
         join_url(&self.binance_url, "api/v3/depth")
     }
@@ -34,6 +50,326 @@ impl BinanceRestCfg {
     }
 }
 
+/// 心跳模式，对应 connection::heartbeat::HeartbeatPolicy
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeartbeatMode {
+    ServerPing,
+    IdlePing,
+    FixedInterval,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HeartbeatPolicyCfg {
+    pub mode: HeartbeatMode,
+    pub interval_secs: u64, // server-ping: 服务端ping间隔; idle-ping: 空闲多久发ping; fixed-interval: 发ping间隔
+    pub timeout_secs: u64,  // server-ping: 额外容忍时间; 其余: 等待pong的超时时间
+}
+
+/// 按交易所配置心跳，缺省时使用各交易所的默认值
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HeartbeatCfg {
+    pub binance: Option<HeartbeatPolicyCfg>,
+    pub okex: Option<HeartbeatPolicyCfg>,
+    pub bybit: Option<HeartbeatPolicyCfg>,
+}
+
+/// 重连退避与熔断参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReconnectCfg {
+    pub base_delay_ms: u64,     // 首次重连等待
+    pub max_delay_ms: u64,      // 退避上限
+    pub failure_threshold: u32, // 连续失败多少次后熔断
+    pub open_secs: u64,         // 熔断后暂停重连的时间
+    pub stable_secs: u64,       // 连接存活超过该时长才重置退避
+}
+
+impl Default for ReconnectCfg {
+    fn default() -> Self {
+        Self {
+            base_delay_ms: 1000,
+            max_delay_ms: 60_000,
+            failure_threshold: 5,
+            open_secs: 120,
+            stable_secs: 30,
+        }
+    }
+}
+
+/// 单个交易所的接入地址，缺省时使用 sub_msg 中的默认地址
+/// 可指向测试网、托管机房的接入点或本地mock服务
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ExchangeEndpointsCfg {
+    pub market: Option<String>,      // inc/trade/时间信号
+    pub kline: Option<String>,       // kline（okex为business频道）
+    pub derivatives: Option<String>, // 标记价格、资金费率、强平
+    pub sbe: Option<String>,         // 币安现货SBE
+    pub rest: Option<String>,        // REST base url
+    pub sapi: Option<String>,        // 币安sapi签名接口 base url
+}
+
+/// TLS后端，rustls需要编译时启用 rustls feature
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TlsBackend {
+    #[default]
+    Native,
+    Rustls,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsCfg {
+    #[serde(default)]
+    pub backend: TlsBackend,
+    #[serde(default = "TlsCfg::default_ca_file")]
+    #[cfg_attr(not(feature = "rustls"), allow(dead_code))]
+    pub ca_file: String, // rustls使用的根证书（PEM）
+}
+
+impl TlsCfg {
+    fn default_ca_file() -> String {
+        "/etc/ssl/certs/ca-certificates.crt".to_string()
+    }
+}
+
+impl Default for TlsCfg {
+    fn default() -> Self {
+        Self {
+            backend: TlsBackend::default(),
+            ca_file: Self::default_ca_file(),
+        }
+    }
+}
+
+/// 域名解析与IP优选，hosts为静态覆盖（域名 -> IP列表）
+#[derive(Debug, Deserialize, Clone)]
+pub struct DnsCfg {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "DnsCfg::default_refresh_secs")]
+    pub refresh_secs: u64, // 重新解析与测量的间隔
+    #[serde(default = "DnsCfg::default_probe_timeout_ms")]
+    pub probe_timeout_ms: u64, // 单个IP握手测量的超时
+    #[serde(default = "DnsCfg::default_failure_cooldown_secs")]
+    pub failure_cooldown_secs: u64, // IP连接失败后降为最低优先级的时间
+    #[serde(default)]
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+}
+
+impl DnsCfg {
+    fn default_refresh_secs() -> u64 {
+        300
+    }
+
+    fn default_probe_timeout_ms() -> u64 {
+        2000
+    }
+
+    fn default_failure_cooldown_secs() -> u64 {
+        60
+    }
+
+    /// 未启用且没有静态覆盖时使用系统解析
+    pub fn is_active(&self) -> bool {
+        self.enabled || !self.hosts.is_empty()
+    }
+}
+
+impl Default for DnsCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_secs: Self::default_refresh_secs(),
+            probe_timeout_ms: Self::default_probe_timeout_ms(),
+            failure_cooldown_secs: Self::default_failure_cooldown_secs(),
+            hosts: HashMap::new(),
+        }
+    }
+}
+
+/// 连接健康检查，超过 stale_secs 没有数据的batch主动重连，0表示不检查（默认）
+/// groups按连接分组覆盖：inc, trade, signal, kline, derivatives
+/// 强平、衍生品指标与低流动性的成交batch推送稀疏，开启时按分组设置或设为0
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HealthCfg {
+    #[serde(default)]
+    pub stale_secs: u64,
+    #[serde(default)]
+    pub groups: HashMap<String, u64>,
+}
+
+impl HealthCfg {
+    /// 该分组的无数据阈值，None表示不检查
+    pub fn stale_after(&self, group: &str) -> Option<std::time::Duration> {
+        let secs = self.groups.get(group).copied().unwrap_or(self.stale_secs);
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+}
+
+/// 重点symbol使用独立的小batch，不与大量低流动性symbol共用连接和parser任务
+/// symbols为交易所原始写法（如 BTCUSDT、BTC-USDT-SWAP），不在当前symbol列表中的忽略
+#[derive(Debug, Deserialize, Clone)]
+pub struct PriorityCfg {
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default = "PriorityCfg::default_batch_size")]
+    pub batch_size: usize, // 每个重点batch包含的symbol数
+    #[serde(default)]
+    pub redundant: bool, // 重点batch建立A/B两条连接，redundant_connections开启时总是建立
+    #[serde(default = "PriorityCfg::default_channel_capacity")]
+    pub channel_capacity: usize, // 重点batch连接到parser的broadcast通道容量
+}
+
+impl PriorityCfg {
+    fn default_batch_size() -> usize {
+        2
+    }
+
+    fn default_channel_capacity() -> usize {
+        65536
+    }
+
+    pub fn is_priority(&self, symbol: &str) -> bool {
+        self.symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol))
+    }
+
+    /// 把symbol分为重点与普通两部分，重点symbol按配置中的顺序排列
+    pub fn split(&self, symbols: &[String]) -> (Vec<String>, Vec<String>) {
+        let priority: Vec<String> = self
+            .symbols
+            .iter()
+            .filter_map(|p| symbols.iter().find(|s| s.eq_ignore_ascii_case(p)).cloned())
+            .collect();
+        let regular = symbols
+            .iter()
+            .filter(|s| !priority.contains(s))
+            .cloned()
+            .collect();
+        (priority, regular)
+    }
+}
+
+impl Default for PriorityCfg {
+    fn default() -> Self {
+        Self {
+            symbols: Vec::new(),
+            batch_size: Self::default_batch_size(),
+            redundant: false,
+            channel_capacity: Self::default_channel_capacity(),
+        }
+    }
+}
+
+/// websocket帧大小限制（字节），超限的帧由websocket层拒绝，连接断开重连
+#[derive(Debug, Deserialize, Clone)]
+pub struct WsLimitsCfg {
+    #[serde(default = "WsLimitsCfg::default_max_message_size")]
+    pub max_message_size: usize, // 单条消息（含分片）的上限
+    #[serde(default = "WsLimitsCfg::default_max_frame_size")]
+    pub max_frame_size: usize, // 单帧payload的上限
+    #[serde(default = "WsLimitsCfg::default_max_write_buffer_size")]
+    pub max_write_buffer_size: usize, // 写缓冲上限，写不出去时报错而不是无限堆积
+}
+
+impl WsLimitsCfg {
+    fn default_max_message_size() -> usize {
+        16 << 20
+    }
+
+    fn default_max_frame_size() -> usize {
+        4 << 20
+    }
+
+    fn default_max_write_buffer_size() -> usize {
+        1 << 20
+    }
+
+    pub fn to_ws_config(&self) -> WebSocketConfig {
+        WebSocketConfig {
+            max_message_size: Some(self.max_message_size),
+            max_frame_size: Some(self.max_frame_size),
+            max_write_buffer_size: self.max_write_buffer_size,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for WsLimitsCfg {
+    fn default() -> Self {
+        Self {
+            max_message_size: Self::default_max_message_size(),
+            max_frame_size: Self::default_max_frame_size(),
+            max_write_buffer_size: Self::default_max_write_buffer_size(),
+        }
+    }
+}
+
+/// parser解析结果监控：按间隔输出各parser的计数，解析失败/未知事件的原始帧限频采样到sample_dir
+#[derive(Debug, Deserialize, Clone)]
+pub struct ParseMonitorCfg {
+    #[serde(default = "ParseMonitorCfg::default_stats_interval_secs")]
+    pub stats_interval_secs: u64, // 计数日志间隔，0表示不输出
+    #[serde(default)]
+    pub sample_dir: Option<String>, // 采样文件目录，None表示不采样
+    #[serde(default = "ParseMonitorCfg::default_sample_interval_secs")]
+    pub sample_interval_secs: u64, // 每个parser两次采样的最小间隔
+    #[serde(default = "ParseMonitorCfg::default_max_sample_bytes")]
+    pub max_sample_bytes: usize, // 单帧采样的最大长度，超出部分截断
+}
+
+impl ParseMonitorCfg {
+    fn default_stats_interval_secs() -> u64 {
+        60
+    }
+
+    fn default_sample_interval_secs() -> u64 {
+        60
+    }
+
+    fn default_max_sample_bytes() -> usize {
+        64 << 10
+    }
+}
+
+impl Default for ParseMonitorCfg {
+    fn default() -> Self {
+        Self {
+            stats_interval_secs: Self::default_stats_interval_secs(),
+            sample_dir: None,
+            sample_interval_secs: Self::default_sample_interval_secs(),
+            max_sample_bytes: Self::default_max_sample_bytes(),
+        }
+    }
+}
+
+/// 出口代理，url 形如 http://host:port 或 socks5://host:port
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProxyEndpointCfg {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// 出口代理配置，优先级：连接分组 > 交易所 > default
+/// 连接分组：inc, trade, signal, kline, derivatives, rest
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProxyCfg {
+    pub default: Option<ProxyEndpointCfg>,
+    #[serde(default)]
+    pub exchanges: HashMap<String, ProxyEndpointCfg>,
+    #[serde(default)]
+    pub groups: HashMap<String, ProxyEndpointCfg>,
+}
+
+/// 按连接分组绑定本地地址，优先级：连接分组 > default
+/// 连接分组：inc, trade, signal, kline, derivatives
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LocalBindCfg {
+    pub default: Option<IpAddr>,
+    #[serde(default)]
+    pub groups: HashMap<String, IpAddr>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ConfigFile {
     is_primary: bool,
@@ -41,6 +377,11 @@ struct ConfigFile {
     snapshot_requery_time: Option<String>,
     symbol_socket: String,
     symbol_snapshot_dir: Option<String>,
+    symbol_refresh_secs: Option<u64>,
+    redundant_connections: Option<bool>,
+    binance_combined_streams: Option<bool>,
+    kernel_timestamps: Option<bool>,
+    okex_checksum: Option<bool>,
     binance: ZmqProxyCfg,
     #[serde(rename = "binance-spot")]
     binance_spot: ZmqProxyCfg,
@@ -53,6 +394,17 @@ struct ConfigFile {
     bybit: ZmqProxyCfg,
     #[serde(rename = "bybit-spot")]
     bybit_spot: ZmqProxyCfg,
+    heartbeat: Option<HeartbeatCfg>,
+    reconnect: Option<ReconnectCfg>,
+    proxy: Option<ProxyCfg>,
+    local_bind: Option<LocalBindCfg>,
+    endpoints: Option<HashMap<String, ExchangeEndpointsCfg>>,
+    dns: Option<DnsCfg>,
+    tls: Option<TlsCfg>,
+    health: Option<HealthCfg>,
+    ws_limits: Option<WsLimitsCfg>,
+    priority: Option<PriorityCfg>,
+    parse_monitor: Option<ParseMonitorCfg>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -123,6 +475,16 @@ pub struct Config {
     pub snapshot_requery_time: Option<String>,
     pub symbol_socket: String,
     pub symbol_snapshot_dir: String,
+    #[serde(default)]
+    pub symbol_refresh_secs: u64, // 在线刷新symbol的间隔，0（默认）表示只在计划重启时刷新
+    #[serde(default)]
+    pub redundant_connections: bool, // inc/trade batch是否建立A/B两条连接
+    #[serde(default)]
+    pub binance_combined_streams: bool, // 币安JSON连接使用 /stream?streams= 组合URL订阅
+    #[serde(default)]
+    pub kernel_timestamps: bool, // websocket socket开启内核接收时间戳，以 OrderBookIncTs/TradeInfoTs 消息输出
+    #[serde(default)]
+    pub okex_checksum: bool, // okex订单簿维护本地副本并校验checksum，不一致时重新订阅
     pub exchange: Exchange, // 在运行时设置，不从配置文件读取
     pub binance: ZmqProxyCfg,
     #[serde(rename = "binance-spot")]
@@ -136,6 +498,34 @@ pub struct Config {
     pub bybit: ZmqProxyCfg,
     #[serde(rename = "bybit-spot")]
     pub bybit_spot: ZmqProxyCfg,
+    #[serde(default)]
+    pub heartbeat: HeartbeatCfg,
+    #[serde(default)]
+    pub reconnect: ReconnectCfg,
+    #[serde(default)]
+    pub proxy: ProxyCfg,
+    #[serde(default)]
+    pub local_bind: LocalBindCfg,
+    #[serde(default)]
+    pub endpoints: HashMap<String, ExchangeEndpointsCfg>, // key为交易所
+    #[serde(skip)]
+    pub rate_limiter: Arc<RateLimiter>, // 在运行时按交易所创建，所有连接共享
+    #[serde(default)]
+    pub dns: DnsCfg,
+    #[serde(skip)]
+    pub resolver: Option<Arc<HostResolver>>, // 在运行时按dns配置创建，所有连接共享
+    #[serde(default)]
+    pub tls: TlsCfg,
+    #[serde(skip)]
+    pub tls_client: Option<Arc<TlsClient>>, // 在运行时按tls配置创建，共享会话缓存
+    #[serde(default)]
+    pub health: HealthCfg,
+    #[serde(default)]
+    pub ws_limits: WsLimitsCfg,
+    #[serde(default)]
+    pub priority: PriorityCfg,
+    #[serde(default)]
+    pub parse_monitor: ParseMonitorCfg,
 }
 
 impl Config {
@@ -153,25 +543,68 @@ impl Config {
         Self::ensure_snapshot_dir(Path::new(&symbol_snapshot_dir)).await?;
 
         // 构造 Config 结构体
-        let config = Config {
+        let endpoints = config_file.endpoints.unwrap_or_default();
+        let mut binance_rest = config_file.binance_rest.unwrap_or_else(|| BinanceRestCfg {
+            binance_url: String::new(),
+            binance_futures_url: String::new(),
+            binance_sapi_url: BinanceRestCfg::default_sapi_url(),
+        });
+        // 币安REST地址也可以在endpoints中配置，命令行参数仍然优先
+        let rest_override = |exchange: &str| endpoints.get(exchange).and_then(|e| e.rest.clone());
+        if let Some(url) = rest_override("binance-spot").or_else(|| rest_override("binance")) {
+            binance_rest.binance_url = url;
+        }
+        if let Some(url) = rest_override("binance-futures") {
+            binance_rest.binance_futures_url = url;
+        }
+        let sapi_override = |exchange: &str| endpoints.get(exchange).and_then(|e| e.sapi.clone());
+        if let Some(url) = sapi_override("binance-spot").or_else(|| sapi_override("binance")) {
+            binance_rest.binance_sapi_url = url;
+        }
+
+        let mut config = Config {
             is_primary: config_file.is_primary,
             restart_duration_secs: config_file.restart_duration_secs,
             snapshot_requery_time: config_file.snapshot_requery_time,
             symbol_socket: config_file.symbol_socket,
             symbol_snapshot_dir,
+            symbol_refresh_secs: config_file.symbol_refresh_secs.unwrap_or(0),
+            redundant_connections: config_file.redundant_connections.unwrap_or(false),
+            binance_combined_streams: config_file.binance_combined_streams.unwrap_or(false),
+            kernel_timestamps: config_file.kernel_timestamps.unwrap_or(false),
+            okex_checksum: config_file.okex_checksum.unwrap_or(false),
             exchange, // 从命令行参数设置
             binance: config_file.binance,
             binance_spot: config_file.binance_spot,
             binance_futures: config_file.binance_futures,
-            binance_rest: config_file.binance_rest.unwrap_or_else(|| BinanceRestCfg {
-                binance_url: String::new(),
-                binance_futures_url: String::new(),
-            }),
+            binance_rest,
             okex: config_file.okex,
             okex_swap: config_file.okex_swap,
             bybit: config_file.bybit,
             bybit_spot: config_file.bybit_spot,
+            heartbeat: config_file.heartbeat.unwrap_or_default(),
+            reconnect: config_file.reconnect.unwrap_or_default(),
+            proxy: config_file.proxy.unwrap_or_default(),
+            local_bind: config_file.local_bind.unwrap_or_default(),
+            endpoints,
+            rate_limiter: Arc::new(RateLimiter::default()),
+            resolver: None,
+            dns: config_file.dns.unwrap_or_default(),
+            tls: config_file.tls.unwrap_or_default(),
+            tls_client: None,
+            health: config_file.health.unwrap_or_default(),
+            ws_limits: config_file.ws_limits.unwrap_or_default(),
+            priority: config_file.priority.unwrap_or_default(),
+            parse_monitor: config_file.parse_monitor.unwrap_or_default(),
         };
+        config.tls_client = Some(Arc::new(TlsClient::from_cfg(&config.tls)?));
+        if config.dns.is_active() {
+            config.resolver = Some(Arc::new(HostResolver::from_cfg(&config.dns)));
+        }
+        config.rate_limiter = Arc::new(RateLimiter::new(ExchangeLimits::for_exchange(
+            &config.get_exchange(),
+        )));
+        config.validate_proxy()?;
 
         Ok(config)
     }
@@ -200,6 +633,124 @@ impl Config {
         }
     }
 
+    pub fn get_heartbeat_policy(&self) -> HeartbeatPolicy {
+        let policy_cfg = match self.exchange {
+            Exchange::Binance | Exchange::BinanceSpot | Exchange::BinanceFutures => {
+                self.heartbeat.binance.as_ref()
+            }
+            Exchange::Okex | Exchange::OkexSwap => self.heartbeat.okex.as_ref(),
+            Exchange::Bybit | Exchange::BybitSpot => self.heartbeat.bybit.as_ref(),
+        };
+        match policy_cfg {
+            Some(policy_cfg) => HeartbeatPolicy::from_cfg(policy_cfg),
+            None => HeartbeatPolicy::default_for_exchange(&self.get_exchange()),
+        }
+    }
+
+    /// 当前交易所配置的接入地址，未配置时使用默认地址
+    fn endpoint_or(
+        &self,
+        kind: &str,
+        pick: fn(&ExchangeEndpointsCfg) -> Option<&String>,
+        default: &str,
+    ) -> String {
+        match self.endpoints.get(&self.get_exchange()).and_then(pick) {
+            Some(url) => {
+                info!("Using configured {} endpoint {}", kind, url);
+                url.clone()
+            }
+            None => default.to_string(),
+        }
+    }
+
+    pub fn market_ws_url(&self) -> String {
+        let exchange = self.get_exchange();
+        self.endpoint_or(
+            "market",
+            |e| e.market.as_ref(),
+            SubscribeMsgs::get_exchange_mkt_data_url(&exchange),
+        )
+    }
+
+    pub fn kline_ws_url(&self) -> String {
+        let exchange = self.get_exchange();
+        self.endpoint_or(
+            "kline",
+            |e| e.kline.as_ref(),
+            SubscribeMsgs::get_exchange_kline_data_url(&exchange),
+        )
+    }
+
+    pub fn sbe_ws_url(&self) -> String {
+        let exchange = self.get_exchange();
+        self.endpoint_or(
+            "sbe",
+            |e| e.sbe.as_ref(),
+            SubscribeMsgs::get_exchange_sbe_data_url(&exchange),
+        )
+    }
+
+    pub fn derivatives_ws_url(&self) -> String {
+        let exchange = self.get_exchange();
+        self.endpoint_or(
+            "derivatives",
+            |e| e.derivatives.as_ref(),
+            SubscribeMsgs::get_exchange_derivatives_data_url(&exchange),
+        )
+    }
+
+    /// 指定连接分组使用的传输参数
+    pub fn connect_options(&self, group: &str) -> anyhow::Result<ConnectOptions> {
+        let endpoint = self
+            .proxy
+            .groups
+            .get(group)
+            .or_else(|| self.proxy.exchanges.get(&self.get_exchange()))
+            .or(self.proxy.default.as_ref());
+        let proxy = endpoint.map(EgressProxy::from_cfg).transpose()?;
+        if let Some(proxy) = &proxy {
+            info!(
+                "Using {:?} proxy {}:{} for {} connections",
+                proxy.scheme, proxy.host, proxy.port, group
+            );
+        }
+        let local_addr = self
+            .local_bind
+            .groups
+            .get(group)
+            .or(self.local_bind.default.as_ref())
+            .copied();
+        if let Some(local_addr) = local_addr {
+            info!(
+                "Binding {} connections to local address {}",
+                group, local_addr
+            );
+        }
+        Ok(ConnectOptions {
+            proxy,
+            local_addr,
+            rate_limiter: self.rate_limiter.clone(),
+            resolver: self.resolver.clone(),
+            tls: self.tls_client.clone(),
+            kernel_timestamps: self.kernel_timestamps,
+            ws_config: self.ws_limits.to_ws_config(),
+        })
+    }
+
+    /// 启动时校验所有代理配置，避免运行中才发现配置错误
+    fn validate_proxy(&self) -> anyhow::Result<()> {
+        let endpoints = self
+            .proxy
+            .default
+            .iter()
+            .chain(self.proxy.exchanges.values())
+            .chain(self.proxy.groups.values());
+        for endpoint in endpoints {
+            EgressProxy::from_cfg(endpoint)?;
+        }
+        Ok(())
+    }
+
     pub fn get_zmq_proxy(&self) -> ZmqProxyCfg {
         match self.exchange {
             Exchange::BinanceFutures => self.binance_futures.clone(),
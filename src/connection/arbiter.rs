@@ -0,0 +1,142 @@
+use crate::connection::dedup::FrameKey;
+use std::collections::{HashMap, VecDeque};
+use tokio::time::Instant;
+
+// A/B 冗余连接仲裁
+// 同一batch建立两条独立连接（两条腿），parser前按帧身份取先到的一份转发，后到的丢弃
+// 同时统计每条腿先到的比例，以及后到时落后另一条腿的时间，用于判断哪条腿更快
+
+pub const LEG_NAMES: [&str; 2] = ["A", "B"];
+
+/// 单条腿的仲裁统计
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LegStats {
+    pub wins: u64,         // 先到并被转发的帧数
+    pub late: u64,         // 后到被丢弃的帧数
+    pub lag_us_total: u64, // 后到时落后于另一条腿的累计时间
+    pub lag_us_max: u64,
+}
+
+impl LegStats {
+    /// 先到的比例，total为两条腿的wins之和
+    pub fn win_rate(&self, total: u64) -> f64 {
+        if total == 0 {
+            0.0
+        } else {
+            self.wins as f64 / total as f64
+        }
+    }
+
+    pub fn avg_lag_us(&self) -> u64 {
+        self.lag_us_total.checked_div(self.late).unwrap_or(0)
+    }
+}
+
+pub struct Arbiter {
+    key_fn: fn(&[u8]) -> FrameKey,
+    capacity: usize,
+    next_seq: u64,
+    pending: HashMap<FrameKey, (usize, Instant, u64)>, // 只到了一条腿的帧：先到的腿、到达时间、序号
+    order: VecDeque<(FrameKey, u64)>,
+    stats: [LegStats; 2],
+}
+
+impl Arbiter {
+    pub fn new(key_fn: fn(&[u8]) -> FrameKey, capacity: usize) -> Self {
+        Self {
+            key_fn,
+            capacity,
+            next_seq: 0,
+            pending: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            stats: [LegStats::default(); 2],
+        }
+    }
+
+    /// 返回true表示该帧首次到达，需要转发
+    pub fn admit(&mut self, leg: usize, frame: &[u8]) -> bool {
+        let key = (self.key_fn)(frame);
+        let now = Instant::now();
+        if let Some(&(first_leg, first_at, _)) = self.pending.get(&key) {
+            // 同一条腿上的重复（如重连后的快照）不是冗余副本，照常转发
+            if first_leg != leg {
+                self.pending.remove(&key);
+                let lag_us = now.duration_since(first_at).as_micros() as u64;
+                let stats = &mut self.stats[leg];
+                stats.late += 1;
+                stats.lag_us_total += lag_us;
+                stats.lag_us_max = stats.lag_us_max.max(lag_us);
+                return false;
+            }
+        }
+        // 另一条腿断开时只有一份到达，按到达顺序淘汰最旧的记录
+        if self.order.len() >= self.capacity {
+            if let Some((oldest, seq)) = self.order.pop_front() {
+                if self.pending.get(&oldest).map(|entry| entry.2) == Some(seq) {
+                    self.pending.remove(&oldest);
+                }
+            }
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.pending.insert(key.clone(), (leg, now, seq));
+        self.order.push_back((key, seq));
+        self.stats[leg].wins += 1;
+        true
+    }
+
+    pub fn stats(&self) -> [LegStats; 2] {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::dedup::binance_frame_key;
+
+    const FRAME: &[u8] = br#"{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":10,"u":12}"#;
+    const NEXT: &[u8] = br#"{"e":"depthUpdate","E":2,"s":"BTCUSDT","U":13,"u":15}"#;
+
+    #[test]
+    fn first_copy_wins_and_late_copy_is_counted() {
+        let mut arbiter = Arbiter::new(binance_frame_key, 16);
+        assert!(arbiter.admit(0, FRAME));
+        assert!(!arbiter.admit(1, FRAME));
+        // B先到的帧由B转发，A后到
+        assert!(arbiter.admit(1, NEXT));
+        assert!(!arbiter.admit(0, NEXT));
+
+        let [a, b] = arbiter.stats();
+        assert_eq!((a.wins, a.late), (1, 1));
+        assert_eq!((b.wins, b.late), (1, 1));
+        assert!(a.lag_us_max >= a.avg_lag_us());
+        assert_eq!(a.win_rate(a.wins + b.wins), 0.5);
+        assert_eq!(LegStats::default().win_rate(0), 0.0);
+        assert_eq!(LegStats::default().avg_lag_us(), 0);
+    }
+
+    #[test]
+    fn same_leg_repeats_are_forwarded() {
+        let mut arbiter = Arbiter::new(binance_frame_key, 16);
+        assert!(arbiter.admit(0, FRAME));
+        // 同一条腿上的重复不是冗余副本
+        assert!(arbiter.admit(0, FRAME));
+        // 另一条腿的副本只丢弃一次，之后视为新帧
+        assert!(!arbiter.admit(1, FRAME));
+        assert!(arbiter.admit(1, FRAME));
+        let [a, b] = arbiter.stats();
+        assert_eq!((a.wins, a.late), (2, 0));
+        assert_eq!((b.wins, b.late), (1, 1));
+    }
+
+    #[test]
+    fn oldest_pending_frames_are_evicted() {
+        let mut arbiter = Arbiter::new(binance_frame_key, 1);
+        assert!(arbiter.admit(0, FRAME));
+        // 容量为1，FRAME的记录被NEXT淘汰，B上晚到的FRAME当作新帧转发
+        assert!(arbiter.admit(0, NEXT));
+        assert!(arbiter.admit(1, FRAME));
+        assert_eq!(arbiter.stats()[1].late, 0);
+    }
+}
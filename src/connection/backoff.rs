@@ -0,0 +1,223 @@
+use crate::cfg::ReconnectCfg;
+use rand::Rng;
+use tokio::time::{Duration, Instant};
+
+/// 带抖动的指数退避，避免所有batch在交易所故障时同步重连
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// 下一次重试前的等待时间，取 [d/2, d] 区间内的随机值，d = min(max, base * 2^attempt)
+    pub fn next_delay(&mut self) -> Duration {
+        let exp = self.base.saturating_mul(1u32 << self.attempt.min(16));
+        let capped = exp.min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        let half = capped / 2;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_ms)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// 熔断器状态，Open期间不再尝试连接
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half-open",
+        }
+    }
+}
+
+/// 每个batch一个熔断器：连续失败达到阈值后进入Open，冷却结束后HalfOpen试连一次
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    consecutive_failures: u32,
+    state: BreakerState,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            consecutive_failures: 0,
+            state: BreakerState::Closed,
+            open_until: None,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.state = BreakerState::Closed;
+        self.open_until = None;
+    }
+
+    /// 记录一次失败，返回熔断器是否因此进入Open
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let should_open = self.state == BreakerState::HalfOpen
+            || self.consecutive_failures >= self.failure_threshold;
+        if should_open {
+            self.state = BreakerState::Open;
+            self.open_until = Some(Instant::now() + self.open_duration);
+        }
+        should_open
+    }
+
+    /// Open状态下需要等待到的时间点，等待结束后转为HalfOpen
+    pub fn open_until(&self) -> Option<Instant> {
+        match self.state {
+            BreakerState::Open => self.open_until,
+            _ => None,
+        }
+    }
+
+    pub fn half_open(&mut self) {
+        if self.state == BreakerState::Open {
+            self.state = BreakerState::HalfOpen;
+            self.open_until = None;
+        }
+    }
+}
+
+/// start_ws 使用的重连策略：退避 + 熔断
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub backoff: Backoff,
+    pub breaker: CircuitBreaker,
+    pub stable_after: Duration, // 连接存活超过该时长才视为一次成功，避免连上即断时反复重置退避
+}
+
+impl ReconnectPolicy {
+    pub fn from_cfg(cfg: &ReconnectCfg) -> Self {
+        Self {
+            backoff: Backoff::new(
+                Duration::from_millis(cfg.base_delay_ms),
+                Duration::from_millis(cfg.max_delay_ms),
+            ),
+            breaker: CircuitBreaker::new(
+                cfg.failure_threshold,
+                Duration::from_secs(cfg.open_secs),
+            ),
+            stable_after: Duration::from_secs(cfg.stable_secs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_jitter_stays_within_bounds_and_cap() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(1000);
+        let mut backoff = Backoff::new(base, max);
+        for attempt in 0..40u32 {
+            let full = base.saturating_mul(1u32 << attempt.min(16)).min(max);
+            let delay = backoff.next_delay();
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+        }
+        // 达到上限后一直在 [max/2, max] 之间
+        let delay = backoff.next_delay();
+        assert!(delay >= max / 2 && delay <= max);
+    }
+
+    #[test]
+    fn backoff_reset_starts_over() {
+        let base = Duration::from_millis(100);
+        let mut backoff = Backoff::new(base, Duration::from_secs(60));
+        for _ in 0..10 {
+            backoff.next_delay();
+        }
+        assert!(backoff.next_delay() >= Duration::from_secs(30));
+        backoff.reset();
+        assert!(backoff.next_delay() <= base);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_half_opens() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.open_until(), None);
+
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker
+            .open_until()
+            .is_some_and(|until| until > Instant::now()));
+
+        breaker.half_open();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert_eq!(breaker.open_until(), None);
+        // HalfOpen试连失败立即重新Open
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.consecutive_failures(), 4);
+    }
+
+    #[test]
+    fn breaker_closes_on_success() {
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.half_open();
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+        assert_eq!(breaker.open_until(), None);
+        // 成功后重新计数，单次失败不会打开
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        // half_open 只对Open生效
+        breaker.half_open();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        // 阈值为0时按1处理
+        let mut breaker = CircuitBreaker::new(0, Duration::from_secs(30));
+        assert!(breaker.record_failure());
+    }
+}
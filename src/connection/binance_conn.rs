@@ -1,16 +1,23 @@
 use crate::cfg::BinanceRestCfg;
 use crate::connection::connection::{
-    MktConnection, MktConnectionHandler, MktConnectionRunner, WsConnector,
+    MktConnection, MktConnectionHandler, MktConnectionRunner, ReplacementLeg, WsConnectionResult,
+    WsConnector,
 };
+use crate::connection::dedup::{binance_frame_key, FrameDedup, FrameVerdict};
+use crate::connection::heartbeat::{Heartbeat, HeartbeatAction};
+use crate::connection::live_sub::{forward_command, next_command};
+use crate::connection::sub_ack::{handle_ack_frame, AckFormat, SubAckTracker, SUB_ACK_TIMEOUT};
+use crate::connection::transport::ConnectOptions;
 use crate::mkt_msg::{MktMsg, MktMsgType};
+use crate::sub_msg::binance_combined_stream_url;
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures_util::{SinkExt, TryStreamExt};
 use log::{error, info, warn};
 use reqwest::Client;
 use serde_json::Value;
 use std::collections::HashSet;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tokio::time::{self, Duration, Instant};
 use tokio_tungstenite::tungstenite::Message;
@@ -18,85 +25,487 @@ use tokio_tungstenite::tungstenite::Message;
 ///为了支持send，BinanceFuturesConnection的成员需要支持send ---> MktConnection需要send
 pub struct BinanceConnection {
     base_connection: MktConnection,
-    delay_interval: Duration,
-    ping_interval: Duration,
+    headers: Option<Vec<(String, String)>>, // SBE连接需要的api key header
+    invalid_request: bool,                  // 服务端以 "Invalid request" 关闭了连接，需要定位非法订阅项
+    combined: bool,                         // 组合stream模式：订阅写在URL中，不发送SUBSCRIBE
+    sbe: bool,                              // SBE连接，由manager在启动SBE batch时指定
+    bisect_suspects: Vec<Vec<String>>,      // 上次二分未完成时剩余的可疑订阅组，下次从这里继续
 }
 
 impl BinanceConnection {
-    pub fn new(connection: MktConnection) -> Self {
+    const MAX_PROBES: usize = 32; // 一次二分定位最多发起的探测连接数
+    // 币安每个连接最长24小时，提前建立新连接，重叠期内两个socket同时接收并去重
+    const ROTATE_AFTER: Duration = Duration::from_secs(23 * 3600 + 30 * 60);
+    const ROTATE_RETRY: Duration = Duration::from_secs(60); // 新连接建立失败后的重试间隔
+
+    pub fn new(connection: MktConnection, combined: bool, sbe: bool) -> Self {
         Self {
             base_connection: connection,
-            delay_interval: Duration::from_secs(5),
-            ping_interval: Duration::from_secs(180),
+            headers: None,
+            invalid_request: false,
+            // SBE推送为二进制帧，没有组合stream的外层包装
+            combined: combined && !sbe,
+            sbe,
+            bisect_suspects: Vec::new(),
+        }
+    }
+
+    /// 实际连接的URL与建连后发送的订阅消息，组合stream模式下不发送订阅消息
+    fn connect_target(&self, sub_msg: &Value) -> (String, Value) {
+        if self.combined {
+            (
+                binance_combined_stream_url(&self.base_connection.url, sub_msg),
+                Value::Null,
+            )
+        } else {
+            (self.base_connection.url.clone(), sub_msg.clone())
+        }
+    }
+
+    async fn connect(&self, sub_msg: &Value) -> anyhow::Result<WsConnectionResult> {
+        let (url, sub_msg) = self.connect_target(sub_msg);
+        Self::open(
+            &url,
+            &sub_msg,
+            &self.base_connection.connection_name,
+            self.headers.as_deref(),
+            &self.base_connection.connect_options,
+        )
+        .await
+    }
+
+    async fn open(
+        url: &str,
+        sub_msg: &Value,
+        connection_name: &str,
+        headers: Option<&[(String, String)]>,
+        options: &ConnectOptions,
+    ) -> anyhow::Result<WsConnectionResult> {
+        match headers {
+            Some(header_pairs) => {
+                WsConnector::connect_with_headers(
+                    url,
+                    sub_msg,
+                    connection_name,
+                    header_pairs,
+                    options,
+                )
+                .await
+            }
+            None => WsConnector::connect(url, sub_msg, connection_name, options).await,
         }
     }
+
+    /// 后台建立替换连接，不阻塞旧socket的读取
+    fn spawn_replacement(&self) -> JoinHandle<anyhow::Result<WsConnectionResult>> {
+        let (url, sub_msg) = self.connect_target(&self.base_connection.sub_msg);
+        let connection_name = self.base_connection.connection_name.clone();
+        let headers = self.headers.clone();
+        let options = self.base_connection.connect_options.clone();
+        tokio::spawn(async move {
+            Self::open(&url, &sub_msg, &connection_name, headers.as_deref(), &options).await
+        })
+    }
+
+    fn sub_params(&self) -> Vec<String> {
+        self.base_connection.sub_msg["params"]
+            .as_array()
+            .map(|params| {
+                params
+                    .iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn set_sub_params(&mut self, params: Vec<String>) {
+        self.base_connection.sub_msg["params"] = serde_json::json!(params);
+    }
+
+    /// 从订阅消息中剔除已被隔离的订阅项，隔离列表保存在registry中，计划重启后仍然生效
+    fn strip_quarantined(&mut self) {
+        let params = self.sub_params();
+        let registry = &self.base_connection.registry;
+        let (kept, removed): (Vec<String>, Vec<String>) =
+            params.into_iter().partition(|p| !registry.is_quarantined(p));
+        if !removed.is_empty() {
+            warn!(
+                "[{}] Skipping quarantined streams: {:?}",
+                self.base_connection.connection_name, removed
+            );
+            self.set_sub_params(kept);
+        }
+    }
+
+    /// 用单独的连接订阅一组stream，返回服务端是否接受
+    async fn probe_params(&self, params: &[String]) -> anyhow::Result<bool> {
+        let sub_msg = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": params,
+            "id": 1,
+        });
+        // 探测总是使用SUBSCRIBE，依靠回执判断订阅项是否合法
+        let WsConnectionResult {
+            mut reader, writer, ..
+        } = Self::open(
+            &self.base_connection.url,
+            &sub_msg,
+            &self.base_connection.connection_name,
+            self.headers.as_deref(),
+            &self.base_connection.connect_options,
+        )
+        .await?;
+        let deadline = Instant::now() + SUB_ACK_TIMEOUT;
+        let accepted = loop {
+            let msg = match time::timeout_at(deadline, reader.try_next()).await {
+                Ok(msg) => msg?,
+                Err(_) => return Err(anyhow::anyhow!("probe timed out waiting for ack")),
+            };
+            match msg {
+                Some(Message::Text(text)) => {
+                    if text.contains("\"error\"") {
+                        break false;
+                    }
+                    if text.contains("\"result\"") {
+                        break true;
+                    }
+                }
+                Some(Message::Ping(payload)) => {
+                    writer.send(Message::Pong(payload))?;
+                }
+                Some(Message::Close(frame)) => {
+                    if frame.as_ref().map(|f| f.reason == "Invalid request").unwrap_or(false) {
+                        return Ok(false);
+                    }
+                    return Err(anyhow::anyhow!("probe connection closed: {:?}", frame));
+                }
+                Some(_) => {}
+                None => return Err(anyhow::anyhow!("probe connection closed by server")),
+            }
+        };
+        writer.close();
+        Ok(accepted)
+    }
+
+    /// 二分当前订阅项，找出导致 "Invalid request" 的stream并隔离
+    /// 探测次数用尽或中断时，先隔离已经定位到的stream，剩余的可疑组留到下次继续
+    async fn quarantine_invalid_params(&mut self) {
+        let params = self.sub_params();
+        // 只沿用组内订阅项都还在订阅中的可疑组，其余的状态已经未知
+        let mut suspects: Vec<Vec<String>> = std::mem::take(&mut self.bisect_suspects)
+            .into_iter()
+            .filter(|group| group.iter().all(|p| params.contains(p)))
+            .collect();
+        if suspects.is_empty() {
+            info!(
+                "[{}] Bisecting {} streams to locate invalid subscription",
+                self.base_connection.connection_name,
+                params.len()
+            );
+            suspects.push(params);
+        } else {
+            info!(
+                "[{}] Resuming bisect from {} suspect groups",
+                self.base_connection.connection_name,
+                suspects.len()
+            );
+        }
+        let mut invalid = Vec::new();
+        let mut probes = 0;
+        'bisect: while let Some(group) = suspects.pop() {
+            if group.len() == 1 {
+                invalid.extend(group);
+                continue;
+            }
+            let (left, right) = group.split_at(group.len() / 2);
+            let mut failed = Vec::new();
+            for (index, half) in [left, right].into_iter().enumerate() {
+                let aborted = if probes >= Self::MAX_PROBES
+                    || *self.base_connection.shutdown_rx.borrow()
+                {
+                    warn!(
+                        "[{}] Bisect aborted after {} probes",
+                        self.base_connection.connection_name, probes
+                    );
+                    true
+                } else {
+                    probes += 1;
+                    match self.probe_params(half).await {
+                        Ok(true) => false,
+                        Ok(false) => {
+                            failed.push(half.to_vec());
+                            false
+                        }
+                        Err(e) => {
+                            warn!(
+                                "[{}] Bisect aborted, probe failed: {}",
+                                self.base_connection.connection_name, e
+                            );
+                            true
+                        }
+                    }
+                };
+                if aborted {
+                    // 左半合法时右半一定包含非法项，否则整组保留
+                    if index == 1 && failed.is_empty() {
+                        suspects.push(right.to_vec());
+                    } else {
+                        suspects.push(group.clone());
+                    }
+                    break 'bisect;
+                }
+            }
+            suspects.extend(failed);
+        }
+
+        if !suspects.is_empty() {
+            warn!(
+                "[{}] {} suspect groups left unresolved, resuming on the next Invalid request",
+                self.base_connection.connection_name,
+                suspects.len()
+            );
+            self.bisect_suspects = suspects;
+        }
+        if invalid.is_empty() {
+            warn!(
+                "[{}] Bisect found no invalid stream after {} probes",
+                self.base_connection.connection_name, probes
+            );
+            return;
+        }
+        for param in &invalid {
+            error!(
+                "[{}] Quarantined stream {} (Invalid request)",
+                self.base_connection.connection_name, param
+            );
+            self.base_connection
+                .registry
+                .quarantine(param, "Invalid request");
+        }
+        self.strip_quarantined();
+    }
 }
 
 #[async_trait]
 impl MktConnectionRunner for BinanceConnection {
     async fn run_connection(&mut self) -> anyhow::Result<()> {
-        let mut ping_send_timer =
-            Instant::now() + Duration::from_secs(180) + Duration::from_secs(5);
+        // 币安默认由服务端每3min发送ping，超过 interval + grace 没收到ping则重连
+        let mut heartbeat = Heartbeat::new(self.base_connection.heartbeat);
+        let WsConnectionResult {
+            mut reader,
+            mut writer,
+            connected_at,
+            sub_parts,
+        } = self
+            .base_connection
+            .connection
+            .take()
+            .expect("run_connection called without connection");
+        let mut sub_ack = SubAckTracker::new(AckFormat::Binance, &sub_parts);
+        // 24小时换连接：先建新连接，新连接开始推送后再关闭旧连接
+        let mut rotate_at = connected_at + Self::ROTATE_AFTER;
+        let mut pending_replacement: Option<JoinHandle<anyhow::Result<WsConnectionResult>>> = None;
+        let mut replacement_sub_msg = Value::Null; // 替换连接建立时使用的订阅消息
+        let mut replacement: Option<ReplacementLeg> = None;
+        let mut dedup = FrameDedup::new(binance_frame_key, ReplacementLeg::DEDUP_CAPACITY);
+        let health = self.base_connection.health.clone();
         loop {
-            let mut ws_stream = self
-                .base_connection
-                .connection
-                .as_mut()
-                .unwrap()
-                .ws_stream
-                .lock()
-                .await;
+            // ====切换到新连接====
+            if replacement.as_ref().is_some_and(ReplacementLeg::handover_now) {
+                let next = replacement.take().unwrap();
+                let connected_at = next.take_over(&mut reader, &mut writer, &mut sub_ack, &mut dedup, &self.base_connection.connection_name);
+                rotate_at = connected_at + Self::ROTATE_AFTER;
+                heartbeat = Heartbeat::new(self.base_connection.heartbeat);
+            }
+
+            let replacement_deadline = replacement.as_ref().map(ReplacementLeg::deadline);
             tokio::select! {
                 // ===== 优先处理关闭信号 =====
                 _ = self.base_connection.shutdown_rx.changed() => {
                     let should_close = *self.base_connection.shutdown_rx.borrow();
                     if should_close {
-                        ws_stream.close(None).await?; // 发送 CLOSE 帧
+                        writer.close(); // 发送 CLOSE 帧
                         return Ok(());
                     }
                 }
-                // ====处理ping超时====
-                _ = time::sleep_until(ping_send_timer) => {
-                    warn!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
-                    ws_stream.close(None).await?; // 发送 CLOSE 帧
+                // ====处理心跳超时====
+                _ = time::sleep_until(heartbeat.deadline()) => {
+                    match heartbeat.on_timeout() {
+                        HeartbeatAction::Reconnect => {
+                            warn!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
+                            writer.close(); // 发送 CLOSE 帧
+                            health.record_close("ping timeout");
+                            break;
+                        }
+                        HeartbeatAction::SendPing => {
+                            // 币安允许客户端主动ping，服务端会回复pong frame
+                            if let Err(e) = writer.send(Message::Ping(Vec::new())) {
+                                error!("Failed to send ping message: {:?}", e);
+                                break;
+                            }
+                            info!("[{}] Sent ping message, next deadline {:?}", self.base_connection.connection_name, heartbeat.deadline());
+                        }
+                    }
+                }
+                // ====到期前建立替换连接====
+                _ = time::sleep_until(rotate_at), if pending_replacement.is_none() && replacement.is_none() => {
+                    info!("[{}] Connection approaching 24h limit, opening replacement", self.base_connection.connection_name);
+                    pending_replacement = Some(self.spawn_replacement());
+                    replacement_sub_msg = self.base_connection.sub_msg.clone();
+                    rotate_at = Instant::now() + Self::ROTATE_RETRY;
+                }
+                result = async { pending_replacement.as_mut().unwrap().await }, if pending_replacement.is_some() => {
+                    pending_replacement = None;
+                    match result {
+                        Ok(Ok(connection)) if replacement_sub_msg != self.base_connection.sub_msg => {
+                            // 建立期间订阅发生了变化，丢弃后立即重建
+                            warn!("[{}] Subscriptions changed while opening replacement, retrying", self.base_connection.connection_name);
+                            connection.writer.close();
+                            rotate_at = Instant::now();
+                        }
+                        Ok(Ok(connection)) => {
+                            // 新连接上的订阅以当前订阅消息为准
+                            replacement = Some(ReplacementLeg::start(connection, AckFormat::Binance, &mut dedup, &self.base_connection.connection_name));
+                        }
+                        Ok(Err(e)) => {
+                            warn!("[{}] Failed to open replacement connection: {:?}", self.base_connection.connection_name, e);
+                        }
+                        Err(e) => {
+                            warn!("[{}] Replacement task failed: {:?}", self.base_connection.connection_name, e);
+                        }
+                    }
+                }
+                _ = time::sleep_until(replacement_deadline.unwrap_or(rotate_at)), if replacement_deadline.is_some() => {
+                    if !replacement.as_mut().unwrap().on_deadline() {
+                        warn!("[{}] Replacement produced no data within {:?}, dropping it", self.base_connection.connection_name, ReplacementLeg::REPLACEMENT_TIMEOUT);
+                        replacement.take().unwrap().abandon(&mut dedup);
+                    }
+                }
+                // ====处理新连接的消息====
+                msg = async { replacement.as_mut().unwrap().try_next().await }, if replacement.is_some() => {
+                    let r = replacement.as_mut().unwrap();
+                    let data = match msg {
+                        Ok(Some(Message::Ping(payload))) => {
+                            let _ = r.send(Message::Pong(payload));
+                            None
+                        }
+                        Ok(Some(Message::Text(text))) => {
+                            if r.sub_ack.is_pending() && handle_ack_frame(&mut r.sub_ack, &text, &self.base_connection.connection_name, &self.base_connection.registry) {
+                                None
+                            } else {
+                                Some(Bytes::from(text.into_bytes()))
+                            }
+                        }
+                        Ok(Some(Message::Binary(data))) => Some(Bytes::from(data)),
+                        Ok(Some(Message::Pong(_))) | Ok(Some(Message::Frame(_))) => None,
+                        other => {
+                            warn!("[{}] Replacement connection lost: {:?}", self.base_connection.connection_name, other);
+                            replacement.take().unwrap().abandon(&mut dedup);
+                            None
+                        }
+                    };
+                    let frame = data.and_then(|bytes| replacement.as_mut().unwrap().on_data(bytes, &mut dedup));
+                    if let Some(frame) = frame {
+                        if let Err(e) = self.base_connection.forward(frame) {
+                            error!("failed to broadcast message: {}", e);
+                            break;
+                        }
+                    }
+                }
+                // ====处理订阅回执超时====
+                _ = time::sleep_until(sub_ack.deadline()), if sub_ack.is_pending() => {
+                    error!("[{}] No subscription ack within {:?}, pending: {:?}. reset connecting...", self.base_connection.connection_name, SUB_ACK_TIMEOUT, sub_ack.pending_args());
+                    self.base_connection.registry.record_ack_timeout(&self.base_connection.connection_name);
+                    writer.close(); // 发送 CLOSE 帧
+                    health.record_close("subscription ack timeout");
+                    break;
+                }
+                // ====处理在线增减订阅====
+                cmd = next_command(&mut self.base_connection.cmd_rx) => {
+                    if let Some(r) = replacement.as_mut() {
+                        r.forward_command(&cmd);
+                    }
+                    if let Err(e) = forward_command(&writer, cmd, &mut self.base_connection.sub_msg, &mut sub_ack) {
+                        error!("[{}] Failed to send subscription change: {:?}", self.base_connection.connection_name, e);
+                        break;
+                    }
+                }
+                // ====长时间没有数据，由app通知重连====
+                _ = health.reconnect_requested() => {
+                    if health.is_stale() {
+                        warn!("[{}] No data received for too long. reset connecting...", self.base_connection.connection_name);
+                        writer.close(); // 发送 CLOSE 帧
+                        health.record_close("stale: no data");
+                        break;
+                    }
+                }
+                // ====写任务退出====
+                _ = writer.closed() => {
+                    error!("[{}] WebSocket writer stopped. reset connecting...", self.base_connection.connection_name);
+                    if let Some(r) = replacement.as_mut() {
+                        r.request_handover();
+                        continue;
+                    }
+                    health.record_close("writer stopped");
                     break;
                 }
                 // ====处理ws消息====
-                msg = ws_stream.try_next() => {
+                msg = reader.try_next() => {
                     match msg {
                         Ok(Some(msg)) => {
                             match msg {
                                 Message::Ping(payload) => {
                                     info!("[{}] Sent pong message to server: {:?}", self.base_connection.connection_name, payload);
-                                    if let Err(e) = ws_stream.send(Message::Pong(payload)).await {
+                                    if let Err(e) = writer.send(Message::Pong(payload)) {
                                         error!("Failed to send pong message: {:?}", e);
                                         break;
                                     }
-                                    ping_send_timer = Instant::now() + self.ping_interval + self.delay_interval;
-                                    info!("[{}] Reset ping_send_timer to {:?}", self.base_connection.connection_name, ping_send_timer);
+                                    heartbeat.on_server_ping();
+                                    info!("[{}] Reset heartbeat deadline to {:?}", self.base_connection.connection_name, heartbeat.deadline());
+                                }
+                                Message::Pong(_) => {
+                                    heartbeat.on_pong();
                                 }
                                 Message::Close(frame) => {
                                     warn!("[{}] Received close frame: {:?}", self.base_connection.connection_name, frame);
                                     if let Some(close_frame) = &frame {
                                         if close_frame.reason == "Invalid request" {
-                                            error!("[{}] Received Invalid request close frame!", self.base_connection.connection_name);
-                                            error!("Subscription message was: {}", self.base_connection.sub_msg);
-                                            std::process::exit(1);
+                                            // 只影响本batch，由start_ws二分定位非法订阅项后重连
+                                            error!("[{}] Received Invalid request close frame, subscription message was: {}", self.base_connection.connection_name, self.base_connection.sub_msg);
+                                            self.invalid_request = true;
                                         }
                                     }
+                                    if let Some(r) = replacement.as_mut().filter(|_| !self.invalid_request) {
+                                        r.request_handover();
+                                        continue;
+                                    }
+                                    health.record_close(format!("close frame: {:?}", frame));
                                     break;
                                 }
                                 Message::Text(text) => {
+                                    heartbeat.on_message();
+                                    // 订阅回执不转发给parser
+                                    if sub_ack.is_pending() && handle_ack_frame(&mut sub_ack, &text, &self.base_connection.connection_name, &self.base_connection.registry) {
+                                        continue;
+                                    }
                                     let bytes = Bytes::from(text.into_bytes());
-                                    if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                    if dedup.admit(&bytes) == FrameVerdict::Duplicate {
+                                        continue;
+                                    }
+                                    if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                         //利用shutdown关闭
                                         error!("failed to broadcast message: {}", e);
                                         break;
                                     }
                                 }
                                 Message::Binary(data) => {
+                                    heartbeat.on_message();
                                     let bytes = Bytes::from(data);
-                                    if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                    if dedup.admit(&bytes) == FrameVerdict::Duplicate {
+                                        continue;
+                                    }
+                                    if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                         error!("failed to broadcast message: {}", e);
                                         break;
                                     }
@@ -108,16 +517,33 @@ impl MktConnectionRunner for BinanceConnection {
                         }
                         Err(e) => {
                             error!("[{}] WebSocket error: {:?}", self.base_connection.connection_name, e);
+                            // 换连接期间旧socket断开，直接由新socket接替
+                            if let Some(r) = replacement.as_mut() {
+                                r.request_handover();
+                                continue;
+                            }
+                            health.on_read_error(&e);
                             break;
                         }
                         Ok(None) => {
                             warn!("[{}] WebSocket connection closed by server", self.base_connection.connection_name);
+                            if let Some(r) = replacement.as_mut() {
+                                r.request_handover();
+                                continue;
+                            }
+                            health.record_close("closed by server");
                             break;
                         }
                     }
                 }
             }
         }
+        if let Some(handle) = pending_replacement {
+            handle.abort();
+        }
+        if let Some(r) = replacement {
+            r.close();
+        }
         return Ok(());
     }
 }
@@ -125,10 +551,7 @@ impl MktConnectionRunner for BinanceConnection {
 #[async_trait]
 impl MktConnectionHandler for BinanceConnection {
     async fn start_ws(&mut self) -> anyhow::Result<()> {
-        let use_sbe = self
-            .base_connection
-            .url
-            .contains("stream-sbe.binance.com");
+        let use_sbe = self.sbe;
         let api_key = if use_sbe {
             std::env::var("BINANCE_SBE_API_KEY")
                 .or_else(|_| std::env::var("BINANCE_API_KEY"))
@@ -141,41 +564,43 @@ impl MktConnectionHandler for BinanceConnection {
                 "BINANCE_SBE_API_KEY or BINANCE_API_KEY not set for SBE connection"
             ));
         }
-        let headers = api_key.map(|key| vec![("X-MBX-APIKEY".to_string(), key)]);
+        self.headers = api_key.map(|key| vec![("X-MBX-APIKEY".to_string(), key)]);
 
         loop {
-            let connect_result = if let Some(ref header_pairs) = headers {
-                WsConnector::connect_with_headers(
-                    &self.base_connection.url,
-                    &self.base_connection.sub_msg,
-                    &self.base_connection.connection_name,
-                    header_pairs,
-                )
-                .await
-            } else {
-                WsConnector::connect(
-                    &self.base_connection.url,
-                    &self.base_connection.sub_msg,
-                    &self.base_connection.connection_name,
-                )
-                .await
-            };
+            self.strip_quarantined();
+            if self.sub_params().is_empty() {
+                error!(
+                    "[{}] All streams quarantined, connection stopped",
+                    self.base_connection.connection_name
+                );
+                break Ok(());
+            }
+            let connect_result = self.connect(&self.base_connection.sub_msg).await;
             match connect_result {
                 Ok(connection) => {
                     info!(
                         "[{}] successfully connected at {:?}",
                         self.base_connection.connection_name, connection.connected_at
                     );
+                    let connected_at = connection.connected_at;
                     self.base_connection.connection = Some(connection);
+                    self.base_connection.on_connected();
                     self.run_connection().await?;
                     //检查shutdown的当前情况，如果是true则break
                     if *self.base_connection.shutdown_rx.borrow() {
                         break Ok(());
                     } else {
+                        if self.invalid_request {
+                            self.invalid_request = false;
+                            self.quarantine_invalid_params().await;
+                        }
                         info!(
                             "[{}] Connection closed, reconnecting...",
                             self.base_connection.connection_name
                         );
+                        if !self.base_connection.on_disconnected(connected_at).await {
+                            break Ok(());
+                        }
                     }
                 }
                 Err(e) => {
@@ -183,7 +608,9 @@ impl MktConnectionHandler for BinanceConnection {
                         "[{}] Failed to connect: {:?}",
                         self.base_connection.connection_name, e
                     );
-                    time::sleep(Duration::from_secs(5)).await;
+                    if !self.base_connection.on_connect_failed(e.to_string()).await {
+                        break Ok(());
+                    }
                 }
             }
         }
@@ -313,15 +740,16 @@ impl BinanceFuturesSnapshotQuery {
         rest_cfg: BinanceRestCfg,
         symbols: Vec<String>,
         tx: tokio::sync::broadcast::Sender<Bytes>,
+        options: ConnectOptions,
     ) {
         if exchange == "binance-spot" && !Self::BINANCE_SPOT_SNAPSHOT_ENABLED {
             log::info!("Skip depth snapshot for binance-spot (temporary disabled)");
             return;
         }
 
-        let client = Client::builder()
-            .timeout(Self::REQUEST_TIMEOUT)
-            .build()
+        let client = options
+            .apply_to_client(Client::builder().timeout(Self::REQUEST_TIMEOUT))
+            .and_then(|builder| builder.build())
             .expect("Failed to create HTTP client");
 
         // 创建一个HashSet来跟踪无效的符号
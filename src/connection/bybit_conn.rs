@@ -1,12 +1,14 @@
 use crate::connection::connection::{
-    MktConnection, MktConnectionHandler, MktConnectionRunner, WsConnector,
+    MktConnection, MktConnectionHandler, MktConnectionRunner, WsConnectionResult, WsConnector,
 };
+use crate::connection::heartbeat::{Heartbeat, HeartbeatAction};
+use crate::connection::live_sub::{forward_command, next_command};
+use crate::connection::sub_ack::{handle_ack_frame, AckFormat, SubAckTracker, SUB_ACK_TIMEOUT};
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures_util::{SinkExt, TryStreamExt};
 use log::{error, info, warn};
 use serde_json::json;
-use tokio::time::{self, Duration, Instant};
+use tokio::time;
 use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
@@ -52,60 +54,91 @@ impl MktConnectionRunner for BybitConnection {
     async fn run_connection(&mut self) -> anyhow::Result<()> {
         //bybit依赖于客户主动发送心跳
         //需要考虑的事件是
-        //1、心跳发送计时，间隔20s，发送一次心跳
-        //2、心跳发送后，等待pong消息，如果20s内没有收到pong消息，则重启websocket
-        //注意bybit文档并未给出这个超时时间，因此设置为20s，和发送频率保持一致
-        // 把问题转化为，必须稳定的收到pong 否则断开
-        let mut ping_timer: Instant = Instant::now() + Duration::from_secs(20); // 心跳计时，初始值20s，倒计时结束发ping消息
-        let mut reset_timer: Instant = ping_timer + Duration::from_secs(5); // 倒计时，初始值40s，倒计时结束重启websocket
-        let mut waiting_pong = false;
+        //1、心跳发送计时，默认间隔20s，发送一次心跳
+        //2、心跳发送后，等待pong消息，如果pong_timeout内没有收到pong消息，则重启websocket
+        //注意bybit文档并未给出这个超时时间，默认设置为5s
+        // 把问题转化为，必须稳定的收到pong 否则断开，计时统一由 Heartbeat 维护
+        let mut heartbeat = Heartbeat::new(self.base_connection.heartbeat);
+        let WsConnectionResult {
+            mut reader,
+            writer,
+            sub_parts,
+            ..
+        } = self
+            .base_connection
+            .connection
+            .take()
+            .expect("run_connection called without connection");
+        let mut sub_ack = SubAckTracker::new(AckFormat::Bybit, &sub_parts);
+        let health = self.base_connection.health.clone();
         loop {
-            let mut ws_stream = self
-                .base_connection
-                .connection
-                .as_mut()
-                .unwrap()
-                .ws_stream
-                .lock()
-                .await;
             tokio::select! {
                 // ===== 优先处理关闭信号 =====
                 _ = self.base_connection.shutdown_rx.changed() => {
                     let should_close = *self.base_connection.shutdown_rx.borrow();
                     if should_close {
-                        ws_stream.close(None).await?; // 发送 CLOSE 帧
+                        writer.close(); // 发送 CLOSE 帧
                         return Ok(());
                     }
                 }
                 // ====处理心跳====
-                _ = time::sleep_until(ping_timer) => {
-                    // 发送心跳
-                    //ws.send(JSON.stringify({"req_id": "100001", "op": "ping"}));
-                    // 生成心跳消息
-                    // 生成一个当前时间戳作为req_id, tokio的uuid
-                    let req_id = Uuid::new_v4().to_string();
-                    let ping_msg = json!({
-                        "req_id": req_id,
-                        "op": "ping"
-                    });
-                    if let Err(e) = ws_stream.send(Message::Text(ping_msg.to_string())).await {
-                        error!("Failed to send ping message: {:?}", e);
+                _ = time::sleep_until(heartbeat.deadline()) => {
+                    match heartbeat.on_timeout() {
+                        HeartbeatAction::Reconnect => {
+                            // 到期没有收到pong消息，则重启websocket
+                            log::error!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
+                            writer.close(); // 发送 CLOSE 帧
+                            health.record_close("ping timeout");
+                            break;
+                        }
+                        HeartbeatAction::SendPing => {
+                            //ws.send(JSON.stringify({"req_id": "100001", "op": "ping"}));
+                            // 生成一个uuid作为req_id
+                            let req_id = Uuid::new_v4().to_string();
+                            let ping_msg = json!({
+                                "req_id": req_id,
+                                "op": "ping"
+                            });
+                            if let Err(e) = writer.send(Message::Text(ping_msg.to_string())) {
+                                error!("Failed to send ping message: {:?}", e);
+                                break;
+                            }
+                            log::info!("[{}] Sent ping message with req_id: {:?}, next heartbeat deadline {:?}", self.base_connection.connection_name, req_id, heartbeat.deadline());
+                        }
+                    }
+                }
+                // ====处理订阅回执超时====
+                _ = time::sleep_until(sub_ack.deadline()), if sub_ack.is_pending() => {
+                    error!("[{}] No subscription ack within {:?}, pending: {:?}. reset connecting...", self.base_connection.connection_name, SUB_ACK_TIMEOUT, sub_ack.pending_args());
+                    self.base_connection.registry.record_ack_timeout(&self.base_connection.connection_name);
+                    writer.close(); // 发送 CLOSE 帧
+                    health.record_close("subscription ack timeout");
+                    break;
+                }
+                // ====处理在线增减订阅====
+                cmd = next_command(&mut self.base_connection.cmd_rx) => {
+                    if let Err(e) = forward_command(&writer, cmd, &mut self.base_connection.sub_msg, &mut sub_ack) {
+                        error!("[{}] Failed to send subscription change: {:?}", self.base_connection.connection_name, e);
                         break;
                     }
-                    waiting_pong = true;
-                    // 重置心跳计时
-                    ping_timer = Instant::now() + Duration::from_secs(20);
-                    log::info!("[{}] Sent ping message with req_id: {:?}, reset ping timer to {:?}", self.base_connection.connection_name, req_id, ping_timer);
                 }
-                // ====处理超时====
-                _ = time::sleep_until(reset_timer) => {
-                    // 到期没有收到pong消息，则重启websocket
-                    log::error!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
-                    ws_stream.close(None).await?; // 发送 CLOSE 帧
+                // ====长时间没有数据，由app通知重连====
+                _ = health.reconnect_requested() => {
+                    if health.is_stale() {
+                        warn!("[{}] No data received for too long. reset connecting...", self.base_connection.connection_name);
+                        writer.close(); // 发送 CLOSE 帧
+                        health.record_close("stale: no data");
+                        break;
+                    }
+                }
+                // ====写任务退出====
+                _ = writer.closed() => {
+                    error!("[{}] WebSocket writer stopped. reset connecting...", self.base_connection.connection_name);
+                    health.record_close("writer stopped");
                     break;
                 }
                 // ====处理ws消息====
-                msg = ws_stream.try_next() => {
+                msg = reader.try_next() => {
                     match msg {
                         Ok(Some(msg)) => {
                             match msg {
@@ -115,6 +148,7 @@ impl MktConnectionRunner for BybitConnection {
                                 }
                                 Message::Close(frame) => {
                                     warn!("Received close frame: {:?}", frame);
+                                    health.record_close(format!("close frame: {:?}", frame));
                                     break;
                                 }
                                 Message::Pong(payload) => {
@@ -122,18 +156,23 @@ impl MktConnectionRunner for BybitConnection {
                                     warn!("Unexpected pong message: {:?}", payload);
                                 }
                                 Message::Text(text) => {
-                                    if waiting_pong {
+                                    heartbeat.on_message();
+                                    // 订阅回执不转发给parser
+                                    if sub_ack.is_pending() && handle_ack_frame(&mut sub_ack, &text, &self.base_connection.connection_name, &self.base_connection.registry) {
+                                        continue;
+                                    }
+                                    if heartbeat.is_waiting_pong() {
                                         // 只有在等待pong消息时，需要parser text，检查是否是pong消息
-                                        let msg: serde_json::Value = serde_json::from_slice(&text.as_bytes()).unwrap();
+                                        let msg: serde_json::Value = serde_json::from_slice(text.as_bytes()).unwrap_or_default();
                                         if is_bybit_pong_msg(&msg) {
                                             log::info!("[{}] Received pong message: {:?}", self.base_connection.connection_name, msg);
-                                            waiting_pong = false;
-                                            let req_id = msg["req_id"].as_str().unwrap();
-                                            reset_timer = ping_timer + Duration::from_secs(5);
-                                            log::info!("[{}] Received pong message with req_id: {:?}, reset reset timer to {:?}", self.base_connection.connection_name, req_id, reset_timer);
+                                            heartbeat.on_pong();
+                                            let req_id = msg["req_id"].as_str().unwrap_or_default();
+                                            log::info!("[{}] Received pong message with req_id: {:?}, next heartbeat deadline {:?}", self.base_connection.connection_name, req_id, heartbeat.deadline());
                                             //检查pong消息是否是suceess，如果不是，则断开连接
-                                            if !msg["success"].as_bool().unwrap() {
+                                            if !msg["success"].as_bool().unwrap_or(false) {
                                                 error!("[{}] Pong message is not success: {:?}", self.base_connection.connection_name, msg);
+                                                health.record_close("pong not success");
                                                 break;
                                             }
                                             continue;
@@ -142,15 +181,16 @@ impl MktConnectionRunner for BybitConnection {
                                     // 1、非等待pong消息，直接广播
                                     // 2、等待pong消息时，如果is_bybit_pong_msg为false，不会走到continue，而是走到这里，直接广播
                                     let bytes = Bytes::from(text.into_bytes());
-                                    if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                    if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                         //利用shutdown关闭
                                         error!("failed to broadcast message: {}", e);
                                         break;
                                     }
                                 }
                                 Message::Binary(data) => {
+                                    heartbeat.on_message();
                                     let bytes = Bytes::from(data);
-                                    if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                    if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                         error!("failed to broadcast message: {}", e);
                                         break;
                                     }
@@ -162,10 +202,12 @@ impl MktConnectionRunner for BybitConnection {
                         }
                         Err(e) => {
                             error!("[{}] WebSocket error: {:?}", self.base_connection.connection_name, e);
+                            health.on_read_error(&e);
                             break;
                         }
                         Ok(None) => {
                             warn!("[{}] WebSocket connection closed by server", self.base_connection.connection_name);
+                            health.record_close("closed by server");
                             break;
                         }
                     }
@@ -184,6 +226,7 @@ impl MktConnectionHandler for BybitConnection {
                 &self.base_connection.url,
                 &self.base_connection.sub_msg,
                 &self.base_connection.connection_name,
+                &self.base_connection.connect_options,
             )
             .await
             {
@@ -192,7 +235,9 @@ impl MktConnectionHandler for BybitConnection {
                         "[{}] Successfully connected at {:?}",
                         self.base_connection.connection_name, connection.connected_at
                     );
+                    let connected_at = connection.connected_at;
                     self.base_connection.connection = Some(connection);
+                    self.base_connection.on_connected();
                     self.run_connection().await?;
                     //检查shutdown的当前情况，如果是true则break
                     if *self.base_connection.shutdown_rx.borrow() {
@@ -202,6 +247,9 @@ impl MktConnectionHandler for BybitConnection {
                             "[{}] Connection closed, reconnecting...",
                             self.base_connection.connection_name
                         );
+                        if !self.base_connection.on_disconnected(connected_at).await {
+                            break Ok(());
+                        }
                     }
                 }
                 Err(e) => {
@@ -209,7 +257,9 @@ impl MktConnectionHandler for BybitConnection {
                         "[{}] Failed to connect: {:?}",
                         self.base_connection.connection_name, e
                     );
-                    time::sleep(Duration::from_secs(5)).await;
+                    if !self.base_connection.on_connect_failed(e.to_string()).await {
+                        break Ok(());
+                    }
                 }
             }
         }
@@ -1,28 +1,317 @@
+use crate::cfg::Config;
+use crate::connection::backoff::{Backoff, BreakerState, ReconnectPolicy};
+use crate::connection::dedup::{FrameDedup, FrameVerdict};
+use crate::connection::health::ConnectionHealth;
+use crate::connection::heartbeat::HeartbeatPolicy;
+use crate::connection::live_sub::{SubCommand, SubCommandReceiver};
+use crate::connection::rate_limit::{split_op_message, MessageLimiter, Throttle};
+use crate::connection::registry::ConnectionRegistry;
+use crate::connection::rx_timestamp::{RawFrame, RecvClock};
+use crate::connection::sub_ack::{stamp_request_id, AckFormat, SubAckTracker};
+use crate::connection::tls::WsTransport;
+use crate::connection::transport::ConnectOptions;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures_util::SinkExt;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt, TryStreamExt};
 use log::{error, info, warn};
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::sync::{broadcast, watch, Mutex};
-use tokio::{
-    net::TcpStream,
-    time::{self, Duration, Instant},
-};
+use tokio::sync::mpsc::{self, error::SendError};
+use tokio::sync::{broadcast, watch};
+use tokio::time::{self, Duration, Instant};
 use tokio_tungstenite::{
-    connect_async,
+    client_async_with_config,
     tungstenite::{
         client::IntoClientRequest,
+        error::UrlError,
+        handshake::client::Request,
         http::{HeaderName, HeaderValue},
         Message,
     },
-    MaybeTlsStream, WebSocketStream,
+    WebSocketStream,
 };
 use url::Url;
 
+pub type WsStream = WebSocketStream<WsTransport>;
+
+/// websocket读端，同时提供底层socket最近一次读到数据的内核接收时间
+pub struct WsReader {
+    stream: SplitStream<WsStream>,
+    recv_clock: RecvClock,
+}
+
+impl WsReader {
+    pub async fn try_next(
+        &mut self,
+    ) -> Result<Option<Message>, tokio_tungstenite::tungstenite::Error> {
+        self.stream.try_next().await
+    }
+
+    /// 刚读出的帧的内核接收时间（纳秒），未开启内核时间戳时为0
+    pub fn recv_ts(&self) -> i64 {
+        self.recv_clock.load()
+    }
+
+    /// 读到的帧打上接收时间，转发给parser
+    pub fn frame(&self, data: Bytes) -> RawFrame {
+        RawFrame::new(data, self.recv_ts())
+    }
+}
+
+/// websocket写端，pong/ping/订阅消息通过通道交给独立的写任务发送
+/// 读循环直接持有读端，不再需要每条消息加锁，读的同时也可以发送订阅变更
+/// 写任务按交易所的消息频率限制发送，心跳与控制帧优先于排队的订阅消息
+#[derive(Debug, Clone)]
+pub struct WsWriter {
+    tx: mpsc::UnboundedSender<Message>,
+    max_args: Option<usize>, // 单条订阅消息的args上限
+}
+
+impl WsWriter {
+    fn spawn(
+        mut sink: SplitSink<WsStream, Message>,
+        connection_name: String,
+        mut limiter: MessageLimiter,
+        max_args: Option<usize>,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        tokio::spawn(async move {
+            // 只有订阅/取消订阅排队等待限频，心跳与关闭帧不被积压的订阅消息阻塞
+            let mut control: VecDeque<Message> = VecDeque::new();
+            let mut ops: VecDeque<Message> = VecDeque::new();
+            let mut ready_at: Option<Instant> = None; // 下一条限频消息的发送时间点
+            loop {
+                let msg = tokio::select! {
+                    biased;
+                    msg = rx.recv() => {
+                        let Some(msg) = msg else { break };
+                        match limiter.classify(&msg) {
+                            Throttle::Immediate => msg,
+                            throttle => {
+                                if throttle == Throttle::Control {
+                                    control.push_back(msg);
+                                } else {
+                                    ops.push_back(msg);
+                                }
+                                if ready_at.is_none() {
+                                    ready_at = Some(limiter.reserve());
+                                }
+                                continue;
+                            }
+                        }
+                    }
+                    _ = time::sleep_until(ready_at.unwrap_or_else(Instant::now)), if ready_at.is_some() => {
+                        let Some(msg) = control.pop_front().or_else(|| ops.pop_front()) else {
+                            ready_at = None;
+                            continue;
+                        };
+                        ready_at = if control.is_empty() && ops.is_empty() {
+                            None
+                        } else {
+                            Some(limiter.reserve())
+                        };
+                        msg
+                    }
+                };
+                let is_close = matches!(msg, Message::Close(_));
+                if let Err(e) = sink.send(msg).await {
+                    warn!("[{}] WebSocket write failed: {:?}", connection_name, e);
+                    break;
+                }
+                if is_close {
+                    break;
+                }
+            }
+        });
+        Self { tx, max_args }
+    }
+
+    /// 写任务退出（写失败或已关闭）时返回错误
+    pub fn send(&self, msg: Message) -> Result<(), SendError<Message>> {
+        self.tx.send(msg)
+    }
+
+    /// 发送订阅/取消订阅消息，超过args上限时拆分为多条，每条分配请求id
+    /// 返回实际发送的消息，用于匹配订阅回执
+    pub fn send_op(
+        &self,
+        msg: &serde_json::Value,
+    ) -> Result<Vec<serde_json::Value>, SendError<Message>> {
+        let mut parts = split_op_message(msg, self.max_args);
+        for part in parts.iter_mut() {
+            stamp_request_id(part);
+            self.tx.send(Message::Text(part.to_string()))?;
+        }
+        Ok(parts)
+    }
+
+    /// 发送 CLOSE 帧，之后写任务退出
+    pub fn close(&self) {
+        let _ = self.tx.send(Message::Close(None));
+    }
+
+    /// 写任务退出后返回，读循环据此判断连接已不可写
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+}
+
 pub struct WsConnectionResult {
-    pub ws_stream: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+    pub reader: WsReader,
+    pub writer: WsWriter,
     pub connected_at: Instant,
+    pub sub_parts: Vec<serde_json::Value>, // 建连时实际发送的订阅消息（拆分后、带请求id），用于匹配回执
+}
+
+impl WsConnectionResult {
+    fn new(
+        ws_stream: WsStream,
+        connection_name: &str,
+        limiter: MessageLimiter,
+        max_args: Option<usize>,
+        sub_parts: Vec<serde_json::Value>,
+    ) -> Self {
+        let recv_clock = ws_stream.get_ref().recv_clock();
+        let (sink, stream) = ws_stream.split();
+        Self {
+            reader: WsReader { stream, recv_clock },
+            writer: WsWriter::spawn(sink, connection_name.to_string(), limiter, max_args),
+            connected_at: Instant::now(),
+            sub_parts,
+        }
+    }
+}
+
+/// 换连接期间的新socket（币安24小时换连接、okex服务升级）
+/// 新socket开始推送数据后与旧socket重叠接收并去重，追上旧socket或重叠超时后接替旧socket
+pub struct ReplacementLeg {
+    reader: WsReader,
+    writer: WsWriter,
+    pub sub_ack: SubAckTracker,
+    connected_at: Instant,
+    started: Instant,
+    handover_at: Option<Instant>, // 收到第一条数据后设置，最长重叠时间
+    handover_now: bool,           // 新socket已追上旧socket，或旧socket已断开
+}
+
+impl ReplacementLeg {
+    pub const REPLACEMENT_TIMEOUT: Duration = Duration::from_secs(30); // 新连接开始推送数据的最长等待
+    pub const MAX_OVERLAP: Duration = Duration::from_secs(5); // 新连接开始推送后最长重叠时间
+    pub const DEDUP_TAIL: Duration = Duration::from_secs(5); // 切换后继续去重的时间
+    pub const DEDUP_CAPACITY: usize = 65536;
+
+    /// 新连接建立完成，开始重叠期
+    pub fn start(
+        connection: WsConnectionResult,
+        ack_format: AckFormat,
+        dedup: &mut FrameDedup,
+        connection_name: &str,
+    ) -> Self {
+        info!(
+            "[{}] Replacement connected at {:?}, waiting for data",
+            connection_name, connection.connected_at
+        );
+        dedup.start();
+        Self {
+            reader: connection.reader,
+            writer: connection.writer,
+            sub_ack: SubAckTracker::new(ack_format, &connection.sub_parts),
+            connected_at: connection.connected_at,
+            started: Instant::now(),
+            handover_at: None,
+            handover_now: false,
+        }
+    }
+
+    pub fn handover_now(&self) -> bool {
+        self.handover_now
+    }
+
+    /// 旧socket已不可用，下一轮直接由新socket接替
+    pub fn request_handover(&mut self) {
+        self.handover_now = true;
+    }
+
+    /// 未推送数据时为建立超时，推送后为最长重叠时间
+    pub fn deadline(&self) -> Instant {
+        self.handover_at.unwrap_or(self.started + Self::REPLACEMENT_TIMEOUT)
+    }
+
+    /// 计时到期：已推送数据时切换，返回false表示超时未推送数据，需要丢弃
+    pub fn on_deadline(&mut self) -> bool {
+        if self.handover_at.is_some() {
+            self.handover_now = true;
+        }
+        self.handover_now
+    }
+
+    pub async fn try_next(
+        &mut self,
+    ) -> Result<Option<Message>, tokio_tungstenite::tungstenite::Error> {
+        self.reader.try_next().await
+    }
+
+    /// 新socket上的控制帧直接回复
+    pub fn send(&self, msg: Message) -> Result<(), SendError<Message>> {
+        self.writer.send(msg)
+    }
+
+    /// 新socket的数据帧去重，返回需要转发的帧；收到旧socket已转发过的帧说明已追上，可以立即切换
+    pub fn on_data(&mut self, data: Bytes, dedup: &mut FrameDedup) -> Option<RawFrame> {
+        if self.handover_at.is_none() {
+            self.handover_at = Some(Instant::now() + Self::MAX_OVERLAP);
+        }
+        match dedup.admit(&data) {
+            FrameVerdict::Forward => Some(self.reader.frame(data)),
+            FrameVerdict::Duplicate => {
+                self.handover_now = true;
+                None
+            }
+        }
+    }
+
+    /// 重叠期内的订阅变更同步发送到新socket
+    pub fn forward_command(&mut self, cmd: &SubCommand) {
+        if let Ok(parts) = self.writer.send_op(&cmd.msg) {
+            if cmd.subscribe {
+                self.sub_ack.expect(&parts);
+            }
+        }
+    }
+
+    /// 丢弃新连接，结束重叠期
+    pub fn abandon(self, dedup: &mut FrameDedup) {
+        self.writer.close();
+        dedup.stop();
+    }
+
+    /// 关闭新连接（整个连接任务退出时）
+    pub fn close(self) {
+        self.writer.close();
+    }
+
+    /// 新socket的读写端与订阅回执替换旧连接并关闭旧socket，返回新连接的建立时间
+    pub fn take_over(
+        self,
+        reader: &mut WsReader,
+        writer: &mut WsWriter,
+        sub_ack: &mut SubAckTracker,
+        dedup: &mut FrameDedup,
+        connection_name: &str,
+    ) -> Instant {
+        info!(
+            "[{}] Handing over to replacement connection established at {:?}",
+            connection_name, self.connected_at
+        );
+        *reader = self.reader;
+        writer.close();
+        *writer = self.writer;
+        *sub_ack = self.sub_ack;
+        dedup.finish_after(Self::DEDUP_TAIL);
+        self.connected_at
+    }
 }
 
 //每个行情订阅连接，包含一个连接，一个发送通道，一个关闭标志
@@ -30,19 +319,30 @@ pub struct MktConnection {
     pub connection_name: String, // 连接名称，如 "binance-futures-inc", "binance-kline" 等
     pub sub_msg: serde_json::Value, // 行情订阅消息
     pub url: String,             // 行情URL
-    pub tx: broadcast::Sender<Bytes>, // 行情消息广播发送端
+    pub tx: broadcast::Sender<RawFrame>, // 行情消息广播发送端
     pub shutdown_rx: watch::Receiver<bool>, // 关闭信号接收端
     pub connection: Option<WsConnectionResult>, // 连接状态
+    pub heartbeat: HeartbeatPolicy, // 心跳策略
+    pub reconnect: ReconnectPolicy, // 重连退避与熔断
+    pub registry: Arc<ConnectionRegistry>, // 连接状态表，熔断状态写入此处供app展示
+    pub cmd_rx: Option<SubCommandReceiver>, // 在线增减订阅的命令通道，signal等固定订阅的连接为None
+    pub connect_options: ConnectOptions, // 出口代理等传输参数
+    pub health: Arc<ConnectionHealth>, // 健康状态，登记在registry中
 }
 
 impl MktConnection {
     /// 创建新的MktConnection实例
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         connection_name: String,
         url: String,
         sub_msg: serde_json::Value,
-        tx: broadcast::Sender<Bytes>,
+        tx: broadcast::Sender<RawFrame>,
         global_shutdown_rx: watch::Receiver<bool>,
+        heartbeat: HeartbeatPolicy,
+        reconnect: ReconnectPolicy,
+        registry: Arc<ConnectionRegistry>,
+        health: Arc<ConnectionHealth>,
     ) -> Self {
         Self {
             connection_name,
@@ -51,9 +351,105 @@ impl MktConnection {
             tx,
             shutdown_rx: global_shutdown_rx,
             connection: None,
+            heartbeat,
+            reconnect,
+            registry,
+            cmd_rx: None,
+            connect_options: ConnectOptions::default(),
+            health,
+        }
+    }
+
+    /// 建连成功后调用，关闭熔断器，退避在连接稳定后才重置
+    pub fn on_connected(&mut self) {
+        self.reconnect.breaker.record_success();
+        self.registry.record_success(&self.connection_name);
+        self.health.on_connected();
+    }
+
+    /// 把一帧数据转发给parser，同时计入健康统计
+    pub fn forward(&self, frame: RawFrame) -> Result<usize, broadcast::error::SendError<RawFrame>> {
+        self.health.on_message(frame.data.len());
+        self.tx.send(frame)
+    }
+
+    /// 连接断开后调用，存活时间过短视为一次失败，需要退避后再重连
+    /// 返回false表示等待期间收到了关闭信号
+    pub async fn on_disconnected(&mut self, connected_at: Instant) -> bool {
+        self.health.on_disconnected();
+        let alive = connected_at.elapsed();
+        if alive >= self.reconnect.stable_after {
+            self.reconnect.backoff.reset();
+            return true;
+        }
+        self.on_connect_failed(format!("connection dropped after {:?}", alive))
+            .await
+    }
+
+    /// 建连失败后调用，记录失败并按退避/熔断状态等待
+    /// 返回false表示等待期间收到了关闭信号
+    pub async fn on_connect_failed(&mut self, error: String) -> bool {
+        let opened = self.reconnect.breaker.record_failure();
+        let breaker = self.reconnect.breaker.state();
+        let failures = self.reconnect.breaker.consecutive_failures();
+        self.registry
+            .record_failure(&self.connection_name, breaker, failures, error);
+
+        let wait_until = match self.reconnect.breaker.open_until() {
+            Some(until) => {
+                if opened {
+                    warn!(
+                        "[{}] Circuit breaker open after {} consecutive failures, pausing reconnect for {:?}",
+                        self.connection_name,
+                        failures,
+                        until.saturating_duration_since(Instant::now())
+                    );
+                }
+                until
+            }
+            None => {
+                let delay = self.reconnect.backoff.next_delay();
+                info!(
+                    "[{}] Reconnecting in {:?} (consecutive failures: {})",
+                    self.connection_name, delay, failures
+                );
+                Instant::now() + delay
+            }
+        };
+
+        if !self.sleep_or_shutdown(wait_until).await {
+            return false;
+        }
+        if self.reconnect.breaker.state() == BreakerState::Open {
+            self.reconnect.breaker.half_open();
+            self.registry
+                .set_breaker(&self.connection_name, BreakerState::HalfOpen);
+            info!(
+                "[{}] Circuit breaker half-open, trying to reconnect",
+                self.connection_name
+            );
+        }
+        true
+    }
+
+    async fn sleep_or_shutdown(&mut self, until: Instant) -> bool {
+        if *self.shutdown_rx.borrow() {
+            return false;
+        }
+        tokio::select! {
+            _ = time::sleep_until(until) => true,
+            _ = self.shutdown_rx.changed() => !*self.shutdown_rx.borrow(),
         }
     }
 }
+
+/// 连接任务退出后健康状态不再展示，计划重启后同名连接继续使用
+impl Drop for MktConnection {
+    fn drop(&mut self) {
+        self.registry.retire_health(&self.connection_name, &self.health);
+    }
+}
+
 pub struct WsConnector;
 
 impl WsConnector {
@@ -84,33 +480,103 @@ impl WsConnector {
     }
 
     const MAX_RETRIES: usize = 5;
-    const RETRY_DELAY: Duration = Duration::from_secs(1);
+    const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+    const RETRY_MAX_DELAY: Duration = Duration::from_secs(8);
+
+    /// 按传输参数建立TCP连接（可能经由代理），再完成TLS与websocket握手
+    async fn open(
+        request: Request,
+        options: &ConnectOptions,
+    ) -> Result<WsStream, tokio_tungstenite::tungstenite::Error> {
+        let host = request
+            .uri()
+            .host()
+            .ok_or(tokio_tungstenite::tungstenite::Error::Url(
+                UrlError::NoHostName,
+            ))?
+            .to_string();
+        let tls = request.uri().scheme_str() == Some("wss");
+        let port = request
+            .uri()
+            .port_u16()
+            .unwrap_or(if tls { 443 } else { 80 });
+        let stream = options.open_stream(&host, port, tls).await?;
+        let (ws_stream, _) =
+            client_async_with_config(request, stream, Some(options.ws_config)).await?;
+        Ok(ws_stream)
+    }
+
+    /// 按交易所的建连频率排队
+    async fn wait_connection_slot(connection_name: &str, options: &ConnectOptions) {
+        let waited = options.rate_limiter.acquire_connection().await;
+        if !waited.is_zero() {
+            info!(
+                "[{}] Waited {:?} for connection rate limit",
+                connection_name, waited
+            );
+        }
+    }
+
+    /// 发送订阅消息（超过args上限时拆分，按消息频率限制发送），再拆分读写端
+    async fn subscribe(
+        mut ws_stream: WsStream,
+        sub_msg: &serde_json::Value,
+        connection_name: &str,
+        options: &ConnectOptions,
+    ) -> anyhow::Result<WsConnectionResult> {
+        let mut limiter = options.rate_limiter.message_limiter();
+        let max_args = options.rate_limiter.limits().max_args;
+        if sub_msg.is_null() {
+            // 订阅已包含在URL中（如币安组合stream），无需发送订阅消息
+            info!("[{}] Connected without subscription message", connection_name);
+            return Ok(WsConnectionResult::new(
+                ws_stream,
+                connection_name,
+                limiter,
+                max_args,
+                Vec::new(),
+            ));
+        }
+        let mut parts = split_op_message(sub_msg, max_args);
+        for part in parts.iter_mut() {
+            stamp_request_id(part);
+            let msg = Message::Text(part.to_string());
+            limiter.acquire(&msg).await;
+            if let Err(e) = ws_stream.send(msg).await {
+                error!(
+                    "[{}] Failed to send subscription message: {}",
+                    connection_name, e
+                );
+                return Err(e.into());
+            }
+        }
+        info!(
+            "[{}] Successful send subscription message ({} part(s))",
+            connection_name,
+            parts.len()
+        );
+        Ok(WsConnectionResult::new(
+            ws_stream,
+            connection_name,
+            limiter,
+            max_args,
+            parts,
+        ))
+    }
 
     pub async fn connect(
         url: &str,
         sub_msg: &serde_json::Value,
         connection_name: &str,
+        options: &ConnectOptions,
     ) -> anyhow::Result<WsConnectionResult> {
         let url = Url::parse(url).with_context(|| "Invalid URL")?;
+        let mut backoff = Backoff::new(Self::RETRY_BASE_DELAY, Self::RETRY_MAX_DELAY);
         for retry in 0..Self::MAX_RETRIES {
-            match connect_async(url.clone()).await {
-                Ok((mut ws_stream, _)) => {
-                    match ws_stream.send(Message::Text(sub_msg.to_string())).await {
-                        Ok(_) => {
-                            info!("[{}] Successful send subscription message", connection_name);
-                            return Ok(WsConnectionResult {
-                                ws_stream: Arc::new(Mutex::new(ws_stream)),
-                                connected_at: Instant::now(),
-                            });
-                        }
-                        Err(e) => {
-                            error!(
-                                "[{}] Failed to send subscription message: {}",
-                                connection_name, e
-                            );
-                            return Err(e.into());
-                        }
-                    }
+            Self::wait_connection_slot(connection_name, options).await;
+            match Self::open(url.clone().into_client_request()?, options).await {
+                Ok(ws_stream) => {
+                    return Self::subscribe(ws_stream, sub_msg, connection_name, options).await;
                 }
                 Err(e) => {
                     if Self::is_dns_error(&e) {
@@ -120,7 +586,7 @@ impl WsConnector {
                             retry + 1,
                             Self::MAX_RETRIES
                         );
-                        time::sleep(Self::RETRY_DELAY).await;
+                        time::sleep(backoff.next_delay()).await;
                     } else {
                         return Err(e.into());
                     }
@@ -139,32 +605,17 @@ impl WsConnector {
         sub_msg: &serde_json::Value,
         connection_name: &str,
         headers: &[(String, String)],
+        options: &ConnectOptions,
     ) -> anyhow::Result<WsConnectionResult> {
         let url = Url::parse(url).with_context(|| "Invalid URL")?;
+        let mut backoff = Backoff::new(Self::RETRY_BASE_DELAY, Self::RETRY_MAX_DELAY);
         for retry in 0..Self::MAX_RETRIES {
             let mut request = url.clone().into_client_request()?;
             apply_headers(request.headers_mut(), headers)?;
-            match connect_async(request).await {
-                Ok((mut ws_stream, _)) => {
-                    match ws_stream.send(Message::Text(sub_msg.to_string())).await {
-                        Ok(_) => {
-                            info!(
-                                "[{}] Successful send subscription message",
-                                connection_name
-                            );
-                            return Ok(WsConnectionResult {
-                                ws_stream: Arc::new(Mutex::new(ws_stream)),
-                                connected_at: Instant::now(),
-                            });
-                        }
-                        Err(e) => {
-                            error!(
-                                "[{}] Failed to send subscription message: {}",
-                                connection_name, e
-                            );
-                            return Err(e.into());
-                        }
-                    }
+            Self::wait_connection_slot(connection_name, options).await;
+            match Self::open(request, options).await {
+                Ok(ws_stream) => {
+                    return Self::subscribe(ws_stream, sub_msg, connection_name, options).await;
                 }
                 Err(e) => {
                     if Self::is_dns_error(&e) {
@@ -174,7 +625,7 @@ impl WsConnector {
                             retry + 1,
                             Self::MAX_RETRIES
                         );
-                        time::sleep(Self::RETRY_DELAY).await;
+                        time::sleep(backoff.next_delay()).await;
                     } else {
                         return Err(e.into());
                     }
@@ -217,24 +668,47 @@ pub trait MktConnectionHandler: MktConnectionRunner + Send {
 }
 
 /// 根据交易所类型构造相应的连接处理器
+/// sbe为true时按币安SBE连接处理：携带api key header，不使用组合stream
+#[allow(clippy::too_many_arguments)]
 pub fn construct_connection(
-    exchange: String,
+    cfg: &Config,
+    group: &str,
     connection_name: String,
     url: String,
     subscribe_msg: serde_json::Value,
-    tx: broadcast::Sender<Bytes>,
+    tx: broadcast::Sender<RawFrame>,
     global_shutdown_rx: watch::Receiver<bool>,
+    registry: Arc<ConnectionRegistry>,
+    cmd_rx: Option<SubCommandReceiver>,
+    sbe: bool,
 ) -> anyhow::Result<Box<dyn MktConnectionHandler>> {
     use crate::connection::binance_conn::BinanceConnection;
     use crate::connection::bybit_conn::BybitConnection;
     use crate::connection::okex_conn::OkexConnection;
 
-    let base_connection =
-        MktConnection::new(connection_name, url, subscribe_msg, tx, global_shutdown_rx);
+    let exchange = cfg.get_exchange();
+    let health = registry.health(&connection_name, group);
+    let mut base_connection = MktConnection::new(
+        connection_name,
+        url,
+        subscribe_msg,
+        tx,
+        global_shutdown_rx,
+        cfg.get_heartbeat_policy(),
+        ReconnectPolicy::from_cfg(&cfg.reconnect),
+        registry,
+        health,
+    );
+    base_connection.cmd_rx = cmd_rx;
+    base_connection.connect_options = cfg.connect_options(group)?;
 
     match exchange.as_str() {
         "binance-futures" | "binance" | "binance-spot" => {
-            Ok(Box::new(BinanceConnection::new(base_connection)))
+            Ok(Box::new(BinanceConnection::new(
+                base_connection,
+                cfg.binance_combined_streams,
+                sbe,
+            )))
         }
         "okex-swap" | "okex" => Ok(Box::new(OkexConnection::new(base_connection))),
         "bybit" | "bybit-spot" => Ok(Box::new(BybitConnection::new(base_connection))),
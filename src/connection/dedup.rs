@@ -0,0 +1,337 @@
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use tokio::time::{Duration, Instant};
+
+// 帧去重
+// 同一批订阅在两个socket上同时接收时（换连接的重叠期、A/B冗余连接），同一事件会到达两次
+// 以 (symbol, update id / trade id) 作为帧的身份，无法识别的帧（SBE二进制、数组消息等）退化为按内容哈希
+
+/// 帧的身份，用于判断两个socket上收到的是否为同一事件
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FrameKey {
+    Update(String, i64),   // 深度增量：symbol + 最后一个update id
+    Trade(String, i64),    // 逐笔成交：symbol + trade id
+    Event(String, i64),    // 其他带symbol的事件：symbol + 事件时间
+    Named(String, String), // id为字符串的事件：symbol + id
+    Hash(u64),             // 无法解析时按原始内容哈希
+}
+
+#[derive(Deserialize)]
+struct BinanceKeyFields<'a> {
+    #[serde(borrow)]
+    e: Option<&'a str>,
+    #[serde(borrow)]
+    s: Option<&'a str>,
+    u: Option<i64>,
+    t: Option<i64>,
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+    #[serde(borrow)]
+    data: Option<Box<BinanceKeyFields<'a>>>, // 组合stream的外层包装
+}
+
+/// 提取币安帧的身份
+pub fn binance_frame_key(frame: &[u8]) -> FrameKey {
+    if let Ok(fields) = serde_json::from_slice::<BinanceKeyFields>(frame) {
+        let fields = match fields.data {
+            Some(data) => *data,
+            None => fields,
+        };
+        if let Some(symbol) = fields.s {
+            match (fields.e, fields.u, fields.t, fields.event_time) {
+                (Some("depthUpdate"), Some(u), _, _) => {
+                    return FrameKey::Update(symbol.to_string(), u)
+                }
+                (Some("trade"), _, Some(t), _) => return FrameKey::Trade(symbol.to_string(), t),
+                (_, _, _, Some(event_time)) => {
+                    return FrameKey::Event(
+                        format!("{}@{}", fields.e.unwrap_or(""), symbol),
+                        event_time,
+                    )
+                }
+                _ => {}
+            }
+        }
+    }
+    content_key(frame)
+}
+
+#[derive(Deserialize)]
+struct OkexKeyArg<'a> {
+    #[serde(borrow)]
+    channel: Option<&'a str>,
+    #[serde(borrow, rename = "instId")]
+    inst_id: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct OkexKeyData<'a> {
+    #[serde(rename = "seqId")]
+    seq_id: Option<i64>,
+    #[serde(borrow, rename = "tradeId")]
+    trade_id: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct OkexKeyFields<'a> {
+    #[serde(borrow)]
+    arg: Option<OkexKeyArg<'a>>,
+    #[serde(borrow, default)]
+    data: Vec<OkexKeyData<'a>>,
+}
+
+/// 提取okex帧的身份：深度用seqId，成交用第一条tradeId
+pub fn okex_frame_key(frame: &[u8]) -> FrameKey {
+    if let Ok(fields) = serde_json::from_slice::<OkexKeyFields>(frame) {
+        if let (Some(arg), Some(first)) = (fields.arg, fields.data.first()) {
+            let target = format!(
+                "{}:{}",
+                arg.channel.unwrap_or(""),
+                arg.inst_id.unwrap_or("")
+            );
+            if let Some(seq_id) = first.seq_id {
+                return FrameKey::Update(target, seq_id);
+            }
+            if let Some(trade_id) = first.trade_id {
+                return match trade_id.parse::<i64>() {
+                    Ok(id) => FrameKey::Trade(target, id),
+                    Err(_) => FrameKey::Named(target, trade_id.to_string()),
+                };
+            }
+        }
+    }
+    content_key(frame)
+}
+
+#[derive(Deserialize)]
+struct BybitKeyTrade {
+    i: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BybitKeyData {
+    Book { u: i64 },
+    Trades(Vec<BybitKeyTrade>),
+}
+
+#[derive(Deserialize)]
+struct BybitKeyFields<'a> {
+    #[serde(borrow)]
+    topic: Option<&'a str>,
+    data: Option<BybitKeyData>,
+}
+
+/// 提取bybit帧的身份：深度用update id，成交用第一条成交id
+pub fn bybit_frame_key(frame: &[u8]) -> FrameKey {
+    if let Ok(fields) = serde_json::from_slice::<BybitKeyFields>(frame) {
+        if let (Some(topic), Some(data)) = (fields.topic, fields.data) {
+            match data {
+                BybitKeyData::Book { u } => return FrameKey::Update(topic.to_string(), u),
+                BybitKeyData::Trades(trades) => {
+                    if let Some(id) = trades.into_iter().next().and_then(|trade| trade.i) {
+                        return FrameKey::Named(topic.to_string(), id);
+                    }
+                }
+            }
+        }
+    }
+    content_key(frame)
+}
+
+/// 按交易所选择帧身份的提取函数
+pub fn frame_key_fn(exchange: &str) -> fn(&[u8]) -> FrameKey {
+    if exchange.starts_with("okex") {
+        okex_frame_key
+    } else if exchange.starts_with("bybit") {
+        bybit_frame_key
+    } else {
+        binance_frame_key
+    }
+}
+
+pub fn content_key(frame: &[u8]) -> FrameKey {
+    let mut hasher = DefaultHasher::new();
+    frame.hash(&mut hasher);
+    FrameKey::Hash(hasher.finish())
+}
+
+/// 去重结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameVerdict {
+    Forward,   // 首次出现，需要转发
+    Duplicate, // 另一个socket已经转发过
+}
+
+/// 重叠期内的帧去重窗口，未激活时所有帧直接放行
+pub struct FrameDedup {
+    key_fn: fn(&[u8]) -> FrameKey,
+    capacity: usize,
+    active: bool,
+    until: Option<Instant>,
+    seen: HashSet<FrameKey>,
+    order: VecDeque<FrameKey>,
+}
+
+impl FrameDedup {
+    pub fn new(key_fn: fn(&[u8]) -> FrameKey, capacity: usize) -> Self {
+        Self {
+            key_fn,
+            capacity,
+            active: false,
+            until: None,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// 开始重叠期
+    pub fn start(&mut self) {
+        self.active = true;
+        self.until = None;
+    }
+
+    /// 重叠期结束后再保持 tail 时长，吸收新连接上滞后到达的重复帧
+    pub fn finish_after(&mut self, tail: Duration) {
+        if self.active {
+            self.until = Some(Instant::now() + tail);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.until = None;
+        self.seen.clear();
+        self.order.clear();
+    }
+
+    pub fn admit(&mut self, frame: &[u8]) -> FrameVerdict {
+        if !self.active {
+            return FrameVerdict::Forward;
+        }
+        if let Some(until) = self.until {
+            if Instant::now() >= until {
+                self.stop();
+                return FrameVerdict::Forward;
+            }
+        }
+        let key = (self.key_fn)(frame);
+        if self.seen.contains(&key) {
+            return FrameVerdict::Duplicate;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        FrameVerdict::Forward
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPTH: &[u8] = br#"{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":10,"u":12}"#;
+
+    #[test]
+    fn duplicates_across_sockets_are_suppressed() {
+        let mut dedup = FrameDedup::new(binance_frame_key, 16);
+        // 未开始重叠期时全部放行
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+
+        dedup.start();
+        // 旧socket先到，新socket上的同一事件被丢弃，事件时间不同也视为同一条增量
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+        let late = br#"{"e":"depthUpdate","E":2,"s":"BTCUSDT","U":10,"u":12}"#;
+        assert_eq!(dedup.admit(late), FrameVerdict::Duplicate);
+        let next = br#"{"e":"depthUpdate","E":3,"s":"BTCUSDT","U":13,"u":15}"#;
+        assert_eq!(dedup.admit(next), FrameVerdict::Forward);
+        assert_eq!(dedup.admit(next), FrameVerdict::Duplicate);
+
+        dedup.stop();
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+    }
+
+    #[test]
+    fn dedup_expires_after_tail() {
+        let mut dedup = FrameDedup::new(binance_frame_key, 16);
+        dedup.start();
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+        dedup.finish_after(Duration::from_secs(60));
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Duplicate);
+        // tail到期后去重结束，之后的帧直接放行
+        dedup.finish_after(Duration::ZERO);
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+        // 未开始重叠期时 finish_after 不会激活去重
+        dedup.finish_after(Duration::from_secs(60));
+        assert_eq!(dedup.admit(DEPTH), FrameVerdict::Forward);
+    }
+
+    #[test]
+    fn binance_combined_stream_envelope() {
+        let combined = br#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":10,"u":12}}"#;
+        assert_eq!(
+            binance_frame_key(combined),
+            FrameKey::Update("BTCUSDT".to_string(), 12)
+        );
+        assert_eq!(binance_frame_key(combined), binance_frame_key(DEPTH));
+        let trade =
+            br#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1,"s":"BTCUSDT","t":99}}"#;
+        assert_eq!(
+            binance_frame_key(trade),
+            FrameKey::Trade("BTCUSDT".to_string(), 99)
+        );
+    }
+
+    #[test]
+    fn okex_and_bybit_keys() {
+        let book = br#"{"arg":{"channel":"books","instId":"BTC-USDT"},"data":[{"seqId":42,"prevSeqId":41}]}"#;
+        assert_eq!(
+            okex_frame_key(book),
+            FrameKey::Update("books:BTC-USDT".to_string(), 42)
+        );
+        let trade = br#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[{"tradeId":"7"}]}"#;
+        assert_eq!(
+            okex_frame_key(trade),
+            FrameKey::Trade("trades:BTC-USDT".to_string(), 7)
+        );
+
+        let book = br#"{"topic":"orderbook.50.BTCUSDT","type":"delta","data":{"s":"BTCUSDT","u":5,"seq":9}}"#;
+        assert_eq!(
+            bybit_frame_key(book),
+            FrameKey::Update("orderbook.50.BTCUSDT".to_string(), 5)
+        );
+        let trade = br#"{"topic":"publicTrade.BTCUSDT","data":[{"i":"abc-1"}]}"#;
+        assert_eq!(
+            bybit_frame_key(trade),
+            FrameKey::Named("publicTrade.BTCUSDT".to_string(), "abc-1".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_frames_fall_back_to_content_hash() {
+        let sbe: &[u8] = &[0x01, 0x02, 0x03, 0x04];
+        assert!(matches!(binance_frame_key(sbe), FrameKey::Hash(_)));
+        assert_eq!(binance_frame_key(sbe), content_key(sbe));
+        let pong = b"pong";
+        assert_eq!(okex_frame_key(pong), content_key(pong));
+        let array = br#"[{"e":"24hrTicker","s":"BTCUSDT"}]"#;
+        assert!(matches!(binance_frame_key(array), FrameKey::Hash(_)));
+
+        let mut dedup = FrameDedup::new(okex_frame_key, 16);
+        dedup.start();
+        assert_eq!(dedup.admit(sbe), FrameVerdict::Forward);
+        assert_eq!(dedup.admit(sbe), FrameVerdict::Duplicate);
+        assert_eq!(
+            dedup.admit(&[0x01, 0x02, 0x03, 0x05]),
+            FrameVerdict::Forward
+        );
+    }
+}
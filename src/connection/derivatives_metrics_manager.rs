@@ -1,12 +1,15 @@
 use crate::cfg::Config;
 use crate::connection::connection::construct_connection;
+use crate::connection::registry::ConnectionRegistry;
 use crate::parser::binance_parser::BinanceDerivativesMetricsParser;
 use crate::parser::bybit_parser::BybitDerivativesMetricsParser;
 use crate::parser::default_parser::Parser;
+use crate::parser::monitor::ParseMonitor;
 use crate::parser::okex_parser::OkexDerivativesMetricsParser;
 use crate::sub_msg::DerivativesMetricsSubscribeMsgs;
 use bytes::Bytes;
 use log::{error, info};
+use std::sync::Arc;
 use tokio::sync::{broadcast, watch};
 use tokio::task::JoinSet;
 
@@ -16,6 +19,7 @@ pub struct DerivativesMetricsDataConnectionManager {
     subscribe_msgs: DerivativesMetricsSubscribeMsgs,
     metrics_tx: broadcast::Sender<Bytes>,
     global_shutdown_rx: watch::Receiver<bool>,
+    registry: Arc<ConnectionRegistry>,
     join_set: JoinSet<()>,
 }
 
@@ -24,6 +28,7 @@ impl DerivativesMetricsDataConnectionManager {
         cfg: &Config,
         global_shutdown: &watch::Sender<bool>,
         metrics_tx: broadcast::Sender<Bytes>,
+        registry: Arc<ConnectionRegistry>,
     ) -> Self {
         let subscribe_msgs = DerivativesMetricsSubscribeMsgs::new(&cfg).await;
         Self {
@@ -31,6 +36,7 @@ impl DerivativesMetricsDataConnectionManager {
             subscribe_msgs,
             metrics_tx,
             global_shutdown_rx: global_shutdown.subscribe(),
+            registry,
             join_set: JoinSet::new(),
         }
     }
@@ -56,7 +62,7 @@ impl DerivativesMetricsDataConnectionManager {
         msgs: &crate::sub_msg::BinancePerpsSubscribeMsgs,
     ) {
         let exchange = self.cfg.get_exchange().clone();
-        let url = crate::sub_msg::BinancePerpsSubscribeMsgs::WS_URL.to_string();
+        let url = self.cfg.derivatives_ws_url();
 
         info!(
             "Starting Binance derivatives connections for exchange: {}",
@@ -88,7 +94,7 @@ impl DerivativesMetricsDataConnectionManager {
 
     async fn start_okex_connections(&mut self, msgs: &crate::sub_msg::OkexPerpsSubscribeMsgs) {
         let exchange = self.cfg.get_exchange().clone();
-        let url = crate::sub_msg::OkexPerpsSubscribeMsgs::WS_URL.to_string();
+        let url = self.cfg.derivatives_ws_url();
 
         info!(
             "Starting OKEx derivatives connections for exchange: {}",
@@ -112,7 +118,7 @@ impl DerivativesMetricsDataConnectionManager {
 
     async fn start_bybit_connections(&mut self, msgs: &crate::sub_msg::BybitPerpsSubscribeMsgs) {
         let exchange = self.cfg.get_exchange().clone();
-        let url = crate::sub_msg::BybitPerpsSubscribeMsgs::WS_URL.to_string();
+        let url = self.cfg.derivatives_ws_url();
 
         let total_streams = msgs.ticker_stream_msgs.len() + msgs.liquidation_orders_msgs.len();
         info!(
@@ -183,6 +189,8 @@ impl DerivativesMetricsDataConnectionManager {
     ) {
         let metrics_tx = self.metrics_tx.clone();
         let global_shutdown_rx = self.global_shutdown_rx.clone();
+        let cfg = self.cfg.clone();
+        let registry = self.registry.clone();
 
         info!(
             "Creating derivatives connection: {} (exchange: {})",
@@ -204,6 +212,7 @@ impl DerivativesMetricsDataConnectionManager {
         info!("Spawning connection task for {}", description);
         let task_description = description.clone();
         let ws_description = description.clone();
+        let mut monitor = ParseMonitor::new(description.clone(), &self.cfg.parse_monitor);
         self.join_set.spawn(async move {
             info!("Connection task started for {}", task_description);
             
@@ -221,12 +230,16 @@ impl DerivativesMetricsDataConnectionManager {
             tokio::spawn(async move {
                 info!("WebSocket connection task starting for {}", ws_description);
                 let mut connection = match construct_connection(
-                    ws_exchange.clone(),
+                    &cfg,
+                    "derivatives",
                     format!("{}-{}", ws_exchange, ws_description),
                     ws_url,
                     ws_subscribe_msg,
                     raw_tx,
-                    ws_global_shutdown_rx
+                    ws_global_shutdown_rx,
+                    registry,
+                    None,
+                    false,
                 ) {
                     Ok(c) => {
                         info!("WebSocket connection constructed successfully for {}", ws_description);
@@ -258,7 +271,8 @@ impl DerivativesMetricsDataConnectionManager {
                         msg_result = raw_rx.recv() => {
                             match msg_result {
                                 Ok(raw_msg) => {
-                                    let _parsed_count = parser.parse(raw_msg, &metrics_tx);
+                                    let outcome = parser.parse(raw_msg.data.clone(), raw_msg.recv_ts, &metrics_tx);
+                                    monitor.record(&outcome, &raw_msg.data);
                                 }
                                 Err(broadcast::error::RecvError::Closed) => {
                                     info!("Raw message channel closed for {}", parser_description);
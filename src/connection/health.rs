@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite;
+
+// 连接健康状态
+// 每个连接持有一份，转发一帧数据时累加消息数与字节数，并刷新最后收到数据的时间
+// 建连、断开原因由连接写入；app定期采样计算 msg/s 与 bytes/s，按分组汇总输出，只单独列出未连接或stale的连接
+// 超过阈值没有数据的batch由app标记为stale，并通知连接主动断开重连
+// websocket层拒绝的帧（超过大小限制、非UTF-8的文本帧）计入rejected；二进制帧（如binance SBE）原样转发，由parser判断格式
+
+/// 单个连接的健康状态，数据路径只做原子累加
+#[derive(Debug)]
+pub struct ConnectionHealth {
+    group: String,
+    epoch: Instant,
+    messages: AtomicU64,
+    bytes: AtomicU64,
+    last_message_us: AtomicU64, // 相对epoch的微秒，0表示还没有收到数据
+    rejected_frames: AtomicU64,
+    state: Mutex<HealthState>,
+    reconnect: Notify,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    connected_at: Option<Instant>,
+    connected_since: Option<DateTime<Utc>>,
+    connects: u64,
+    last_close_reason: Option<String>,
+    stale: bool,
+    sample: Option<(Instant, u64, u64)>, // 上一次采样的时间、消息数、字节数
+    msg_rate: f64,
+    byte_rate: f64,
+}
+
+/// app展示用的健康快照
+#[derive(Debug, Clone)]
+pub struct HealthSnapshot {
+    pub connected_since: Option<DateTime<Utc>>, // None表示当前未连接
+    pub reconnects: u64,
+    pub idle: Option<Duration>, // 距最后一帧数据的时间，连接后还没有数据时从建连开始计算
+    pub msg_rate: f64,
+    pub byte_rate: f64,
+    pub last_close_reason: Option<String>,
+    pub stale: bool,
+    pub rejected_frames: u64, // 累计被拒绝的帧数
+}
+
+impl HealthSnapshot {
+    /// 未连接或被标记为stale，app单独列出该连接
+    pub fn is_unhealthy(&self) -> bool {
+        self.connected_since.is_none() || self.stale
+    }
+}
+
+/// 一个连接分组的汇总，app按分组输出一行
+#[derive(Debug, Default)]
+pub struct HealthSummary {
+    pub connections: usize,
+    pub connected: usize,
+    pub unhealthy: usize,
+    pub reconnects: u64,
+    pub msg_rate: f64,
+    pub byte_rate: f64,
+    pub max_idle: Option<Duration>,
+    pub rejected_frames: u64,
+}
+
+impl HealthSummary {
+    pub fn add(&mut self, snapshot: &HealthSnapshot) {
+        self.connections += 1;
+        self.connected += usize::from(snapshot.connected_since.is_some());
+        self.unhealthy += usize::from(snapshot.is_unhealthy());
+        self.reconnects += snapshot.reconnects;
+        self.msg_rate += snapshot.msg_rate;
+        self.byte_rate += snapshot.byte_rate;
+        self.max_idle = self.max_idle.max(snapshot.idle);
+        self.rejected_frames += snapshot.rejected_frames;
+    }
+}
+
+impl ConnectionHealth {
+    pub fn new(group: &str) -> Self {
+        Self {
+            group: group.to_string(),
+            epoch: Instant::now(),
+            messages: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            last_message_us: AtomicU64::new(0),
+            rejected_frames: AtomicU64::new(0),
+            state: Mutex::new(HealthState::default()),
+            reconnect: Notify::new(),
+        }
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// 转发一帧数据
+    pub fn on_message(&self, len: usize) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+        let us = self.epoch.elapsed().as_micros() as u64;
+        self.last_message_us.store(us.max(1), Ordering::Relaxed);
+    }
+
+    /// websocket层拒绝了一帧数据
+    pub fn on_rejected_frame(&self) {
+        self.rejected_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 读websocket出错，记录断开原因；超过大小限制或文本帧UTF-8校验失败时同时计入rejected
+    pub fn on_read_error(&self, e: &tungstenite::Error) {
+        if matches!(
+            e,
+            tungstenite::Error::Capacity(_) | tungstenite::Error::Utf8
+        ) {
+            self.on_rejected_frame();
+        }
+        self.record_close(format!("websocket error: {}", e));
+    }
+
+    pub fn on_connected(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connected_at = Some(Instant::now());
+        state.connected_since = Some(Utc::now());
+        state.connects += 1;
+        state.stale = false;
+    }
+
+    pub fn on_disconnected(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connected_at = None;
+        state.connected_since = None;
+    }
+
+    /// 记录连接断开的原因，在读循环退出前调用
+    pub fn record_close(&self, reason: impl Into<String>) {
+        self.state.lock().unwrap().last_close_reason = Some(reason.into());
+    }
+
+    /// 当前连接已经多久没有数据，未连接时返回None
+    pub fn idle(&self) -> Option<Duration> {
+        let connected_at = self.state.lock().unwrap().connected_at?;
+        let last_us = self.last_message_us.load(Ordering::Relaxed);
+        let last = self.epoch + Duration::from_micros(last_us);
+        Some(connected_at.max(last).elapsed())
+    }
+
+    /// 标记为stale并通知连接重连，已经标记过时返回false
+    pub fn mark_stale(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.stale || state.connected_at.is_none() {
+            return false;
+        }
+        state.stale = true;
+        self.reconnect.notify_one();
+        true
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.state.lock().unwrap().stale
+    }
+
+    /// 连接在 select! 中等待重连通知，收到后需用 is_stale 确认（通知可能在上一个连接期间发出）
+    pub async fn reconnect_requested(&self) {
+        self.reconnect.notified().await
+    }
+
+    /// 按与上一次采样的差值计算速率并返回快照
+    pub fn sample(&self) -> HealthSnapshot {
+        let idle = self.idle();
+        let now = Instant::now();
+        let messages = self.messages.load(Ordering::Relaxed);
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        if let Some((at, last_messages, last_bytes)) = state.sample {
+            let secs = now.duration_since(at).as_secs_f64();
+            if secs > 0.0 {
+                state.msg_rate = (messages - last_messages) as f64 / secs;
+                state.byte_rate = (bytes - last_bytes) as f64 / secs;
+            }
+        }
+        state.sample = Some((now, messages, bytes));
+        HealthSnapshot {
+            connected_since: state.connected_since,
+            reconnects: state.connects.saturating_sub(1),
+            idle,
+            msg_rate: state.msg_rate,
+            byte_rate: state.byte_rate,
+            last_close_reason: state.last_close_reason.clone(),
+            stale: state.stale,
+            rejected_frames: self.rejected_frames.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::cfg::HeartbeatPolicyCfg;
+use tokio::time::{Duration, Instant};
+
+// 三个交易所的心跳机制可以归纳为三类：
+// 1. ServerPing    服务端定期发ping（币安3min），本地只需回pong，超过 interval + grace 没收到ping则重连
+// 2. IdlePing      N秒内没有任何消息则主动发ping（okex），等待pong期间消息不刷新计时，pong超时则重连
+// 3. FixedInterval 固定间隔主动发ping（bybit 20s），每次ping后必须在pong_timeout内收到pong，否则重连
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatPolicy {
+    ServerPing {
+        interval: Duration,
+        grace: Duration,
+    },
+    IdlePing {
+        idle: Duration,
+        pong_timeout: Duration,
+    },
+    FixedInterval {
+        interval: Duration,
+        pong_timeout: Duration,
+    },
+}
+
+impl HeartbeatPolicy {
+    /// 各交易所的默认心跳参数，与配置文件缺省时的行为一致
+    pub fn default_for_exchange(exchange: &str) -> Self {
+        match exchange {
+            "binance-futures" | "binance" | "binance-spot" => HeartbeatPolicy::ServerPing {
+                interval: Duration::from_secs(180),
+                grace: Duration::from_secs(5),
+            },
+            "okex-swap" | "okex" => HeartbeatPolicy::IdlePing {
+                idle: Duration::from_secs(25),
+                pong_timeout: Duration::from_secs(25),
+            },
+            "bybit" | "bybit-spot" => HeartbeatPolicy::FixedInterval {
+                interval: Duration::from_secs(20),
+                pong_timeout: Duration::from_secs(5),
+            },
+            _ => panic!("Unsupported exchange: {}", exchange),
+        }
+    }
+
+    pub fn from_cfg(cfg: &HeartbeatPolicyCfg) -> Self {
+        let interval = Duration::from_secs(cfg.interval_secs);
+        let timeout = Duration::from_secs(cfg.timeout_secs);
+        match cfg.mode {
+            crate::cfg::HeartbeatMode::ServerPing => HeartbeatPolicy::ServerPing {
+                interval,
+                grace: timeout,
+            },
+            crate::cfg::HeartbeatMode::IdlePing => HeartbeatPolicy::IdlePing {
+                idle: interval,
+                pong_timeout: timeout,
+            },
+            crate::cfg::HeartbeatMode::FixedInterval => HeartbeatPolicy::FixedInterval {
+                interval,
+                pong_timeout: timeout,
+            },
+        }
+    }
+}
+
+/// 心跳计时器到期后需要连接执行的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatAction {
+    SendPing,
+    Reconnect,
+}
+
+/// 心跳状态机，连接只需要在 select! 中等待 deadline()，并把事件喂给它
+pub struct Heartbeat {
+    policy: HeartbeatPolicy,
+    deadline: Instant,             // ServerPing: 重连时间点；IdlePing: 发ping或pong超时时间点；FixedInterval: 下次发ping时间点
+    pong_deadline: Option<Instant>, // 仅 FixedInterval 使用，等待pong的超时时间点
+    waiting_pong: bool,
+}
+
+impl Heartbeat {
+    pub fn new(policy: HeartbeatPolicy) -> Self {
+        let now = Instant::now();
+        let deadline = match policy {
+            HeartbeatPolicy::ServerPing { interval, grace } => now + interval + grace,
+            HeartbeatPolicy::IdlePing { idle, .. } => now + idle,
+            HeartbeatPolicy::FixedInterval { interval, .. } => now + interval,
+        };
+        Self {
+            policy,
+            deadline,
+            pong_deadline: None,
+            waiting_pong: false,
+        }
+    }
+
+    pub fn is_waiting_pong(&self) -> bool {
+        self.waiting_pong
+    }
+
+    /// 下一次需要调用 on_timeout 的时间点
+    pub fn deadline(&self) -> Instant {
+        match self.pong_deadline {
+            Some(pong_deadline) if pong_deadline < self.deadline => pong_deadline,
+            _ => self.deadline,
+        }
+    }
+
+    /// 收到任意行情消息
+    pub fn on_message(&mut self) {
+        if let HeartbeatPolicy::IdlePing { idle, .. } = self.policy {
+            // 等待pong期间，普通消息不刷新计时，必须等到pong
+            if !self.waiting_pong {
+                self.deadline = Instant::now() + idle;
+            }
+        }
+    }
+
+    /// 收到服务端的ping
+    pub fn on_server_ping(&mut self) {
+        if let HeartbeatPolicy::ServerPing { interval, grace } = self.policy {
+            self.deadline = Instant::now() + interval + grace;
+        }
+    }
+
+    /// 收到服务端对本地ping的回复
+    pub fn on_pong(&mut self) {
+        self.waiting_pong = false;
+        match self.policy {
+            HeartbeatPolicy::ServerPing { .. } => {}
+            HeartbeatPolicy::IdlePing { idle, .. } => {
+                self.deadline = Instant::now() + idle;
+            }
+            HeartbeatPolicy::FixedInterval { .. } => {
+                self.pong_deadline = None;
+            }
+        }
+    }
+
+    /// deadline 到期时调用，返回连接需要执行的动作
+    pub fn on_timeout(&mut self) -> HeartbeatAction {
+        let now = Instant::now();
+        match self.policy {
+            HeartbeatPolicy::ServerPing { .. } => HeartbeatAction::Reconnect,
+            HeartbeatPolicy::IdlePing { pong_timeout, .. } => {
+                if self.waiting_pong {
+                    return HeartbeatAction::Reconnect;
+                }
+                self.waiting_pong = true;
+                self.deadline = now + pong_timeout;
+                HeartbeatAction::SendPing
+            }
+            HeartbeatPolicy::FixedInterval {
+                interval,
+                pong_timeout,
+            } => {
+                if let Some(pong_deadline) = self.pong_deadline {
+                    if now >= pong_deadline {
+                        return HeartbeatAction::Reconnect;
+                    }
+                }
+                self.waiting_pong = true;
+                self.deadline = now + interval;
+                if self.pong_deadline.is_none() {
+                    self.pong_deadline = Some(now + pong_timeout);
+                }
+                HeartbeatAction::SendPing
+            }
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use crate::cfg::Config;
 use crate::connection::connection::construct_connection;
+use crate::connection::live_sub::{apply_symbol_diff, LiveBatch, SubCommandSender};
+use crate::connection::registry::ConnectionRegistry;
 use crate::parser::binance_parser::BinanceKlineParser;
 use crate::parser::bybit_parser::BybitKlineParser;
 use crate::parser::default_parser::Parser;
+use crate::parser::monitor::ParseMonitor;
 use crate::parser::okex_parser::OkexKlineParser;
-use crate::sub_msg::SubscribeMsgs;
+use crate::sub_msg::{construct_op_message, SubscribeMsgs};
 use bytes::Bytes;
 use log::{error, info};
-use tokio::sync::{broadcast, watch};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, watch};
 use tokio::task::JoinSet;
 
 pub struct KlineDataConnectionManager {
@@ -15,6 +20,8 @@ pub struct KlineDataConnectionManager {
     subscribe_msgs: SubscribeMsgs,
     kline_tx: broadcast::Sender<Bytes>,
     global_shutdown_rx: watch::Receiver<bool>,
+    registry: Arc<ConnectionRegistry>,
+    kline_batches: Vec<LiveBatch>,
     join_set: JoinSet<()>,
 }
 
@@ -23,6 +30,7 @@ impl KlineDataConnectionManager {
         cfg: &Config,
         global_shutdown: &watch::Sender<bool>,
         kline_tx: broadcast::Sender<Bytes>,
+        registry: Arc<ConnectionRegistry>,
     ) -> Self {
         let subscribe_msgs = SubscribeMsgs::new(&cfg).await;
         Self {
@@ -30,24 +38,42 @@ impl KlineDataConnectionManager {
             subscribe_msgs,
             kline_tx,
             global_shutdown_rx: global_shutdown.subscribe(),
+            registry,
+            kline_batches: Vec::new(),
             join_set: JoinSet::new(),
         }
     }
 
     pub async fn start_all_kline_connections(&mut self) {
         let kline_msg_len = self.subscribe_msgs.get_kline_subscribe_msg_len();
+        let kline_channel = SubscribeMsgs::get_kline_channel(&self.cfg.get_exchange());
         for i in 0..kline_msg_len {
-            let exchange = self.cfg.get_exchange().clone();
-            let url =
-                crate::sub_msg::SubscribeMsgs::get_exchange_kline_data_url(&exchange).to_string();
             let subscribe_msg = self.subscribe_msgs.get_kline_subscribe_msg(i).clone();
-
-            self.spawn_kline_connection(exchange, url, subscribe_msg, format!("kline batch {}", i))
-                .await;
+            if let Some(cmd_tx) = self.spawn_kline_batch(i, subscribe_msg).await {
+                let symbols = self.subscribe_msgs.get_kline_symbol_batch(i).to_vec();
+                self.kline_batches
+                    .push(LiveBatch::new(kline_channel.clone(), symbols, cmd_tx));
+            }
         }
         log::info!("All kline connections started...");
     }
 
+    async fn spawn_kline_batch(
+        &mut self,
+        index: usize,
+        subscribe_msg: serde_json::Value,
+    ) -> Option<SubCommandSender> {
+        let exchange = self.cfg.get_exchange().clone();
+        let url = self.cfg.kline_ws_url();
+        self.spawn_kline_connection(
+            exchange,
+            url,
+            subscribe_msg,
+            format!("kline batch {}", index),
+        )
+        .await
+    }
+
     pub async fn shutdown(
         &mut self,
         global_shutdown: &watch::Sender<bool>,
@@ -55,6 +81,7 @@ impl KlineDataConnectionManager {
         if let Err(e) = global_shutdown.send(true) {
             error!("Failed to shutdown KlineDataConnectionManager: {}", e);
         }
+        self.kline_batches.clear();
         let mut join_set = std::mem::take(&mut self.join_set);
         while let Some(result) = join_set.join_next().await {
             match result {
@@ -90,18 +117,22 @@ impl KlineDataConnectionManager {
         url: String,
         subscribe_msg: serde_json::Value,
         description: String,
-    ) {
+    ) -> Option<SubCommandSender> {
         let kline_tx = self.kline_tx.clone();
         let global_shutdown_rx = self.global_shutdown_rx.clone();
+        let cfg = self.cfg.clone();
+        let registry = self.registry.clone();
 
         // Create parser before moving into the async block
         let parser = match self.construct_kline_parser(&exchange).await {
             Ok(p) => p,
             Err(e) => {
                 error!("Failed to create kline parser for {}: {}", description, e);
-                return;
+                return None;
             }
         };
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let mut monitor = ParseMonitor::new(description.clone(), &self.cfg.parse_monitor);
 
         self.join_set.spawn(async move {
             // Create intermediate channel for raw WebSocket data
@@ -116,12 +147,16 @@ impl KlineDataConnectionManager {
 
             tokio::spawn(async move {
                 let mut connection = match construct_connection(
-                    ws_exchange.clone(),
+                    &cfg,
+                    "kline",
                     format!("{}-{}", ws_exchange, ws_description),
                     ws_url,
                     ws_subscribe_msg,
                     raw_tx,
                     ws_global_shutdown_rx,
+                    registry,
+                    Some(cmd_rx),
+                    false,
                 ) {
                     Ok(c) => c,
                     Err(e) => {
@@ -144,7 +179,8 @@ impl KlineDataConnectionManager {
                         msg_result = raw_rx.recv() => {
                             match msg_result {
                                 Ok(raw_msg) => {
-                                    let _parsed_count = parser.parse(raw_msg, &kline_tx);
+                                    let outcome = parser.parse(raw_msg.data.clone(), raw_msg.recv_ts, &kline_tx);
+                                    monitor.record(&outcome, &raw_msg.data);
                                 }
                                 Err(broadcast::error::RecvError::Closed) => {
                                     info!("Raw message channel closed for {}", description);
@@ -166,6 +202,7 @@ impl KlineDataConnectionManager {
                 }
             });
         });
+        Some(cmd_tx)
     }
 
     pub async fn update_subscribe_msgs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
@@ -175,4 +212,32 @@ impl KlineDataConnectionManager {
         SubscribeMsgs::compare_symbol_set(&prev_symbols, &self.subscribe_msgs.get_active_symbols());
         Ok(())
     }
+
+    /// 拉取最新symbol列表，在运行中的kline连接上增减订阅
+    pub async fn refresh_symbols(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let new_symbols: HashSet<String> = self.cfg.get_symbols().await?.into_iter().collect();
+        let prev_symbols = self.subscribe_msgs.get_active_symbols();
+        if new_symbols == prev_symbols {
+            return Ok(());
+        }
+        let diff = SubscribeMsgs::compare_symbol_set(&prev_symbols, &new_symbols);
+        self.subscribe_msgs.set_active_symbols(new_symbols);
+
+        let exchange = self.cfg.get_exchange();
+        let batch_size = self.cfg.get_batch_size();
+        let kline_channel = SubscribeMsgs::get_kline_channel(&exchange);
+        let overflow = apply_symbol_diff(&exchange, &mut self.kline_batches, &diff, batch_size);
+        for chunk in overflow.chunks(batch_size) {
+            let index = self.kline_batches.len();
+            let subscribe_msg = construct_op_message(&exchange, chunk, &kline_channel, true);
+            if let Some(cmd_tx) = self.spawn_kline_batch(index, subscribe_msg).await {
+                self.kline_batches.push(LiveBatch::new(
+                    kline_channel.clone(),
+                    chunk.to_vec(),
+                    cmd_tx,
+                ));
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,160 @@
+use crate::connection::connection::WsWriter;
+use crate::connection::sub_ack::SubAckTracker;
+use crate::sub_msg::{construct_op_message, SymbolDiff};
+use log::{info, warn};
+use serde_json::Value;
+use tokio::sync::mpsc::{self, error::SendError};
+use tokio_tungstenite::tungstenite::Message;
+
+// 在线增减订阅
+// manager 定期拉取symbol列表，与上一次的集合做差，把差异转成 SUBSCRIBE/UNSUBSCRIBE（okex/bybit为op）
+// 通过命令通道发给对应batch的连接，由连接直接写入socket，不需要重启
+// 现有batch放不下的新symbol由manager新开batch
+
+/// manager 发给连接的订阅变更命令
+#[derive(Debug, Clone)]
+pub struct SubCommand {
+    pub msg: Value,
+    pub subscribe: bool,
+}
+
+pub type SubCommandSender = mpsc::UnboundedSender<SubCommand>;
+pub type SubCommandReceiver = mpsc::UnboundedReceiver<SubCommand>;
+
+/// manager 侧记录的一个batch：订阅的频道、当前symbol以及命令发送端
+pub struct LiveBatch {
+    pub channel: String,
+    pub symbols: Vec<String>,
+    pub cmd_tx: SubCommandSender,
+}
+
+impl LiveBatch {
+    pub fn new(channel: String, symbols: Vec<String>, cmd_tx: SubCommandSender) -> Self {
+        Self {
+            channel,
+            symbols,
+            cmd_tx,
+        }
+    }
+
+    fn send(&self, exchange: &str, symbols: &[String], subscribe: bool) {
+        let msg = construct_op_message(exchange, symbols, &self.channel, subscribe);
+        if self.cmd_tx.send(SubCommand { msg, subscribe }).is_err() {
+            warn!(
+                "Connection for channel {} already stopped, drop {} of {:?}",
+                self.channel,
+                if subscribe {
+                    "subscribe"
+                } else {
+                    "unsubscribe"
+                },
+                symbols
+            );
+        }
+    }
+}
+
+/// 把symbol差异下发给现有batch：先取消已下架的symbol，再用空出的位置承接新symbol
+/// 返回现有batch放不下、需要新开batch的symbol
+pub fn apply_symbol_diff(
+    exchange: &str,
+    batches: &mut [LiveBatch],
+    diff: &SymbolDiff,
+    batch_size: usize,
+) -> Vec<String> {
+    for batch in batches.iter_mut() {
+        let removed: Vec<String> = batch
+            .symbols
+            .iter()
+            .filter(|symbol| diff.removed.contains(symbol))
+            .cloned()
+            .collect();
+        if !removed.is_empty() {
+            info!("[{}] Unsubscribing {:?}", batch.channel, removed);
+            batch.send(exchange, &removed, false);
+            batch.symbols.retain(|symbol| !removed.contains(symbol));
+        }
+    }
+
+    let mut pending: &[String] = &diff.added;
+    for batch in batches.iter_mut() {
+        if pending.is_empty() {
+            break;
+        }
+        let room = batch_size.saturating_sub(batch.symbols.len());
+        if room == 0 {
+            continue;
+        }
+        let (take, rest) = pending.split_at(room.min(pending.len()));
+        info!("[{}] Subscribing {:?}", batch.channel, take);
+        batch.send(exchange, take, true);
+        batch.symbols.extend_from_slice(take);
+        pending = rest;
+    }
+    pending.to_vec()
+}
+
+/// 在同一连接上先取消再重新订阅，交易所随后重新推送快照，用于订单簿断档后重新同步
+pub fn resubscribe(exchange: &str, channel: &str, symbols: &[String], cmd_tx: &SubCommandSender) {
+    info!("[{}] Resubscribing {:?}", channel, symbols);
+    for subscribe in [false, true] {
+        let msg = construct_op_message(exchange, symbols, channel, subscribe);
+        if cmd_tx.send(SubCommand { msg, subscribe }).is_err() {
+            warn!(
+                "Connection for channel {} already stopped, drop resubscribe of {:?}",
+                channel, symbols
+            );
+            return;
+        }
+    }
+}
+
+/// 连接在 select! 中等待命令，通道关闭或未设置时永远挂起
+pub async fn next_command(cmd_rx: &mut Option<SubCommandReceiver>) -> SubCommand {
+    if let Some(rx) = cmd_rx.as_mut() {
+        if let Some(cmd) = rx.recv().await {
+            return cmd;
+        }
+    }
+    *cmd_rx = None;
+    std::future::pending().await
+}
+
+/// 把命令写入socket，并同步更新本连接的订阅消息，保证重连后订阅集合一致
+pub fn forward_command(
+    writer: &WsWriter,
+    cmd: SubCommand,
+    sub_msg: &mut Value,
+    sub_ack: &mut SubAckTracker,
+) -> Result<(), SendError<Message>> {
+    let parts = writer.send_op(&cmd.msg)?;
+    if cmd.subscribe {
+        sub_ack.expect(&parts);
+    }
+    merge_sub_args(sub_msg, &cmd);
+    Ok(())
+}
+
+fn merge_sub_args(sub_msg: &mut Value, cmd: &SubCommand) {
+    // 币安的订阅项在params中，okex/bybit在args中
+    let key = if sub_msg.get("params").is_some() {
+        "params"
+    } else {
+        "args"
+    };
+    let Some(items) = cmd.msg[key].as_array() else {
+        return;
+    };
+    let Some(current) = sub_msg[key].as_array_mut() else {
+        return;
+    };
+    if cmd.subscribe {
+        for item in items {
+            if !current.contains(item) {
+                current.push(item.clone());
+            }
+        }
+    } else {
+        current.retain(|item| !items.contains(item));
+    }
+}
@@ -1,6 +1,13 @@
 use crate::cfg::Config;
 use crate::connection::binance_conn::BinanceFuturesSnapshotQuery;
 use crate::connection::connection::construct_connection;
+use crate::connection::arbiter::{Arbiter, LEG_NAMES};
+use crate::connection::dedup::frame_key_fn;
+use crate::connection::live_sub::{
+    apply_symbol_diff, resubscribe, LiveBatch, SubCommandReceiver, SubCommandSender,
+};
+use crate::connection::registry::ConnectionRegistry;
+use crate::connection::rx_timestamp::RawFrame;
 use crate::mkt_msg::{SignalMsg, SignalSource};
 use crate::parser::binance_parser::{
     BinanceIncParser, BinanceSbeIncParser, BinanceSbeTradeParser, BinanceSignalParser,
@@ -8,18 +15,23 @@ use crate::parser::binance_parser::{
 };
 use crate::parser::bybit_parser::{BybitIncParser, BybitSignalParser, BybitTradeParser};
 use crate::parser::default_parser::Parser;
+use crate::parser::monitor::ParseMonitor;
 use crate::parser::okex_parser::{OkexIncParser, OkexSignalParser, OkexTradeParser};
-use crate::sub_msg::SubscribeMsgs;
+use crate::sub_msg::{construct_op_message, SubscribeMsgs};
 use bytes::Bytes;
 use chrono::{NaiveTime, TimeDelta, Utc};
 use log::{error, info};
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::{broadcast, watch, Notify};
+use tokio::sync::{broadcast, mpsc, watch, Notify};
 use tokio::task::JoinSet;
 use tokio::time::{Duration, Instant, MissedTickBehavior};
 
 //订阅逐笔行情，orderbook增量消息，通过parser处理后转发
 
+const ARBITER_CAPACITY: usize = 65536; // A/B仲裁记录的帧数
+const LEG_STATS_INTERVAL: Duration = Duration::from_secs(10); // A/B仲裁统计上报间隔
+
 pub fn next_target_instant(time_str: &str) -> Instant {
     if time_str == "--:--:--" {
         log::warn!("Using fallback time + 30 seconds from now");
@@ -64,7 +76,12 @@ pub struct MktDataConnectionManager {
     subscribe_msgs: SubscribeMsgs,             //所有的订阅消息
     mkt_tx: broadcast::Sender<Bytes>,          //行情消息转发通道（包含signal）
     global_shutdown_rx: watch::Receiver<bool>, //全局关闭信号
+    registry: Arc<ConnectionRegistry>,         //连接状态表
     tp_reset_notify: Arc<Notify>,              //tp重置消息通知
+    inc_batches: Vec<LiveBatch>,               //增量连接的batch，用于在线增减订阅
+    trade_batches: Vec<LiveBatch>,             //逐笔成交连接的batch
+    priority_inc_batches: Vec<LiveBatch>,      //重点symbol的增量batch
+    priority_trade_batches: Vec<LiveBatch>,    //重点symbol的逐笔成交batch
     join_set: JoinSet<()>,                     //任务集合
 }
 
@@ -73,6 +90,7 @@ impl MktDataConnectionManager {
         cfg: &Config,
         global_shutdown: &watch::Sender<bool>,
         mkt_tx: broadcast::Sender<Bytes>,
+        registry: Arc<ConnectionRegistry>,
     ) -> Self {
         let subscribe_msgs = SubscribeMsgs::new(&cfg).await;
         Self {
@@ -80,7 +98,12 @@ impl MktDataConnectionManager {
             subscribe_msgs: subscribe_msgs,
             mkt_tx: mkt_tx,
             global_shutdown_rx: global_shutdown.subscribe(),
+            registry,
             tp_reset_notify: Arc::new(Notify::new()),
+            inc_batches: Vec::new(),
+            trade_batches: Vec::new(),
+            priority_inc_batches: Vec::new(),
+            priority_trade_batches: Vec::new(),
             join_set: JoinSet::new(),
         }
     }
@@ -101,6 +124,70 @@ impl MktDataConnectionManager {
         Ok(())
     }
 
+    /// 拉取最新symbol列表，在运行中的连接上增减订阅，现有batch放不下的symbol新开batch
+    pub async fn refresh_symbols(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let new_symbols: HashSet<String> = self.cfg.get_symbols().await?.into_iter().collect();
+        let prev_symbols = self.subscribe_msgs.get_active_symbols();
+        if new_symbols == prev_symbols {
+            return Ok(());
+        }
+        let diff = SubscribeMsgs::compare_symbol_set(&prev_symbols, &new_symbols);
+        self.subscribe_msgs.set_active_symbols(new_symbols);
+
+        // 重点symbol只进出重点batch，普通symbol只进出普通batch
+        let exchange = self.cfg.get_exchange();
+        let (priority_diff, regular_diff) = diff.partition(|s| self.cfg.priority.is_priority(s));
+        for (diff, priority) in [(regular_diff, false), (priority_diff, true)] {
+            let batch_size = if priority {
+                self.cfg.priority.batch_size.max(1)
+            } else {
+                self.cfg.get_batch_size()
+            };
+
+            let inc_channel = SubscribeMsgs::get_inc_channel(&exchange);
+            let overflow = apply_symbol_diff(&exchange, self.inc_batches_mut(priority), &diff, batch_size);
+            for chunk in overflow.chunks(batch_size) {
+                let index = self.inc_batches_mut(priority).len();
+                let subscribe_msg = construct_op_message(&exchange, chunk, &inc_channel, true);
+                if let Some(cmd_tx) = self.spawn_inc_batch(index, subscribe_msg, priority).await {
+                    self.inc_batches_mut(priority)
+                        .push(LiveBatch::new(inc_channel.clone(), chunk.to_vec(), cmd_tx));
+                }
+            }
+
+            let trade_channel = SubscribeMsgs::get_trade_channel(&exchange);
+            let overflow = apply_symbol_diff(&exchange, self.trade_batches_mut(priority), &diff, batch_size);
+            for chunk in overflow.chunks(batch_size) {
+                let index = self.trade_batches_mut(priority).len();
+                let subscribe_msg = construct_op_message(&exchange, chunk, &trade_channel, true);
+                if let Some(cmd_tx) = self.spawn_trade_batch(index, subscribe_msg, priority).await {
+                    self.trade_batches_mut(priority).push(LiveBatch::new(
+                        trade_channel.clone(),
+                        chunk.to_vec(),
+                        cmd_tx,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn inc_batches_mut(&mut self, priority: bool) -> &mut Vec<LiveBatch> {
+        if priority {
+            &mut self.priority_inc_batches
+        } else {
+            &mut self.inc_batches
+        }
+    }
+
+    fn trade_batches_mut(&mut self, priority: bool) -> &mut Vec<LiveBatch> {
+        if priority {
+            &mut self.priority_trade_batches
+        } else {
+            &mut self.trade_batches
+        }
+    }
+
     pub async fn start_snapshot_task(&mut self) {
         let exchange = self.cfg.get_exchange().clone();
         let is_primary = self.cfg.is_primary;
@@ -154,18 +241,26 @@ impl MktDataConnectionManager {
                                     }
                                 };
                                 let rest_cfg = cfg_for_fetcher.binance_rest.clone();
+                                let options = match cfg_for_fetcher.connect_options("rest") {
+                                    Ok(options) => options,
+                                    Err(e) => {
+                                        error!("快照查询代理配置错误: {}", e);
+                                        return;
+                                    }
+                                };
                                 BinanceFuturesSnapshotQuery::start_fetching_depth(
                                     exchange_for_fetcher.as_str(),
                                     rest_cfg,
                                     symbols,
-                                    snapshot_tx_for_fetcher
+                                    snapshot_tx_for_fetcher,
+                                    options
                                 ).await;
                                 info!("为 {} 成功查询深度快照", exchange_for_fetcher);
                             });
                             
                             // Parse snapshot data and forward to mkt_tx
                             while let Ok(snapshot_data) = snapshot_raw_rx.recv().await {
-                                let _parsed_count = parser.parse(snapshot_data, &mkt_tx_for_snapshot);
+                                let _parsed_count = parser.parse(snapshot_data, 0, &mkt_tx_for_snapshot);
                             }
                         });
                         
@@ -211,180 +306,261 @@ impl MktDataConnectionManager {
     }
 
     pub async fn start_all_connections(&mut self) {
+        let exchange = self.cfg.get_exchange().clone();
         // 1. 启动所有增量连接
+        let inc_channel = SubscribeMsgs::get_inc_channel(&exchange);
+        // 重点batch排在前面，编号在重点与普通batch内各自从0开始
         for i in 0..self.subscribe_msgs.get_inc_subscribe_msg_len() {
-            let exchange = self.cfg.get_exchange().clone();
+            let priority = self.subscribe_msgs.is_priority_batch(i);
+            let index = self.inc_batches_mut(priority).len();
             let subscribe_msg = self.subscribe_msgs.get_inc_subscribe_msg(i).clone();
+            if let Some(cmd_tx) = self.spawn_inc_batch(index, subscribe_msg, priority).await {
+                let symbols = self.subscribe_msgs.get_symbol_batch(i).to_vec();
+                self.inc_batches_mut(priority)
+                    .push(LiveBatch::new(inc_channel.clone(), symbols, cmd_tx));
+            }
+        }
+
+        // 2. 启动所有交易连接
+        let trade_channel = SubscribeMsgs::get_trade_channel(&exchange);
+        for i in 0..self.subscribe_msgs.get_trade_subscribe_msg_len() {
+            let priority = self.subscribe_msgs.is_priority_batch(i);
+            let index = self.trade_batches_mut(priority).len();
+            let subscribe_msg = self.subscribe_msgs.get_trade_subscribe_msg(i).clone();
+            if let Some(cmd_tx) = self.spawn_trade_batch(index, subscribe_msg, priority).await {
+                let symbols = self.subscribe_msgs.get_symbol_batch(i).to_vec();
+                self.trade_batches_mut(priority)
+                    .push(LiveBatch::new(trade_channel.clone(), symbols, cmd_tx));
+            }
+        }
+
+        self.notify_tp_reset();
+
+        // 3、启动独立的时间信号源连接
+        let exchange = self.cfg.get_exchange().clone();
+
+        // 如果是币安且为主机，额外启动快照query
+        let url = self.cfg.market_ws_url();
+        let signal_subscribe_msg = self.subscribe_msgs.get_time_signal_subscribe_msg();
+
+        // Create signal parser based on exchange
+        let signal_parser: Box<dyn Parser> = match exchange.as_str() {
+            "binance-futures" | "binance" | "binance-spot" => {
+                Box::new(BinanceSignalParser::new(false))
+            }
+            "okex-swap" | "okex" => Box::new(OkexSignalParser::new(false)),
+            "bybit" | "bybit-spot" => Box::new(BybitSignalParser::new(false)),
+            _ => {
+                error!("Unsupported exchange for signal parser: {}", exchange);
+                return;
+            }
+        };
+
+        self.spawn_mkt_connection(
+            exchange,
+            url,
+            signal_subscribe_msg,
+            "time signal".to_string(),
+            signal_parser,
+        )
+        .await;
+
+        // 4. 启动币安快照查询任务（仅主节点且为币安交易所）
+        self.start_snapshot_task().await;
+
+        // 5. 启动本地冗余 timesignal 任务
+        self.start_local_timesignal_task();
 
-            // Create inc parser based on exchange (static dispatch for performance)
-            match exchange.as_str() {
-                "binance-futures" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = BinanceIncParser::new(true);
+        log::info!("All connections started...");
+    }
+    /// 启动一个增量batch，返回该连接的订阅命令发送端
+    async fn spawn_inc_batch(
+        &mut self,
+        index: usize,
+        subscribe_msg: serde_json::Value,
+        priority: bool,
+    ) -> Option<SubCommandSender> {
+        let exchange = self.cfg.get_exchange().clone();
+        let kind = if priority { "priority " } else { "" };
+        // Create inc parser based on exchange (static dispatch for performance)
+        match exchange.as_str() {
+            "binance-futures" => {
+                let url = self.cfg.market_ws_url();
+                let parser = BinanceIncParser::new(true);
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("inc msg batch {}", i),
+                        format!("{}inc msg batch {}", kind, index),
+                        "inc",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "binance" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = BinanceIncParser::new(false);
+                    .await,
+                )
+            }
+            "binance" => {
+                let url = self.cfg.market_ws_url();
+                let parser = BinanceIncParser::new(false);
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("inc msg batch {}", i),
+                        format!("{}inc msg batch {}", kind, index),
+                        "inc",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "binance-spot" => {
-                    let url = "wss://stream-sbe.binance.com:9443/ws".to_string();
-                    let parser = BinanceSbeIncParser::new();
+                    .await,
+                )
+            }
+            "binance-spot" => {
+                let url = self.cfg.sbe_ws_url();
+                let parser = BinanceSbeIncParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("sbe inc msg batch {}", i),
+                        format!("{}sbe inc msg batch {}", kind, index),
+                        "inc",
+                        priority,
+                        true, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "bybit" | "bybit-spot" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = BybitIncParser::new();
+                    .await,
+                )
+            }
+            "bybit" | "bybit-spot" => {
+                let url = self.cfg.market_ws_url();
+                let parser = BybitIncParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("inc msg batch {}", i),
+                        format!("{}inc msg batch {}", kind, index),
+                        "inc",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "okex-swap" | "okex" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = OkexIncParser::new();
+                    .await,
+                )
+            }
+            "okex-swap" | "okex" => {
+                let url = self.cfg.market_ws_url();
+                let parser = OkexIncParser::new(self.cfg.okex_checksum);
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("inc msg batch {}", i),
+                        format!("{}inc msg batch {}", kind, index),
+                        "inc",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                _ => {
-                    panic!("Unsupported exchange for trade parser: {}", exchange);
-                }
+                    .await,
+                )
+            }
+            _ => {
+                panic!("Unsupported exchange for inc parser: {}", exchange);
             }
         }
+    }
 
-        // 2. 启动所有交易连接
-        for i in 0..self.subscribe_msgs.get_trade_subscribe_msg_len() {
-            let exchange = self.cfg.get_exchange().clone();
-            let subscribe_msg = self.subscribe_msgs.get_trade_subscribe_msg(i).clone();
-
-            // Create trade parser based on exchange (static dispatch for performance)
-            match exchange.as_str() {
-                "binance-futures" | "binance" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = BinanceTradeParser::new();
+    /// 启动一个逐笔成交batch，返回该连接的订阅命令发送端
+    async fn spawn_trade_batch(
+        &mut self,
+        index: usize,
+        subscribe_msg: serde_json::Value,
+        priority: bool,
+    ) -> Option<SubCommandSender> {
+        let exchange = self.cfg.get_exchange().clone();
+        let kind = if priority { "priority " } else { "" };
+        // Create trade parser based on exchange (static dispatch for performance)
+        match exchange.as_str() {
+            "binance-futures" | "binance" => {
+                let url = self.cfg.market_ws_url();
+                let parser = BinanceTradeParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("trade msg batch {}", i),
+                        format!("{}trade msg batch {}", kind, index),
+                        "trade",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "binance-spot" => {
-                    let url = "wss://stream-sbe.binance.com:9443/ws".to_string();
-                    let parser = BinanceSbeTradeParser::new();
+                    .await,
+                )
+            }
+            "binance-spot" => {
+                let url = self.cfg.sbe_ws_url();
+                let parser = BinanceSbeTradeParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("sbe trade msg batch {}", i),
+                        format!("{}sbe trade msg batch {}", kind, index),
+                        "trade",
+                        priority,
+                        true, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "bybit" | "bybit-spot" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = BybitTradeParser::new();
+                    .await,
+                )
+            }
+            "bybit" | "bybit-spot" => {
+                let url = self.cfg.market_ws_url();
+                let parser = BybitTradeParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("trade msg batch {}", i),
+                        format!("{}trade msg batch {}", kind, index),
+                        "trade",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                "okex-swap" | "okex" => {
-                    let url = SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-                    let parser = OkexTradeParser::new();
+                    .await,
+                )
+            }
+            "okex-swap" | "okex" => {
+                let url = self.cfg.market_ws_url();
+                let parser = OkexTradeParser::new();
+                Some(
                     self.spawn_mkt_connection_typed(
                         exchange,
                         url,
                         subscribe_msg,
-                        format!("trade msg batch {}", i),
+                        format!("{}trade msg batch {}", kind, index),
+                        "trade",
+                        priority,
+                        false, // sbe
                         parser,
                     )
-                    .await;
-                }
-                _ => {
-                    error!("Unsupported exchange for trade parser: {}", exchange);
-                    continue;
-                }
-            };
-        }
-
-        self.notify_tp_reset();
-
-        // 3、启动独立的时间信号源连接
-        let exchange = self.cfg.get_exchange().clone();
-
-        // 如果是币安且为主机，额外启动快照query
-        let url = crate::sub_msg::SubscribeMsgs::get_exchange_mkt_data_url(&exchange).to_string();
-        let signal_subscribe_msg = self.subscribe_msgs.get_time_signal_subscribe_msg();
-
-        // Create signal parser based on exchange
-        let signal_parser: Box<dyn Parser> = match exchange.as_str() {
-            "binance-futures" | "binance" | "binance-spot" => {
-                Box::new(BinanceSignalParser::new(false))
+                    .await,
+                )
             }
-            "okex-swap" | "okex" => Box::new(OkexSignalParser::new(false)),
-            "bybit" | "bybit-spot" => Box::new(BybitSignalParser::new(false)),
             _ => {
-                error!("Unsupported exchange for signal parser: {}", exchange);
-                return;
+                error!("Unsupported exchange for trade parser: {}", exchange);
+                None
             }
-        };
-
-        self.spawn_mkt_connection(
-            exchange,
-            url,
-            signal_subscribe_msg,
-            "time signal".to_string(),
-            signal_parser,
-        )
-        .await;
-
-        // 4. 启动币安快照查询任务（仅主节点且为币安交易所）
-        self.start_snapshot_task().await;
-
-        // 5. 启动本地冗余 timesignal 任务
-        self.start_local_timesignal_task();
-
-        log::info!("All connections started...");
+        }
     }
+
     pub async fn shutdown(
         &mut self,
         global_shutdown: &watch::Sender<bool>,
@@ -393,6 +569,11 @@ impl MktDataConnectionManager {
         if let Err(e) = global_shutdown.send(true) {
             error!("Failed to shutdown MktConnectionManager: {}", e);
         }
+        // 连接退出后命令通道随之失效，重启时按新的订阅消息重建
+        self.inc_batches.clear();
+        self.trade_batches.clear();
+        self.priority_inc_batches.clear();
+        self.priority_trade_batches.clear();
         // 在drop时，等待所有任务完成
         let mut join_set = std::mem::take(&mut self.join_set); // 拿走 join_set，避免借用问题
         while let Some(result) = join_set.join_next().await {
@@ -406,66 +587,115 @@ impl MktDataConnectionManager {
     }
 
     // 泛型版本的连接函数，避免动态分发开销
+    // 开启redundant_connections时同一订阅建立A/B两条连接，parser前由Arbiter取先到的一份
+    // 重点batch使用priority配置的通道容量，priority.redundant开启时也建立A/B两条连接
+    #[allow(clippy::too_many_arguments)]
     async fn spawn_mkt_connection_typed<P>(
         &mut self,
         exchange: String,
         url: String,
         subscribe_msg: serde_json::Value,
         description: String,
+        group: &'static str,
+        priority: bool,
+        sbe: bool,
         parser: P,
-    ) where
+    ) -> SubCommandSender
+    where
         P: Parser + Send + 'static,
     {
         let mkt_tx = self.mkt_tx.clone();
         let global_shutdown_rx = self.global_shutdown_rx.clone();
+        let cfg = self.cfg.clone();
+        let registry = self.registry.clone();
+        let redundant =
+            self.cfg.redundant_connections || (priority && self.cfg.priority.redundant);
+        let capacity = if priority {
+            self.cfg.priority.channel_capacity.max(1)
+        } else {
+            8192
+        };
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        // 订单簿断档时parser请求在本batch的连接上重新订阅
+        let resync_tx = cmd_tx.clone();
+        let resync_channel = (group == "inc").then(|| SubscribeMsgs::get_inc_channel(&exchange));
 
         self.join_set.spawn(async move {
             // Create intermediate channel for raw WebSocket data
-            let (raw_tx, mut raw_rx) = broadcast::channel(8192);
-            
-            // Spawn WebSocket connection task
-            let ws_global_shutdown_rx = global_shutdown_rx.clone();
-            let ws_exchange = exchange.clone();
-            let ws_url = url.clone();
-            let ws_subscribe_msg = subscribe_msg.clone();
-            let ws_description = description.clone();
-            
-            tokio::spawn(async move {
-                let mut connection = match construct_connection(
-                    ws_exchange.clone(),
-                    format!("{}-{}", ws_exchange, ws_description),
-                    ws_url,
-                    ws_subscribe_msg,
-                    raw_tx,
-                    ws_global_shutdown_rx
-                ) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        error!("Failed to create connection for {}: {}", ws_description, e);
-                        return;
+            let (raw_tx, mut raw_rx) = broadcast::channel(capacity);
+            let (raw_tx_b, mut raw_rx_b) = broadcast::channel(capacity);
+            let batch_name = format!("{}-{}", exchange, description);
+
+            if redundant {
+                // 订阅变更命令同时下发给两条腿
+                let (cmd_tx_a, cmd_rx_a) = mpsc::unbounded_channel();
+                let (cmd_tx_b, cmd_rx_b) = mpsc::unbounded_channel();
+                let mut cmd_rx: SubCommandReceiver = cmd_rx;
+                tokio::spawn(async move {
+                    while let Some(cmd) = cmd_rx.recv().await {
+                        let _ = cmd_tx_a.send(cmd.clone());
+                        let _ = cmd_tx_b.send(cmd);
                     }
-                };
-                if let Err(e) = connection.start_ws().await {
-                    error!("Connection failed for {}: {}", ws_description, e);
-                } else {
-                    info!("Connection closed for {}", ws_description);
+                });
+                for (leg, leg_raw_tx, leg_cmd_rx) in
+                    [(LEG_NAMES[0], raw_tx, cmd_rx_a), (LEG_NAMES[1], raw_tx_b, cmd_rx_b)]
+                {
+                    spawn_ws_task(
+                        cfg.clone(),
+                        group,
+                        format!("{}-{}", batch_name, leg),
+                        url.clone(),
+                        subscribe_msg.clone(),
+                        leg_raw_tx,
+                        global_shutdown_rx.clone(),
+                        registry.clone(),
+                        Some(leg_cmd_rx),
+                        sbe,
+                    );
                 }
-            });
-            
+            } else {
+                drop(raw_tx_b);
+                spawn_ws_task(
+                    cfg.clone(),
+                    group,
+                    batch_name.clone(),
+                    url,
+                    subscribe_msg,
+                    raw_tx,
+                    global_shutdown_rx.clone(),
+                    registry.clone(),
+                    Some(cmd_rx),
+                    sbe,
+                );
+            }
+
             // Spawn parser task (静态分发，无虚函数开销)
             let mut shutdown_rx = global_shutdown_rx.clone();
+            let mut monitor = ParseMonitor::new(description.clone(), &cfg.parse_monitor);
             tokio::spawn(async move {
+                let mut arbiter = redundant
+                    .then(|| Arbiter::new(frame_key_fn(&exchange), ARBITER_CAPACITY));
+                let mut stats_timer = tokio::time::interval(LEG_STATS_INTERVAL);
+                // 冗余模式下一条腿退出后继续使用另一条腿
+                let mut legs_open = [true, redundant];
                 loop {
                     tokio::select! {
-                        msg_result = raw_rx.recv() => {
+                        msg_result = raw_rx.recv(), if legs_open[0] => {
                             match msg_result {
                                 Ok(raw_msg) => {
-                                    // 静态分发调用，编译时确定具体类型
-                                    let _parsed_count = parser.parse(raw_msg, &mkt_tx);
+                                    if arbiter.as_mut().is_none_or(|arbiter| arbiter.admit(0, &raw_msg.data)) {
+                                        // 静态分发调用，编译时确定具体类型
+                                        let outcome = parser.parse(raw_msg.data.clone(), raw_msg.recv_ts, &mkt_tx);
+                                        monitor.record(&outcome, &raw_msg.data);
+                                        resync_symbols(&parser, &exchange, resync_channel.as_deref(), &resync_tx);
+                                    }
                                 }
                                 Err(broadcast::error::RecvError::Closed) => {
                                     info!("Raw message channel closed for {}", description);
-                                    break;
+                                    legs_open[0] = false;
+                                    if !legs_open[1] {
+                                        break;
+                                    }
                                 }
                                 Err(broadcast::error::RecvError::Lagged(skipped)) => {
                                     // 如果正在关闭则不打印日志
@@ -476,6 +706,35 @@ impl MktDataConnectionManager {
                                 }
                             }
                         }
+                        msg_result = raw_rx_b.recv(), if legs_open[1] => {
+                            match msg_result {
+                                Ok(raw_msg) => {
+                                    if arbiter.as_mut().is_none_or(|arbiter| arbiter.admit(1, &raw_msg.data)) {
+                                        let outcome = parser.parse(raw_msg.data.clone(), raw_msg.recv_ts, &mkt_tx);
+                                        monitor.record(&outcome, &raw_msg.data);
+                                        resync_symbols(&parser, &exchange, resync_channel.as_deref(), &resync_tx);
+                                    }
+                                }
+                                Err(broadcast::error::RecvError::Closed) => {
+                                    info!("Raw message channel closed for {} leg B", description);
+                                    legs_open[1] = false;
+                                    if !legs_open[0] {
+                                        break;
+                                    }
+                                }
+                                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                    if !*shutdown_rx.borrow() {
+                                        error!("Market data parser lagged for {} leg B (skipped {} messages)", description, skipped);
+                                    }
+                                    continue;
+                                }
+                            }
+                        }
+                        _ = stats_timer.tick(), if arbiter.is_some() => {
+                            if let Some(arbiter) = arbiter.as_ref() {
+                                registry.record_leg_stats(&batch_name, arbiter.stats());
+                            }
+                        }
                         _ = shutdown_rx.changed() => {
                             if *shutdown_rx.borrow() {
                                 info!("Market data parser task shutdown for {}", description);
@@ -486,6 +745,7 @@ impl MktDataConnectionManager {
                 }
             });
         });
+        cmd_tx
     }
 
     async fn spawn_mkt_connection(
@@ -498,40 +758,27 @@ impl MktDataConnectionManager {
     ) {
         let mkt_tx = self.mkt_tx.clone();
         let global_shutdown_rx = self.global_shutdown_rx.clone();
+        let cfg = self.cfg.clone();
+        let registry = self.registry.clone();
 
         self.join_set.spawn(async move {
             // Create intermediate channel for raw WebSocket data
             let (raw_tx, mut raw_rx) = broadcast::channel(8192);
+            let mut monitor = ParseMonitor::new(description.clone(), &cfg.parse_monitor);
             
-            // Spawn WebSocket connection task
-            let ws_global_shutdown_rx = global_shutdown_rx.clone();
-            let ws_exchange = exchange.clone();
-            let ws_url = url.clone();
-            let ws_subscribe_msg = subscribe_msg.clone();
-            let ws_description = description.clone();
-            
-            tokio::spawn(async move {
-                let mut connection = match construct_connection(
-                    ws_exchange.clone(),
-                    format!("{}-{}", ws_exchange, ws_description),
-                    ws_url,
-                    ws_subscribe_msg,
-                    raw_tx,
-                    ws_global_shutdown_rx
-                ) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        error!("Failed to create connection for {}: {}", ws_description, e);
-                        return;
-                    }
-                };
-                if let Err(e) = connection.start_ws().await {
-                    error!("Connection failed for {}: {}", ws_description, e);
-                } else {
-                    info!("Connection closed for {}", ws_description);
-                }
-            });
-            
+            spawn_ws_task(
+                cfg,
+                "signal",
+                format!("{}-{}", exchange, description),
+                url,
+                subscribe_msg,
+                raw_tx,
+                global_shutdown_rx.clone(),
+                registry,
+                None,
+                false,
+            );
+
             // Spawn parser task
             let mut shutdown_rx = global_shutdown_rx.clone();
             tokio::spawn(async move {
@@ -540,7 +787,8 @@ impl MktDataConnectionManager {
                         msg_result = raw_rx.recv() => {
                             match msg_result {
                                 Ok(raw_msg) => {
-                                    let _parsed_count = parser.parse(raw_msg, &mkt_tx);
+                                    let outcome = parser.parse(raw_msg.data.clone(), raw_msg.recv_ts, &mkt_tx);
+                                    monitor.record(&outcome, &raw_msg.data);
                                 }
                                 Err(broadcast::error::RecvError::Closed) => {
                                     info!("Raw message channel closed for {}", description);
@@ -567,3 +815,60 @@ impl MktDataConnectionManager {
         });
     }
 }
+
+/// 把parser请求重新同步的symbol在本batch的连接上重新订阅
+fn resync_symbols<P: Parser>(
+    parser: &P,
+    exchange: &str,
+    channel: Option<&str>,
+    cmd_tx: &SubCommandSender,
+) {
+    let Some(channel) = channel else {
+        return;
+    };
+    let symbols = parser.take_resync();
+    if !symbols.is_empty() {
+        resubscribe(exchange, channel, &symbols, cmd_tx);
+    }
+}
+
+/// 启动一条websocket连接任务，原始消息写入raw_tx
+#[allow(clippy::too_many_arguments)]
+fn spawn_ws_task(
+    cfg: Config,
+    group: &'static str,
+    connection_name: String,
+    url: String,
+    subscribe_msg: serde_json::Value,
+    raw_tx: broadcast::Sender<RawFrame>,
+    shutdown_rx: watch::Receiver<bool>,
+    registry: Arc<ConnectionRegistry>,
+    cmd_rx: Option<SubCommandReceiver>,
+    sbe: bool,
+) {
+    tokio::spawn(async move {
+        let mut connection = match construct_connection(
+            &cfg,
+            group,
+            connection_name.clone(),
+            url,
+            subscribe_msg,
+            raw_tx,
+            shutdown_rx,
+            registry,
+            cmd_rx,
+            sbe,
+        ) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Failed to create connection for {}: {}", connection_name, e);
+                return;
+            }
+        };
+        if let Err(e) = connection.start_ws().await {
+            error!("Connection failed for {}: {}", connection_name, e);
+        } else {
+            info!("Connection closed for {}", connection_name);
+        }
+    });
+}
@@ -1,7 +1,20 @@
+pub mod arbiter;
+pub mod backoff;
 pub mod binance_conn;
 pub mod bybit_conn;
 pub mod connection;
+pub mod dedup;
 pub mod derivatives_metrics_manager;
+pub mod health;
+pub mod heartbeat;
 pub mod kline_manager;
+pub mod live_sub;
 pub mod mkt_manager;
 pub mod okex_conn;
+pub mod rate_limit;
+pub mod registry;
+pub mod resolver;
+pub mod rx_timestamp;
+pub mod sub_ack;
+pub mod tls;
+pub mod transport;
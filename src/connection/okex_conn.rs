@@ -1,11 +1,17 @@
 use crate::connection::connection::{
-    MktConnection, MktConnectionHandler, MktConnectionRunner, WsConnector,
+    MktConnection, MktConnectionHandler, MktConnectionRunner, ReplacementLeg, WsConnectionResult,
+    WsConnector,
 };
+use crate::connection::dedup::{okex_frame_key, FrameDedup, FrameVerdict};
+use crate::connection::heartbeat::{Heartbeat, HeartbeatAction};
+use crate::connection::live_sub::{forward_command, next_command};
+use crate::connection::sub_ack::{handle_ack_frame, AckFormat, SubAckTracker, SUB_ACK_TIMEOUT};
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures_util::{SinkExt, TryStreamExt};
 use log::{error, info, warn};
-use tokio::time::{self, Duration, Instant};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
 use tokio_tungstenite::tungstenite::Message;
 
 // okex
@@ -16,71 +22,202 @@ use tokio_tungstenite::tungstenite::Message;
 // 1. 每次接收到消息后，用户设置一个定时器，定时N秒，N 小于30。
 // 2. 如果定时器被触发（N 秒内没有收到新消息），发送字符串 'ping'。
 // 3. 期待一个文字字符串'pong'作为回应。如果在 N秒内未收到，请发出错误或重新连接。
+// 服务升级前okex会推送 {"event":"notice","code":"64008",...}，随后断开连接
+// 收到后提前建立替换连接，新连接开始推送后接替旧连接，重叠期内去重
 pub struct OkexConnection {
     base_connection: MktConnection,
     restart_count: u32,
 }
 
 impl OkexConnection {
+    const UPGRADE_NOTICE_CODE: &'static str = "64008";
+
     pub fn new(connection: MktConnection) -> Self {
         Self {
             base_connection: connection,
             restart_count: 0,
         }
     }
+
+    /// 后台建立替换连接，不阻塞旧socket的读取
+    fn spawn_replacement(&self) -> JoinHandle<anyhow::Result<WsConnectionResult>> {
+        let url = self.base_connection.url.clone();
+        let sub_msg = self.base_connection.sub_msg.clone();
+        let connection_name = self.base_connection.connection_name.clone();
+        let options = self.base_connection.connect_options.clone();
+        tokio::spawn(async move {
+            WsConnector::connect(&url, &sub_msg, &connection_name, &options).await
+        })
+    }
+
+    /// 解析notice事件，返回 (code, msg)
+    fn parse_notice(text: &str) -> Option<(String, String)> {
+        if !text.contains("\"notice\"") {
+            return None;
+        }
+        let value: Value = serde_json::from_str(text).ok()?;
+        if value["event"].as_str()? != "notice" {
+            return None;
+        }
+        Some((
+            value["code"].as_str().unwrap_or("").to_string(),
+            value["msg"].as_str().unwrap_or("").to_string(),
+        ))
+    }
 }
 
 #[async_trait]
 impl MktConnectionRunner for OkexConnection {
     async fn run_connection(&mut self) -> anyhow::Result<()> {
         //简化设计，全局其实只需要一个倒计时器
-        //初始阶段设置为25s + now，随新消息刷新
-        //当新消息收到后，持续重置倒计时
-        //当倒计时结束，发送ping消息，并设置ping-timeout-timer为25s + now
+        //初始阶段设置为 idle + now，随新消息刷新
+        //当倒计时结束，发送ping消息，并设置 pong_timeout + now
         //在此期间，如果收到新消息也不刷新，必须期待一个pong消息，否则重启websocket
-        //因此只需要一个倒计时 + bool waiting_pong 来判断是否在等待pong消息
-        let mut reset_timer: Instant = Instant::now() + Duration::from_secs(25); // 倒计时，初始值25s
-        let mut waiting_pong: bool = false; // 是否在等待pong消息，初始值false
+        //计时与waiting_pong的状态统一由 Heartbeat 维护
+        let mut heartbeat = Heartbeat::new(self.base_connection.heartbeat);
+        let WsConnectionResult {
+            mut reader,
+            mut writer,
+            sub_parts,
+            ..
+        } = self
+            .base_connection
+            .connection
+            .take()
+            .expect("run_connection called without connection");
+        let mut sub_ack = SubAckTracker::new(AckFormat::Okex, &sub_parts);
+        let mut pending_replacement: Option<JoinHandle<anyhow::Result<WsConnectionResult>>> = None;
+        let mut replacement: Option<ReplacementLeg> = None; // 服务升级期间的新socket
+        let mut dedup = FrameDedup::new(okex_frame_key, ReplacementLeg::DEDUP_CAPACITY);
+        let health = self.base_connection.health.clone();
         loop {
-            let mut ws_stream = self
-                .base_connection
-                .connection
-                .as_mut()
-                .unwrap()
-                .ws_stream
-                .lock()
-                .await;
+            // ====切换到新连接====
+            if replacement.as_ref().is_some_and(ReplacementLeg::handover_now) {
+                let next = replacement.take().unwrap();
+                next.take_over(&mut reader, &mut writer, &mut sub_ack, &mut dedup, &self.base_connection.connection_name);
+                heartbeat = Heartbeat::new(self.base_connection.heartbeat);
+            }
+
+            let replacement_deadline = replacement.as_ref().map(ReplacementLeg::deadline);
             tokio::select! {
                 // ===== 优先处理关闭信号 =====
                 _ = self.base_connection.shutdown_rx.changed() => {
                     let should_close = *self.base_connection.shutdown_rx.borrow();
                     if should_close {
-                        ws_stream.close(None).await?; // 发送 CLOSE 帧
+                        writer.close(); // 发送 CLOSE 帧
+                        if let Some(r) = replacement.take() {
+                            r.close();
+                        }
                         return Ok(());
                     }
                 }
                 // ====处理超时====
-                _ = time::sleep_until(reset_timer) => {
-                    // 如果正在等待pong消息，则重启websocket
-                    if waiting_pong {
-                        warn!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
-                        ws_stream.close(None).await?; // 发送 CLOSE 帧
-                        break;
-                    } else {
-                        // 发送ping消息
-                        if let Err(e) = ws_stream.send(Message::Ping(b"ping".to_vec())).await {
-                            error!("Failed to send ping message: {:?}", e);
+                _ = time::sleep_until(heartbeat.deadline()) => {
+                    match heartbeat.on_timeout() {
+                        // 如果正在等待pong消息，则重启websocket
+                        HeartbeatAction::Reconnect => {
+                            warn!("[{}] Ping timeout detected. reset connecting...", self.base_connection.connection_name);
+                            writer.close(); // 发送 CLOSE 帧
+                            health.record_close("ping timeout");
                             break;
                         }
-                        // 设置倒计时为25s + now
-                        reset_timer = Instant::now() + Duration::from_secs(25);
-                        // 设置等待pong消息
-                        waiting_pong = true;
-                        log::info!("[{}] Sent ping message: {:?}, reset timer to {:?}", self.base_connection.connection_name, b"ping", reset_timer);
+                        // 发送字符串ping
+                        HeartbeatAction::SendPing => {
+                            if let Err(e) = writer.send(Message::Text("ping".to_string())) {
+                                error!("Failed to send ping message: {:?}", e);
+                                break;
+                            }
+                            log::info!("[{}] Sent ping message, reset timer to {:?}", self.base_connection.connection_name, heartbeat.deadline());
+                        }
                     }
                 }
+                // ====替换连接建立完成====
+                result = async { pending_replacement.as_mut().unwrap().await }, if pending_replacement.is_some() => {
+                    pending_replacement = None;
+                    match result {
+                        Ok(Ok(connection)) => {
+                            replacement = Some(ReplacementLeg::start(connection, AckFormat::Okex, &mut dedup, &self.base_connection.connection_name));
+                        }
+                        Ok(Err(e)) => {
+                            warn!("[{}] Failed to open replacement connection: {:?}", self.base_connection.connection_name, e);
+                        }
+                        Err(e) => {
+                            warn!("[{}] Replacement task failed: {:?}", self.base_connection.connection_name, e);
+                        }
+                    }
+                }
+                _ = time::sleep_until(replacement_deadline.unwrap_or_else(Instant::now)), if replacement_deadline.is_some() => {
+                    if !replacement.as_mut().unwrap().on_deadline() {
+                        warn!("[{}] Replacement produced no data within {:?}, dropping it", self.base_connection.connection_name, ReplacementLeg::REPLACEMENT_TIMEOUT);
+                        replacement.take().unwrap().abandon(&mut dedup);
+                    }
+                }
+                // ====处理新连接的消息====
+                msg = async { replacement.as_mut().unwrap().try_next().await }, if replacement.is_some() => {
+                    let r = replacement.as_mut().unwrap();
+                    let data = match msg {
+                        Ok(Some(Message::Text(text))) => {
+                            if text == "pong" || (r.sub_ack.is_pending() && handle_ack_frame(&mut r.sub_ack, &text, &self.base_connection.connection_name, &self.base_connection.registry)) {
+                                None
+                            } else {
+                                Some(Bytes::from(text.into_bytes()))
+                            }
+                        }
+                        Ok(Some(Message::Binary(data))) => Some(Bytes::from(data)),
+                        Ok(Some(Message::Ping(_))) | Ok(Some(Message::Pong(_))) | Ok(Some(Message::Frame(_))) => None,
+                        other => {
+                            warn!("[{}] Replacement connection lost: {:?}", self.base_connection.connection_name, other);
+                            replacement.take().unwrap().abandon(&mut dedup);
+                            None
+                        }
+                    };
+                    let frame = data.and_then(|bytes| replacement.as_mut().unwrap().on_data(bytes, &mut dedup));
+                    if let Some(frame) = frame {
+                        if let Err(e) = self.base_connection.forward(frame) {
+                            error!("failed to broadcast message: {}", e);
+                            break;
+                        }
+                    }
+                }
+                // ====处理订阅回执超时====
+                _ = time::sleep_until(sub_ack.deadline()), if sub_ack.is_pending() => {
+                    error!("[{}] No subscription ack within {:?}, pending: {:?}. reset connecting...", self.base_connection.connection_name, SUB_ACK_TIMEOUT, sub_ack.pending_args());
+                    self.base_connection.registry.record_ack_timeout(&self.base_connection.connection_name);
+                    writer.close(); // 发送 CLOSE 帧
+                    health.record_close("subscription ack timeout");
+                    break;
+                }
+                // ====处理在线增减订阅====
+                cmd = next_command(&mut self.base_connection.cmd_rx) => {
+                    if let Some(r) = replacement.as_mut() {
+                        r.forward_command(&cmd);
+                    }
+                    if let Err(e) = forward_command(&writer, cmd, &mut self.base_connection.sub_msg, &mut sub_ack) {
+                        error!("[{}] Failed to send subscription change: {:?}", self.base_connection.connection_name, e);
+                        break;
+                    }
+                }
+                // ====长时间没有数据，由app通知重连====
+                _ = health.reconnect_requested() => {
+                    if health.is_stale() {
+                        warn!("[{}] No data received for too long. reset connecting...", self.base_connection.connection_name);
+                        writer.close(); // 发送 CLOSE 帧
+                        health.record_close("stale: no data");
+                        break;
+                    }
+                }
+                // ====写任务退出====
+                _ = writer.closed() => {
+                    error!("[{}] WebSocket writer stopped. reset connecting...", self.base_connection.connection_name);
+                    if let Some(r) = replacement.as_mut() {
+                        r.request_handover();
+                        continue;
+                    }
+                    health.record_close("writer stopped");
+                    break;
+                }
                 // ====处理ws消息====
-                msg = ws_stream.try_next() => {
+                msg = reader.try_next() => {
                     match msg {
                         Ok(Some(msg)) => {
                             match msg {
@@ -90,28 +227,48 @@ impl MktConnectionRunner for OkexConnection {
                                 }
                                 Message::Close(frame) => {
                                     warn!("Received close frame: {:?}", frame);
+                                    // 服务升级期间旧socket断开，直接由新socket接替
+                                    if let Some(r) = replacement.as_mut() {
+                                        r.request_handover();
+                                        continue;
+                                    }
+                                    health.record_close(format!("close frame: {:?}", frame));
                                     break;
                                 }
-                                Message::Pong(payload) => {
-                                    warn!("Unexpected pong message: {:?}", payload);
+                                Message::Pong(_) => {
+                                    heartbeat.on_pong();
                                 }
                                 Message::Text(text) => {
                                     // 收期待一个文字字符串'pong'作为回应
                                     if text.eq("pong") {
-                                        // 收到pong消息后，等待pong消息设置为false
-                                        waiting_pong = false;
-                                        // 重置倒计时
-                                        reset_timer = Instant::now() + Duration::from_secs(25);
-                                        log::info!("[{}] Received pong message: {:?}, reset timer to {:?}", self.base_connection.connection_name, text, reset_timer);
+                                        // 收到pong消息后，重置倒计时
+                                        heartbeat.on_pong();
+                                        log::info!("[{}] Received pong message: {:?}, reset timer to {:?}", self.base_connection.connection_name, text, heartbeat.deadline());
                                     }else{
                                         // 收到消息后，如果不是waiting for pong的状态，则重置倒计时
-                                        if !waiting_pong {
-                                            reset_timer = Instant::now() + Duration::from_secs(25);
-                                        }else{
+                                        if heartbeat.is_waiting_pong() {
                                             log::warn!("[{}] Receive msg when waiting for pong : {}", self.base_connection.connection_name, text);
                                         }
+                                        heartbeat.on_message();
+                                        // 订阅回执不转发给parser
+                                        if sub_ack.is_pending() && handle_ack_frame(&mut sub_ack, &text, &self.base_connection.connection_name, &self.base_connection.registry) {
+                                            continue;
+                                        }
+                                        // 服务升级通知：提前建立替换连接
+                                        if let Some((code, notice)) = Self::parse_notice(&text) {
+                                            warn!("[{}] Received notice {}: {}", self.base_connection.connection_name, code, notice);
+                                            self.base_connection.registry.record_notice(&self.base_connection.connection_name, &code, &notice);
+                                            if code == Self::UPGRADE_NOTICE_CODE && pending_replacement.is_none() && replacement.is_none() {
+                                                info!("[{}] Service upgrade pending, opening replacement connection", self.base_connection.connection_name);
+                                                pending_replacement = Some(self.spawn_replacement());
+                                            }
+                                            continue;
+                                        }
                                         let bytes = Bytes::from(text.into_bytes());
-                                        if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                        if dedup.admit(&bytes) == FrameVerdict::Duplicate {
+                                            continue;
+                                        }
+                                        if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                             //利用shutdown关闭
                                             error!("failed to broadcast message: {}", e);
                                             break;
@@ -119,8 +276,12 @@ impl MktConnectionRunner for OkexConnection {
                                     }
                                 }
                                 Message::Binary(data) => {
+                                    heartbeat.on_message();
                                     let bytes = Bytes::from(data);
-                                    if let Err(e) = self.base_connection.tx.send(bytes.clone()) {
+                                    if dedup.admit(&bytes) == FrameVerdict::Duplicate {
+                                        continue;
+                                    }
+                                    if let Err(e) = self.base_connection.forward(reader.frame(bytes)) {
                                         error!("failed to broadcast message: {}", e);
                                         break;
                                     }
@@ -133,29 +294,33 @@ impl MktConnectionRunner for OkexConnection {
                         Err(e) => {
                             self.restart_count += 1;
                             error!("[{}] WebSocket error (restart count: {}): {:?}", self.base_connection.connection_name, self.restart_count, e);
-                            // Debug: Print detailed error info and exit for troubleshooting
-                            error!("[{}] connection failed with detailed info (restart #{}):", self.base_connection.connection_name, self.restart_count);
-                            // error!("  URL: {}", &self.base_connection.url);
-                            // error!("  Subscription message: {}", serde_json::to_string_pretty(&self.base_connection.sub_msg).unwrap_or_else(|_| "Failed to serialize".to_string()));
-                            error!("  Error type: {:?}", e);
-                            error!("Exiting for debugging purposes...");
-
-                            // TODO(human) - Add exit mechanism here
-                            // You can either:
-                            // 1. std::process::exit(1); for immediate exit
-                            // 2. return Err(e.into()); to propagate error up
-                            // 3. Add conditional debugging flag to control exit behavior
-
+                            // 服务升级期间旧socket断开，直接由新socket接替
+                            if let Some(r) = replacement.as_mut() {
+                                r.request_handover();
+                                continue;
+                            }
+                            health.on_read_error(&e);
                             break;
                         }
                         Ok(None) => {
                             warn!("[{}] WebSocket connection closed by server", self.base_connection.connection_name);
+                            if let Some(r) = replacement.as_mut() {
+                                r.request_handover();
+                                continue;
+                            }
+                            health.record_close("closed by server");
                             break;
                         }
                     }
                 }
             }
         }
+        if let Some(handle) = pending_replacement {
+            handle.abort();
+        }
+        if let Some(r) = replacement {
+            r.close();
+        }
         return Ok(());
     }
 }
@@ -168,6 +333,7 @@ impl MktConnectionHandler for OkexConnection {
                 &self.base_connection.url,
                 &self.base_connection.sub_msg,
                 &self.base_connection.connection_name,
+                &self.base_connection.connect_options,
             )
             .await
             {
@@ -176,7 +342,9 @@ impl MktConnectionHandler for OkexConnection {
                         "[{}] Successfully connected at {:?}",
                         self.base_connection.connection_name, connection.connected_at
                     );
+                    let connected_at = connection.connected_at;
                     self.base_connection.connection = Some(connection);
+                    self.base_connection.on_connected();
                     self.run_connection().await?;
                     //检查shutdown的当前情况，如果是true则break
                     if *self.base_connection.shutdown_rx.borrow() {
@@ -186,6 +354,9 @@ impl MktConnectionHandler for OkexConnection {
                             "[{}] Connection closed, reconnecting... (total restart count: {})",
                             self.base_connection.connection_name, self.restart_count
                         );
+                        if !self.base_connection.on_disconnected(connected_at).await {
+                            break Ok(());
+                        }
                     }
                 }
                 Err(e) => {
@@ -193,7 +364,9 @@ impl MktConnectionHandler for OkexConnection {
                         "[{}] Failed to connect: {:?}",
                         self.base_connection.connection_name, e
                     );
-                    time::sleep(Duration::from_secs(5)).await;
+                    if !self.base_connection.on_connect_failed(e.to_string()).await {
+                        break Ok(());
+                    }
                 }
             }
         }
@@ -0,0 +1,297 @@
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+// 交易所限频
+// 建连按IP限频，由同一进程内的所有连接共享一个窗口；发往服务端的消息按连接限频，由每个连接的写任务执行
+// binance: 每IP 5分钟300次建连；每连接每秒10条（现货5条）客户端消息，ping/pong也计入
+// okex:    每IP 每秒3次建连；每连接每小时480次 subscribe/unsubscribe/login
+// bybit:   每IP 5分钟500次建连；现货每个订阅请求最多10个args
+// 订阅消息超过单次args上限时自动拆分为多条发送
+
+/// 滑动窗口：任意 period 时长内最多 limit 次
+#[derive(Debug)]
+struct Window {
+    limit: usize,
+    period: Duration,
+    history: VecDeque<Instant>, // 最近 limit 次的预定时间，单调递增
+}
+
+impl Window {
+    fn new(limit: usize, period: Duration) -> Self {
+        Self {
+            limit,
+            period,
+            history: VecDeque::with_capacity(limit),
+        }
+    }
+
+    /// 预定下一次的时间，调用方需要等到返回的时间点再执行
+    fn reserve(&mut self) -> Instant {
+        let now = Instant::now();
+        let at = if self.history.len() < self.limit {
+            now
+        } else {
+            (self.history[self.history.len() - self.limit] + self.period).max(now)
+        };
+        let at = self.history.back().map_or(at, |last| at.max(*last));
+        self.history.push_back(at);
+        while self.history.len() > self.limit {
+            self.history.pop_front();
+        }
+        at
+    }
+}
+
+/// 单个交易所的限频参数
+#[derive(Debug, Clone, Copy)]
+pub struct ExchangeLimits {
+    pub connections: Option<(usize, Duration)>, // 每IP建连次数
+    pub messages: Option<(usize, Duration)>,    // 每连接客户端消息数
+    pub count_control: bool,                    // ping/pong等控制帧是否计入消息数
+    pub max_args: Option<usize>,                // 单条订阅消息的args上限
+}
+
+impl ExchangeLimits {
+    pub fn for_exchange(exchange: &str) -> Self {
+        match exchange {
+            "binance-futures" => Self {
+                connections: Some((300, Duration::from_secs(300))),
+                messages: Some((10, Duration::from_secs(1))),
+                count_control: true,
+                max_args: None,
+            },
+            "binance" | "binance-spot" => Self {
+                connections: Some((300, Duration::from_secs(300))),
+                messages: Some((5, Duration::from_secs(1))),
+                count_control: true,
+                max_args: None,
+            },
+            "okex-swap" | "okex" => Self {
+                connections: Some((3, Duration::from_secs(1))),
+                messages: Some((480, Duration::from_secs(3600))),
+                count_control: false,
+                max_args: None,
+            },
+            "bybit" => Self {
+                connections: Some((500, Duration::from_secs(300))),
+                messages: None,
+                count_control: false,
+                max_args: None,
+            },
+            "bybit-spot" => Self {
+                connections: Some((500, Duration::from_secs(300))),
+                messages: None,
+                count_control: false,
+                max_args: Some(10),
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+impl Default for ExchangeLimits {
+    /// 不限频
+    fn default() -> Self {
+        Self {
+            connections: None,
+            messages: None,
+            count_control: false,
+            max_args: None,
+        }
+    }
+}
+
+/// 进程内共享的限频器，一个进程只服务一个交易所
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: ExchangeLimits,
+    connections: Option<Mutex<Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: ExchangeLimits) -> Self {
+        Self {
+            limits,
+            connections: limits
+                .connections
+                .map(|(limit, period)| Mutex::new(Window::new(limit, period))),
+        }
+    }
+
+    pub fn limits(&self) -> ExchangeLimits {
+        self.limits
+    }
+
+    /// 建连前调用，超出建连频率时等待到可用的时间点，返回等待的时长
+    pub async fn acquire_connection(&self) -> Duration {
+        let Some(window) = &self.connections else {
+            return Duration::ZERO;
+        };
+        let at = window.lock().unwrap().reserve();
+        let wait = at.saturating_duration_since(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep_until(at).await;
+        }
+        wait
+    }
+
+    /// 每个连接一个消息限频器
+    pub fn message_limiter(&self) -> MessageLimiter {
+        MessageLimiter {
+            window: self
+                .limits
+                .messages
+                .map(|(limit, period)| Window::new(limit, period)),
+            count_control: self.limits.count_control,
+        }
+    }
+}
+
+/// 单个连接的消息限频
+#[derive(Debug)]
+pub struct MessageLimiter {
+    window: Option<Window>,
+    count_control: bool,
+}
+
+/// 消息在写任务中的排队方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throttle {
+    Immediate, // 不计入限频，直接发送
+    Control,   // 计入限频的心跳/控制帧，排在订阅消息之前
+    Op,        // 订阅/取消订阅，按限频排队
+}
+
+impl MessageLimiter {
+    pub fn classify(&self, msg: &Message) -> Throttle {
+        if self.window.is_none() {
+            return Throttle::Immediate;
+        }
+        match msg {
+            Message::Ping(_) | Message::Pong(_) if self.count_control => Throttle::Control,
+            Message::Ping(_) | Message::Pong(_) | Message::Close(_) | Message::Frame(_) => {
+                Throttle::Immediate
+            }
+            // okex/bybit的文本ping不计入订阅请求数
+            Message::Text(text) if text.starts_with('{') => Throttle::Op,
+            Message::Text(_) if self.count_control => Throttle::Control,
+            Message::Text(_) => Throttle::Immediate,
+            Message::Binary(_) => Throttle::Op,
+        }
+    }
+
+    /// 为下一条计入限频的消息预留发送时间点
+    pub fn reserve(&mut self) -> Instant {
+        match self.window.as_mut() {
+            Some(window) => window.reserve(),
+            None => Instant::now(),
+        }
+    }
+
+    /// 发送前调用，需要限频的消息等待到可用的时间点
+    pub async fn acquire(&mut self, msg: &Message) {
+        if self.classify(msg) != Throttle::Immediate {
+            tokio::time::sleep_until(self.reserve()).await;
+        }
+    }
+}
+
+/// 按args上限拆分订阅/取消订阅消息，币安的订阅项在params中，okex/bybit在args中
+pub fn split_op_message(msg: &Value, max_args: Option<usize>) -> Vec<Value> {
+    let Some(max_args) = max_args.filter(|max_args| *max_args > 0) else {
+        return vec![msg.clone()];
+    };
+    let key = if msg.get("params").is_some() {
+        "params"
+    } else {
+        "args"
+    };
+    let Some(items) = msg[key].as_array().filter(|items| items.len() > max_args) else {
+        return vec![msg.clone()];
+    };
+    items
+        .chunks(max_args)
+        .map(|chunk| {
+            let mut part = msg.clone();
+            part[key] = Value::Array(chunk.to_vec());
+            part
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_window_reserve() {
+        let mut window = Window::new(3, Duration::from_secs(1));
+        let start = Instant::now();
+        let first: Vec<Instant> = (0..3).map(|_| window.reserve()).collect();
+        // 窗口未满时立即可用
+        assert!(first
+            .iter()
+            .all(|at| *at < start + Duration::from_millis(100)));
+        // 超过上限后依次排到窗口滑出的时间点
+        assert_eq!(window.reserve(), first[0] + Duration::from_secs(1));
+        assert_eq!(window.reserve(), first[1] + Duration::from_secs(1));
+        let last = window.reserve();
+        assert_eq!(last, first[2] + Duration::from_secs(1));
+        assert_eq!(window.reserve(), first[0] + Duration::from_secs(2));
+        assert!(
+            window.history.len() == 3
+                && window.history.back() == Some(&(first[0] + Duration::from_secs(2)))
+        );
+    }
+
+    #[test]
+    fn test_split_op_message() {
+        let msg = json!({"op": "subscribe", "args": ["a", "b", "c", "d", "e"]});
+        let parts = split_op_message(&msg, Some(2));
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], json!({"op": "subscribe", "args": ["a", "b"]}));
+        assert_eq!(parts[2], json!({"op": "subscribe", "args": ["e"]}));
+
+        // 币安的订阅项在params中
+        let msg = json!({"method": "SUBSCRIBE", "params": ["x", "y", "z"], "id": 1});
+        let parts = split_op_message(&msg, Some(2));
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            parts[1],
+            json!({"method": "SUBSCRIBE", "params": ["z"], "id": 1})
+        );
+
+        // 未超上限或未设置上限时原样返回
+        assert_eq!(split_op_message(&msg, Some(3)), vec![msg.clone()]);
+        assert_eq!(split_op_message(&msg, None), vec![msg.clone()]);
+        assert_eq!(split_op_message(&msg, Some(0)), vec![msg]);
+    }
+
+    #[test]
+    fn test_control_frames_bypass_ops() {
+        let limiter = RateLimiter::new(ExchangeLimits::for_exchange("okex")).message_limiter();
+        assert_eq!(
+            limiter.classify(&Message::Text("ping".into())),
+            Throttle::Immediate
+        );
+        assert_eq!(
+            limiter.classify(&Message::Pong(vec![])),
+            Throttle::Immediate
+        );
+        assert_eq!(limiter.classify(&Message::Close(None)), Throttle::Immediate);
+        assert_eq!(
+            limiter.classify(&Message::Text("{\"op\":\"subscribe\"}".into())),
+            Throttle::Op
+        );
+
+        // 币安的控制帧计入限频，但排在订阅消息之前
+        let limiter =
+            RateLimiter::new(ExchangeLimits::for_exchange("binance-futures")).message_limiter();
+        assert_eq!(limiter.classify(&Message::Pong(vec![])), Throttle::Control);
+        assert_eq!(limiter.classify(&Message::Close(None)), Throttle::Immediate);
+    }
+}
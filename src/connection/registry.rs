@@ -0,0 +1,267 @@
+use crate::connection::arbiter::LegStats;
+use crate::connection::backoff::BreakerState;
+use crate::connection::health::ConnectionHealth;
+use crate::connection::sub_ack::SubRejection;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// 单个连接的运行状态
+#[derive(Debug, Clone)]
+pub struct ConnectionStatus {
+    pub breaker: BreakerState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+    pub ack_timeouts: u64,                    // 订阅回执超时次数
+    pub rejected_args: BTreeMap<String, u32>, // 被交易所拒绝的订阅项及次数
+    pub notices: u64,                         // 交易所推送的notice事件次数（如okex服务升级）
+    pub last_notice: Option<String>,
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        Self {
+            breaker: BreakerState::Closed,
+            consecutive_failures: 0,
+            total_failures: 0,
+            last_error: None,
+            ack_timeouts: 0,
+            rejected_args: BTreeMap::new(),
+            notices: 0,
+            last_notice: None,
+        }
+    }
+}
+
+/// 所有连接共享的状态表，以connection_name为key，供app展示哪些batch处于降级状态
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    inner: Mutex<BTreeMap<String, ConnectionStatus>>,
+    quarantine: Mutex<BTreeMap<String, String>>, // 被隔离的订阅项及原因，registry由app持有，计划重启后仍然保留
+    legs: Mutex<BTreeMap<String, [LegStats; 2]>>, // A/B冗余batch的仲裁统计
+    health: Mutex<BTreeMap<String, Arc<ConnectionHealth>>>, // 运行中连接的健康状态
+    retired_health: Mutex<BTreeMap<String, Arc<ConnectionHealth>>>, // 已退出连接的健康状态，计划重启后同名连接继续使用
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_success(&self, connection_name: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        let status = inner.entry(connection_name.to_string()).or_default();
+        status.breaker = BreakerState::Closed;
+        status.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(
+        &self,
+        connection_name: &str,
+        breaker: BreakerState,
+        consecutive_failures: u32,
+        error: String,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let status = inner.entry(connection_name.to_string()).or_default();
+        status.breaker = breaker;
+        status.consecutive_failures = consecutive_failures;
+        status.total_failures += 1;
+        status.last_error = Some(error);
+    }
+
+    pub fn set_breaker(&self, connection_name: &str, breaker: BreakerState) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .entry(connection_name.to_string())
+            .or_default()
+            .breaker = breaker;
+    }
+
+    pub fn record_sub_rejections(&self, connection_name: &str, rejections: &[SubRejection]) {
+        if rejections.is_empty() {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let status = inner.entry(connection_name.to_string()).or_default();
+        for rejection in rejections {
+            *status
+                .rejected_args
+                .entry(rejection.arg.clone())
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn record_ack_timeout(&self, connection_name: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        let status = inner.entry(connection_name.to_string()).or_default();
+        status.ack_timeouts += 1;
+        status.last_error = Some("subscription ack timeout".to_string());
+    }
+
+    pub fn record_notice(&self, connection_name: &str, code: &str, msg: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        let status = inner.entry(connection_name.to_string()).or_default();
+        status.notices += 1;
+        status.last_notice = Some(format!("{} {}", code, msg));
+    }
+
+    /// 收到过notice事件的连接
+    pub fn noticed(&self) -> Vec<(String, ConnectionStatus)> {
+        self.inner
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, status)| status.notices > 0)
+            .map(|(name, status)| (name.clone(), status.clone()))
+            .collect()
+    }
+
+    pub fn quarantine(&self, arg: &str, reason: &str) {
+        self.quarantine
+            .lock()
+            .unwrap()
+            .insert(arg.to_string(), reason.to_string());
+    }
+
+    pub fn is_quarantined(&self, arg: &str) -> bool {
+        self.quarantine.lock().unwrap().contains_key(arg)
+    }
+
+    pub fn quarantined(&self) -> Vec<(String, String)> {
+        self.quarantine
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(arg, reason)| (arg.clone(), reason.clone()))
+            .collect()
+    }
+
+    pub fn record_leg_stats(&self, batch_name: &str, stats: [LegStats; 2]) {
+        self.legs
+            .lock()
+            .unwrap()
+            .insert(batch_name.to_string(), stats);
+    }
+
+    pub fn leg_stats(&self) -> Vec<(String, [LegStats; 2])> {
+        self.legs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stats)| (name.clone(), *stats))
+            .collect()
+    }
+
+    /// 连接的健康状态，不存在时创建
+    pub fn health(&self, connection_name: &str, group: &str) -> Arc<ConnectionHealth> {
+        self.health
+            .lock()
+            .unwrap()
+            .entry(connection_name.to_string())
+            .or_insert_with(|| {
+                self.retired_health
+                    .lock()
+                    .unwrap()
+                    .remove(connection_name)
+                    .unwrap_or_else(|| Arc::new(ConnectionHealth::new(group)))
+            })
+            .clone()
+    }
+
+    /// 连接任务退出，健康状态移出展示列表，同名连接重新建立时继续使用
+    /// 同名的新连接已经在使用该状态时保留
+    pub fn retire_health(&self, connection_name: &str, health: &Arc<ConnectionHealth>) {
+        let mut entries = self.health.lock().unwrap();
+        if entries
+            .get(connection_name)
+            .is_some_and(|entry| Arc::ptr_eq(entry, health) && Arc::strong_count(entry) <= 2)
+        {
+            if let Some(entry) = entries.remove(connection_name) {
+                self.retired_health
+                    .lock()
+                    .unwrap()
+                    .insert(connection_name.to_string(), entry);
+            }
+        }
+    }
+
+    /// 计划重启前调用，丢弃上一轮退出后没有被重新使用的连接（batch划分变化后不再存在的连接）
+    pub fn prune_retired_health(&self) {
+        self.retired_health.lock().unwrap().clear();
+    }
+
+    pub fn health_entries(&self) -> Vec<(String, Arc<ConnectionHealth>)> {
+        self.health
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, health)| (name.clone(), health.clone()))
+            .collect()
+    }
+
+    /// 熔断器不处于Closed状态的连接
+    pub fn degraded(&self) -> Vec<(String, ConnectionStatus)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .iter()
+            .filter(|(_, status)| status.breaker != BreakerState::Closed)
+            .map(|(name, status)| (name.clone(), status.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retired_health_is_reused_then_pruned() {
+        let registry = ConnectionRegistry::new();
+        let a = registry.health("binance-inc-0", "inc");
+        let b = registry.health("binance-inc-1", "inc");
+        assert_eq!(registry.health_entries().len(), 2);
+
+        // 计划重启：两个连接都退出，只有同名的a重新建立
+        registry.prune_retired_health();
+        registry.retire_health("binance-inc-0", &a);
+        registry.retire_health("binance-inc-1", &b);
+        assert!(registry.health_entries().is_empty());
+        assert!(Arc::ptr_eq(&registry.health("binance-inc-0", "inc"), &a));
+        let names: Vec<String> = registry
+            .health_entries()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["binance-inc-0".to_string()]);
+
+        // 下一次重启前丢弃没有被重新使用的b
+        registry.prune_retired_health();
+        assert!(!Arc::ptr_eq(&registry.health("binance-inc-1", "inc"), &b));
+    }
+
+    #[test]
+    fn retire_ignores_replaced_entry() {
+        let registry = ConnectionRegistry::new();
+        let old = registry.health("okex-inc-0", "inc");
+        registry.retire_health("okex-inc-0", &old);
+        let new = Arc::new(ConnectionHealth::new("inc"));
+        registry
+            .health
+            .lock()
+            .unwrap()
+            .insert("okex-inc-0".to_string(), new.clone());
+        // 旧连接晚退出时不会移走新连接的状态
+        registry.retire_health("okex-inc-0", &old);
+        assert_eq!(registry.health_entries().len(), 1);
+
+        // 新连接先于旧连接建立时两者共用同一份状态，旧连接退出后保留
+        let first = registry.health("bybit-inc-0", "inc");
+        let second = registry.health("bybit-inc-0", "inc");
+        registry.retire_health("bybit-inc-0", &first);
+        drop(first);
+        assert_eq!(registry.health_entries().len(), 2);
+        registry.retire_health("bybit-inc-0", &second);
+        assert_eq!(registry.health_entries().len(), 1);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::cfg::DnsCfg;
+use crate::connection::rx_timestamp::TimestampedStream;
+use crate::connection::transport::EgressPath;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::{self, Duration, Instant};
+
+// 交易所域名解析与IP优选
+// 交易所域名解析出多个IP，从不同机房到各IP的延迟差别明显
+// 定期解析每个域名并测量到每个IP的 TCP（wss时含TLS）握手耗时，连接固定使用最快的IP
+// 探测与连接使用同一出口路径（绑定的本地地址、出口代理、TLS后端），不同出口路径分别测量与排序
+// 支持静态host覆盖；当前IP连接失败后冷却一段时间，期间按耗时顺序尝试其他IP
+
+/// 单个IP的测量结果
+#[derive(Debug, Clone)]
+struct IpProbe {
+    ip: IpAddr,
+    handshake: Option<Duration>, // None表示探测失败
+    failed_at: Option<Instant>,  // 最近一次实际连接失败的时间
+}
+
+#[derive(Debug)]
+struct HostEntry {
+    host: String,
+    path: EgressPath,
+    port: u16,
+    tls: bool,
+    probes: Vec<IpProbe>, // 按握手耗时排序，探测失败的排在最后
+    refreshed_at: Instant,
+}
+
+impl HostEntry {
+    /// 按优先级排列的候选IP：冷却中的IP排在最后
+    fn candidates(&self, cooldown: Duration) -> Vec<IpAddr> {
+        let cooling = |probe: &IpProbe| probe.failed_at.is_some_and(|at| at.elapsed() < cooldown);
+        let mut ready: Vec<IpAddr> = Vec::with_capacity(self.probes.len());
+        let mut cooling_down: Vec<IpAddr> = Vec::new();
+        for probe in &self.probes {
+            if cooling(probe) {
+                cooling_down.push(probe.ip);
+            } else {
+                ready.push(probe.ip);
+            }
+        }
+        ready.extend(cooling_down);
+        ready
+    }
+}
+
+#[derive(Debug)]
+pub struct HostResolver {
+    overrides: HashMap<String, Vec<IpAddr>>,
+    refresh: Duration,
+    probe_timeout: Duration,
+    failure_cooldown: Duration,
+    hosts: Mutex<HashMap<String, HostEntry>>, // key为 域名|出口路径
+    refresh_started: AtomicBool,
+}
+
+impl HostResolver {
+    pub fn from_cfg(cfg: &DnsCfg) -> Self {
+        Self {
+            overrides: cfg.hosts.clone(),
+            refresh: Duration::from_secs(cfg.refresh_secs.max(1)),
+            probe_timeout: Duration::from_millis(cfg.probe_timeout_ms),
+            failure_cooldown: Duration::from_secs(cfg.failure_cooldown_secs),
+            hosts: Mutex::new(HashMap::new()),
+            refresh_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 按优先级依次连接候选IP，直到成功
+    pub async fn connect(
+        self: &Arc<Self>,
+        host: &str,
+        port: u16,
+        tls: bool,
+        path: &EgressPath,
+    ) -> io::Result<TcpStream> {
+        let key = format!("{}|{}", host, path.describe());
+        let candidates = match self.candidates(&key, host, port, tls, path).await {
+            Ok(candidates) => candidates,
+            Err(e) => match &path.proxy {
+                // 本地解析失败时仍可以由代理解析域名
+                Some(proxy) => {
+                    warn!(
+                        "Failed to resolve {} locally: {}, letting proxy resolve it",
+                        host, e
+                    );
+                    return proxy.connect(host, port, path.local_addr).await;
+                }
+                None => return Err(e),
+            },
+        };
+        let mut last_error = None;
+        for ip in candidates {
+            // 绑定本地地址直连时只能连接同一地址族，经由代理时绑定的是到代理的连接
+            if path.proxy.is_none()
+                && path
+                    .local_addr
+                    .is_some_and(|local| local.is_ipv4() != ip.is_ipv4())
+            {
+                continue;
+            }
+            match path.connect_ip(ip, port).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    warn!("Connect to {} via {} failed: {}", host, ip, e);
+                    self.report_failure(&key, ip);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("no usable address for {}", host),
+            )
+        }))
+    }
+
+    async fn candidates(
+        self: &Arc<Self>,
+        key: &str,
+        host: &str,
+        port: u16,
+        tls: bool,
+        path: &EgressPath,
+    ) -> io::Result<Vec<IpAddr>> {
+        {
+            let hosts = self.hosts.lock().unwrap();
+            if let Some(entry) = hosts.get(key) {
+                return Ok(entry.candidates(self.failure_cooldown));
+            }
+        }
+        // 首次经由该出口路径连接该域名：立即解析并测量，之后由后台任务定期刷新
+        let entry = self.probe_host(host, port, tls, path).await?;
+        let candidates = entry.candidates(self.failure_cooldown);
+        self.hosts.lock().unwrap().insert(key.to_string(), entry);
+        self.ensure_refresh_task();
+        Ok(candidates)
+    }
+
+    fn report_failure(&self, key: &str, ip: IpAddr) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(probe) = hosts
+            .get_mut(key)
+            .and_then(|entry| entry.probes.iter_mut().find(|probe| probe.ip == ip))
+        {
+            probe.failed_at = Some(Instant::now());
+        }
+    }
+
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+        if let Some(ips) = self.overrides.get(host) {
+            return Ok(ips.clone());
+        }
+        let mut ips: Vec<IpAddr> = Vec::new();
+        for addr in lookup_host((host, port)).await? {
+            if !ips.contains(&addr.ip()) {
+                ips.push(addr.ip());
+            }
+        }
+        Ok(ips)
+    }
+
+    async fn probe_host(
+        &self,
+        host: &str,
+        port: u16,
+        tls: bool,
+        path: &EgressPath,
+    ) -> io::Result<HostEntry> {
+        let ips = self.resolve(host, port).await?;
+        let mut probes = futures::future::join_all(ips.into_iter().map(|ip| async move {
+            IpProbe {
+                ip,
+                handshake: self.probe_ip(host, ip, port, tls, path).await,
+                failed_at: None,
+            }
+        }))
+        .await;
+        probes.sort_by_key(|probe| probe.handshake.unwrap_or(Duration::MAX));
+        let summary: Vec<String> = probes
+            .iter()
+            .map(|probe| match probe.handshake {
+                Some(handshake) => format!("{} {:?}", probe.ip, handshake),
+                None => format!("{} failed", probe.ip),
+            })
+            .collect();
+        info!(
+            "Resolved {} ({}): [{}]",
+            host,
+            path.describe(),
+            summary.join(", ")
+        );
+        Ok(HostEntry {
+            host: host.to_string(),
+            path: path.clone(),
+            port,
+            tls,
+            probes,
+            refreshed_at: Instant::now(),
+        })
+    }
+
+    /// 经由出口路径测量 TCP（及TLS）握手耗时，与实际连接使用同一TLS后端
+    async fn probe_ip(
+        &self,
+        host: &str,
+        ip: IpAddr,
+        port: u16,
+        tls: bool,
+        path: &EgressPath,
+    ) -> Option<Duration> {
+        let started = Instant::now();
+        let stream: TcpStream = time::timeout(self.probe_timeout, path.connect_ip(ip, port))
+            .await
+            .ok()?
+            .ok()?;
+        if tls {
+            time::timeout(
+                self.probe_timeout.saturating_sub(started.elapsed()),
+                path.tls_connect(host, TimestampedStream::new(stream, false)),
+            )
+            .await
+            .ok()?
+            .ok()?;
+        }
+        Some(started.elapsed())
+    }
+
+    fn ensure_refresh_task(self: &Arc<Self>) {
+        if self.refresh_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let resolver = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = time::interval(resolver.refresh);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                resolver.refresh_all().await;
+            }
+        });
+    }
+
+    /// 重新解析并测量所有已知域名，保留仍然有效的失败记录
+    async fn refresh_all(&self) {
+        let targets: Vec<(String, String, u16, bool, EgressPath)> = self
+            .hosts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| entry.refreshed_at.elapsed() >= self.refresh / 2)
+            .map(|(key, entry)| {
+                (
+                    key.clone(),
+                    entry.host.clone(),
+                    entry.port,
+                    entry.tls,
+                    entry.path.clone(),
+                )
+            })
+            .collect();
+        for (key, host, port, tls, path) in targets {
+            match self.probe_host(&host, port, tls, &path).await {
+                Ok(mut entry) => {
+                    let mut hosts = self.hosts.lock().unwrap();
+                    if let Some(previous) = hosts.get(&key) {
+                        for probe in entry.probes.iter_mut() {
+                            probe.failed_at = previous
+                                .probes
+                                .iter()
+                                .find(|old| old.ip == probe.ip)
+                                .and_then(|old| old.failed_at);
+                        }
+                    }
+                    hosts.insert(key, entry);
+                }
+                Err(e) => warn!("Failed to refresh DNS for {}: {}", host, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use bytes::Bytes;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+// 内核接收时间戳
+// websocket的TCP socket开启 SO_TIMESTAMPING（接收方向的软件时间戳），读数据时用recvmsg取出控制消息中的时间
+// 只使用内核软件时间（CLOCK_REALTIME）；硬件原始时间是网卡PHC时钟的读数，未与系统时钟同步时不是UTC，不使用
+// SO_TIMESTAMPING 不可用时退回 SO_TIMESTAMPNS；非linux平台不开启，接收时间为0
+// 连接读到一帧后取该socket最近一次recvmsg的时间作为帧的接收时间
+// TLS记录可能包含多帧，同一次读出的帧时间相同；一帧跨多次读时取最后一段到达的时间
+
+/// socket最近一次读到数据的内核接收时间（纳秒，UTC），0表示没有时间戳
+#[derive(Debug, Clone, Default)]
+pub struct RecvClock(Arc<AtomicI64>);
+
+impl RecvClock {
+    pub fn load(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn store(&self, ts: i64) {
+        self.0.store(ts, Ordering::Relaxed);
+    }
+}
+
+/// 连接转发给parser的原始帧
+#[derive(Debug, Clone)]
+pub struct RawFrame {
+    pub data: Bytes,
+    pub recv_ts: i64, // 内核接收时间（纳秒），未开启时为0
+}
+
+impl RawFrame {
+    pub fn new(data: Bytes, recv_ts: i64) -> Self {
+        Self { data, recv_ts }
+    }
+}
+
+/// 读取时记录内核接收时间的TCP流
+#[derive(Debug)]
+pub struct TimestampedStream {
+    inner: TcpStream,
+    clock: RecvClock,
+    enabled: bool,
+}
+
+impl TimestampedStream {
+    /// enable为false或开启失败时按普通TCP流读取
+    pub fn new(inner: TcpStream, enable: bool) -> Self {
+        let enabled = enable && enable_rx_timestamps(&inner);
+        Self {
+            inner,
+            clock: RecvClock::default(),
+            enabled,
+        }
+    }
+
+    pub fn clock(&self) -> RecvClock {
+        self.clock.clone()
+    }
+}
+
+impl AsyncRead for TimestampedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.enabled {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+        loop {
+            std::task::ready!(this.inner.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match this.inner.try_io(tokio::io::Interest::READABLE, || {
+                recv_timestamped(&this.inner, unfilled)
+            }) {
+                Ok((n, ts)) => {
+                    if let Some(ts) = ts {
+                        this.clock.store(ts);
+                    }
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+impl AsyncWrite for TimestampedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn enable_rx_timestamps(stream: &TcpStream) -> bool {
+    use log::warn;
+    use std::os::fd::AsRawFd;
+
+    let fd = stream.as_raw_fd();
+    let flags: libc::c_uint = libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE;
+    if set_sock_opt(fd, libc::SO_TIMESTAMPING, flags as libc::c_int).is_ok() {
+        return true;
+    }
+    match set_sock_opt(fd, libc::SO_TIMESTAMPNS, 1) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("Failed to enable kernel receive timestamps: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn enable_rx_timestamps(_stream: &TcpStream) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn set_sock_opt(fd: libc::c_int, opt: libc::c_int, value: libc::c_int) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            opt,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// recvmsg读取数据，返回读到的字节数和控制消息中的接收时间
+#[cfg(target_os = "linux")]
+fn recv_timestamped(stream: &TcpStream, buf: &mut [u8]) -> io::Result<(usize, Option<i64>)> {
+    use std::os::fd::AsRawFd;
+
+    // 足够容纳 SCM_TIMESTAMPING（3个timespec）的控制消息，按u64对齐
+    let mut control = [0u64; 16];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+    let n = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, 0) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut ts = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let header = &*cmsg;
+            if header.cmsg_level == libc::SOL_SOCKET {
+                let data = libc::CMSG_DATA(cmsg) as *const libc::timespec;
+                if header.cmsg_type == libc::SCM_TIMESTAMPING {
+                    // [软件时间, 已废弃, 硬件原始时间]，只取软件时间
+                    let stamps = std::ptr::read_unaligned(data as *const [libc::timespec; 3]);
+                    ts = timespec_ns(&stamps[0]);
+                } else if header.cmsg_type == libc::SCM_TIMESTAMPNS {
+                    ts = timespec_ns(&std::ptr::read_unaligned(data));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Ok((n as usize, ts))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn recv_timestamped(_stream: &TcpStream, _buf: &mut [u8]) -> io::Result<(usize, Option<i64>)> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(target_os = "linux")]
+fn timespec_ns(ts: &libc::timespec) -> Option<i64> {
+    let ns = ts.tv_sec * 1_000_000_000 + ts.tv_nsec;
+    (ns != 0).then_some(ns)
+}
+
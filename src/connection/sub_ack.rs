@@ -0,0 +1,349 @@
+use crate::connection::registry::ConnectionRegistry;
+use log::{info, warn};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::{Duration, Instant};
+
+// 订阅回执校验
+// 发送订阅消息成功不代表订阅成功，各交易所的回执格式不同：
+// binance: {"result":null,"id":1} 表示整批成功，{"error":{"code":..,"msg":..},"id":1} 表示失败
+// okex:    每个arg回复一条 {"event":"subscribe","arg":{...}}，失败时回复 {"event":"error","msg":..}
+// bybit:   每个请求回复一条 {"success":true/false,"ret_msg":..,"op":"subscribe","req_id":..}
+// binance/bybit的回执按请求id对应到发送的那一条消息：发送前给每条（拆分后的）消息分配进程内唯一的id
+// 未登记的id（如取消订阅的回执）只消费不计数
+// 超时仍有未确认的arg时，连接视为失败，交由start_ws走重连流程
+
+pub const SUB_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 给即将发送的订阅/取消订阅消息分配请求id：币安写入id，bybit写入req_id，okex按arg确认不需要
+pub fn stamp_request_id(msg: &mut Value) {
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    if msg.get("params").is_some() {
+        msg["id"] = Value::from(id);
+    } else if msg["args"]
+        .as_array()
+        .is_some_and(|args| args.iter().all(Value::is_string))
+    {
+        msg["req_id"] = Value::from(id.to_string());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckFormat {
+    Binance,
+    Okex,
+    Bybit,
+}
+
+/// 被交易所拒绝的订阅项
+#[derive(Debug, Clone)]
+pub struct SubRejection {
+    pub arg: String,
+    pub reason: String,
+}
+
+pub struct SubAckTracker {
+    format: AckFormat,
+    pending: BTreeSet<String>,              // 尚未收到回执的订阅项
+    requests: HashMap<String, Vec<String>>, // 请求id -> 该请求中尚未确认的订阅项（binance/bybit）
+    accepted: usize,
+    rejected: usize,
+    deadline: Instant,
+}
+
+impl SubAckTracker {
+    /// parts为建连时实际发送的订阅消息（已拆分并带请求id）
+    pub fn new(format: AckFormat, parts: &[Value]) -> Self {
+        let mut tracker = Self {
+            format,
+            pending: BTreeSet::new(),
+            requests: HashMap::new(),
+            accepted: 0,
+            rejected: 0,
+            deadline: Instant::now() + SUB_ACK_TIMEOUT,
+        };
+        tracker.expect(parts);
+        tracker
+    }
+
+    /// 登记已发送的订阅消息，连接运行中追加订阅时同样调用，新订阅项需要在超时内得到回执
+    pub fn expect(&mut self, parts: &[Value]) {
+        if self.pending.is_empty() {
+            self.deadline = Instant::now() + SUB_ACK_TIMEOUT;
+        }
+        for part in parts {
+            let args = sub_args(self.format, part);
+            if let Some(id) = request_id(part) {
+                self.requests.insert(id, args.iter().cloned().collect());
+            }
+            self.pending.extend(args);
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn pending_args(&self) -> Vec<String> {
+        self.pending.iter().cloned().collect()
+    }
+
+    /// 尝试把文本消息当作订阅回执处理
+    /// 返回None表示不是回执，需要继续转发给parser；返回Some时附带被拒绝的订阅项
+    pub fn on_text(&mut self, text: &str) -> Option<Vec<SubRejection>> {
+        // 行情消息远多于回执，先做字符串过滤，避免每条消息都反序列化
+        let maybe_ack = match self.format {
+            AckFormat::Binance => text.contains("\"result\"") || text.contains("\"error\""),
+            AckFormat::Okex => text.contains("\"event\""),
+            AckFormat::Bybit => text.contains("\"success\""),
+        };
+        if !maybe_ack {
+            return None;
+        }
+        let msg: Value = serde_json::from_str(text).ok()?;
+        match self.format {
+            AckFormat::Binance => self.on_binance(&msg),
+            AckFormat::Okex => self.on_okex(&msg),
+            AckFormat::Bybit => self.on_bybit(&msg),
+        }
+    }
+
+    fn on_binance(&mut self, msg: &Value) -> Option<Vec<SubRejection>> {
+        let id = request_id(msg)?;
+        if msg.get("error").is_none() && msg.get("result").is_none() {
+            return None;
+        }
+        let args = self.requests.remove(&id).unwrap_or_default();
+        if let Some(error) = msg.get("error") {
+            // 币安的错误回执表示整个请求未被处理
+            let reason = error["msg"].as_str().unwrap_or("unknown error").to_string();
+            return Some(self.reject(args, &reason));
+        }
+        self.accept(args);
+        Some(Vec::new())
+    }
+
+    fn on_okex(&mut self, msg: &Value) -> Option<Vec<SubRejection>> {
+        match msg["event"].as_str()? {
+            "subscribe" => {
+                if self.pending.remove(&okex_arg_key(&msg["arg"])) {
+                    self.accepted += 1;
+                }
+                Some(Vec::new())
+            }
+            "error" => {
+                let reason = format!(
+                    "{} {}",
+                    msg["code"].as_str().unwrap_or("-"),
+                    msg["msg"].as_str().unwrap_or("unknown error")
+                );
+                // okex的错误消息不带arg，只能通过msg中的instId匹配，匹配不上时等待超时处理
+                let matched: Vec<String> = self
+                    .pending
+                    .iter()
+                    .filter(|key| {
+                        key.split_once(':')
+                            .is_some_and(|(_, inst)| mentions(&reason, inst))
+                    })
+                    .cloned()
+                    .collect();
+                if matched.is_empty() {
+                    return Some(vec![SubRejection {
+                        arg: "unknown".to_string(),
+                        reason,
+                    }]);
+                }
+                Some(self.reject(matched, &reason))
+            }
+            _ => None,
+        }
+    }
+
+    fn on_bybit(&mut self, msg: &Value) -> Option<Vec<SubRejection>> {
+        if msg["op"].as_str()? != "subscribe" {
+            return None;
+        }
+        let args = request_id(msg)
+            .and_then(|id| self.requests.remove(&id))
+            .unwrap_or_default();
+        if msg["success"].as_bool().unwrap_or(false) {
+            self.accept(args);
+            return Some(Vec::new());
+        }
+        let reason = msg["ret_msg"]
+            .as_str()
+            .unwrap_or("unknown error")
+            .to_string();
+        Some(self.reject_matching(args, &reason))
+    }
+
+    fn accept(&mut self, args: Vec<String>) {
+        for arg in args {
+            if self.pending.remove(&arg) {
+                self.accepted += 1;
+            }
+        }
+    }
+
+    /// 一个请求的回执失败时，能从错误信息中定位到的订阅项记为拒绝，定位不到则整个请求记为拒绝
+    fn reject_matching(&mut self, args: Vec<String>, reason: &str) -> Vec<SubRejection> {
+        let (matched, rest): (Vec<String>, Vec<String>) =
+            args.into_iter().partition(|arg| mentions(reason, arg));
+        if matched.is_empty() {
+            return self.reject(rest, reason);
+        }
+        let rejections = self.reject(matched, reason);
+        // 一个请求只有一条回执，该请求中剩余的订阅项视为成功
+        self.accept(rest);
+        rejections
+    }
+
+    fn reject(&mut self, args: Vec<String>, reason: &str) -> Vec<SubRejection> {
+        args.into_iter()
+            .filter(|arg| self.pending.remove(arg))
+            .map(|arg| {
+                self.rejected += 1;
+                SubRejection {
+                    arg,
+                    reason: reason.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// 连接在订阅回执未完成时对每条文本消息调用，返回true表示消息是回执，不需要转发
+pub fn handle_ack_frame(
+    tracker: &mut SubAckTracker,
+    text: &str,
+    connection_name: &str,
+    registry: &ConnectionRegistry,
+) -> bool {
+    let Some(rejections) = tracker.on_text(text) else {
+        return false;
+    };
+    for rejection in &rejections {
+        warn!(
+            "[{}] Subscription rejected: {} ({})",
+            connection_name, rejection.arg, rejection.reason
+        );
+    }
+    registry.record_sub_rejections(connection_name, &rejections);
+    if !tracker.is_pending() {
+        info!(
+            "[{}] Subscription acknowledged: {} accepted, {} rejected",
+            connection_name, tracker.accepted, tracker.rejected
+        );
+    }
+    true
+}
+
+fn sub_args(format: AckFormat, sub_msg: &Value) -> BTreeSet<String> {
+    match format {
+        AckFormat::Binance => str_array(&sub_msg["params"]),
+        AckFormat::Bybit => str_array(&sub_msg["args"]),
+        AckFormat::Okex => sub_msg["args"]
+            .as_array()
+            .map(|args| args.iter().map(okex_arg_key).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// 消息中的请求id，币安为数字id，bybit为字符串req_id
+fn request_id(msg: &Value) -> Option<String> {
+    match msg.get("id").or_else(|| msg.get("req_id"))? {
+        Value::Number(id) => Some(id.to_string()),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// 错误信息中是否完整出现了该订阅项，避免 BTC-USDT 匹配到 BTC-USDT-SWAP
+fn mentions(reason: &str, token: &str) -> bool {
+    reason
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@')))
+        .any(|word| word == token)
+}
+
+fn str_array(value: &Value) -> BTreeSet<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// okex的订阅项为对象，用 channel:instId 作为key，全市场频道用instType等字段代替instId
+fn okex_arg_key(arg: &Value) -> String {
+    let target = ["instId", "instType", "instFamily", "ccy"]
+        .iter()
+        .find_map(|field| arg[*field].as_str())
+        .unwrap_or("");
+    format!("{}:{}", arg["channel"].as_str().unwrap_or(""), target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stamped(mut msg: Value) -> Value {
+        stamp_request_id(&mut msg);
+        msg
+    }
+
+    #[test]
+    fn binance_ack_only_clears_its_own_request() {
+        let sub = stamped(json!({"method": "SUBSCRIBE", "params": ["btcusdt@depth"], "id": 1}));
+        let mut tracker = SubAckTracker::new(AckFormat::Binance, std::slice::from_ref(&sub));
+        // 重新订阅时先发送的UNSUBSCRIBE的回执
+        let unsub = stamped(json!({"method": "UNSUBSCRIBE", "params": ["ethusdt@depth"], "id": 1}));
+        let ack = format!(r#"{{"result":null,"id":{}}}"#, unsub["id"]);
+        assert!(tracker.on_text(&ack).is_some());
+        assert!(tracker.is_pending());
+        let ack = format!(r#"{{"result":null,"id":{}}}"#, sub["id"]);
+        assert!(tracker.on_text(&ack).is_some());
+        assert!(!tracker.is_pending());
+    }
+
+    #[test]
+    fn bybit_split_acks_are_matched_by_req_id() {
+        let first = stamped(json!({"op": "subscribe", "args": ["orderbook.50.BTCUSDT"]}));
+        let second = stamped(json!({"op": "subscribe", "args": ["orderbook.50.ETHUSDT"]}));
+        let mut tracker = SubAckTracker::new(AckFormat::Bybit, &[first.clone(), second]);
+        let ack = format!(
+            r#"{{"success":true,"op":"subscribe","req_id":{}}}"#,
+            first["req_id"]
+        );
+        assert!(tracker.on_text(&ack).unwrap().is_empty());
+        assert_eq!(
+            tracker.pending_args(),
+            vec!["orderbook.50.ETHUSDT".to_string()]
+        );
+    }
+
+    #[test]
+    fn okex_error_matches_exact_inst_id() {
+        let sub = json!({"op": "subscribe", "args": [
+            {"channel": "books", "instId": "BTC-USDT"},
+            {"channel": "books", "instId": "BTC-USDT-SWAP"},
+        ]});
+        let mut tracker = SubAckTracker::new(AckFormat::Okex, &[sub]);
+        let error = r#"{"event":"error","code":"60018","msg":"Wrong URL or channel:books,instId:BTC-USDT-SWAP doesn't exist."}"#;
+        let rejections = tracker.on_text(error).unwrap();
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].arg, "books:BTC-USDT-SWAP");
+        assert_eq!(tracker.pending_args(), vec!["books:BTC-USDT".to_string()]);
+    }
+}
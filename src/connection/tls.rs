@@ -0,0 +1,177 @@
+use crate::cfg::{TlsBackend, TlsCfg};
+use crate::connection::rx_timestamp::{RecvClock, TimestampedStream};
+#[cfg(feature = "rustls")]
+use log::info;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_native_tls::native_tls;
+
+// TLS后端
+// native-tls 为默认后端；编译时启用 rustls feature 后可在配置中选择 rustls
+// rustls 的客户端配置在进程内共享，会话票据缓存在其中，重连（计划重启、24h换连接）时同一host可以复用会话，省去完整握手
+
+#[cfg(feature = "rustls")]
+const SESSION_CACHE_SIZE: usize = 256; // rustls缓存的会话数
+
+/// websocket底层的传输流
+pub enum WsTransport {
+    Plain(TimestampedStream),
+    Native(Box<tokio_native_tls::TlsStream<TimestampedStream>>),
+    #[cfg(feature = "rustls")]
+    Rustls(Box<tokio_rustls::client::TlsStream<TimestampedStream>>),
+}
+
+impl WsTransport {
+    /// 底层TCP流的内核接收时间
+    pub fn recv_clock(&self) -> RecvClock {
+        match self {
+            WsTransport::Plain(stream) => stream.clock(),
+            WsTransport::Native(stream) => stream.get_ref().get_ref().get_ref().clock(),
+            #[cfg(feature = "rustls")]
+            WsTransport::Rustls(stream) => stream.get_ref().0.clock(),
+        }
+    }
+}
+
+impl AsyncRead for WsTransport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            WsTransport::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            WsTransport::Native(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            #[cfg(feature = "rustls")]
+            WsTransport::Rustls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for WsTransport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            WsTransport::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            WsTransport::Native(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            #[cfg(feature = "rustls")]
+            WsTransport::Rustls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            WsTransport::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            WsTransport::Native(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            #[cfg(feature = "rustls")]
+            WsTransport::Rustls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            WsTransport::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            WsTransport::Native(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            #[cfg(feature = "rustls")]
+            WsTransport::Rustls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// 进程内共享的TLS客户端
+pub struct TlsClient {
+    native: tokio_native_tls::TlsConnector,
+    #[cfg(feature = "rustls")]
+    rustls: Option<tokio_rustls::TlsConnector>, // 选择rustls后端时设置
+}
+
+impl std::fmt::Debug for TlsClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsClient")
+            .field("backend", &self.backend())
+            .finish()
+    }
+}
+
+impl TlsClient {
+    pub fn from_cfg(cfg: &TlsCfg) -> anyhow::Result<Self> {
+        let native = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+        match cfg.backend {
+            TlsBackend::Native => Ok(Self {
+                native,
+                #[cfg(feature = "rustls")]
+                rustls: None,
+            }),
+            #[cfg(feature = "rustls")]
+            TlsBackend::Rustls => Ok(Self {
+                native,
+                rustls: Some(rustls_connector(cfg)?),
+            }),
+            #[cfg(not(feature = "rustls"))]
+            TlsBackend::Rustls => Err(anyhow::anyhow!(
+                "tls.backend is rustls but the binary was built without the rustls feature"
+            )),
+        }
+    }
+
+    pub fn backend(&self) -> TlsBackend {
+        #[cfg(feature = "rustls")]
+        if self.rustls.is_some() {
+            return TlsBackend::Rustls;
+        }
+        TlsBackend::Native
+    }
+
+    /// 在已建立的TCP连接上完成TLS握手
+    pub async fn connect(&self, host: &str, stream: TimestampedStream) -> io::Result<WsTransport> {
+        #[cfg(feature = "rustls")]
+        if let Some(connector) = &self.rustls {
+            let server_name = rustls_pki_types::ServerName::try_from(host.to_string())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let stream = connector.connect(server_name, stream).await?;
+            if stream.get_ref().1.handshake_kind()
+                == Some(tokio_rustls::rustls::HandshakeKind::Resumed)
+            {
+                info!("TLS session resumed for {}", host);
+            }
+            return Ok(WsTransport::Rustls(Box::new(stream)));
+        }
+        let stream = self
+            .native
+            .connect(host, stream)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(WsTransport::Native(Box::new(stream)))
+    }
+}
+
+#[cfg(feature = "rustls")]
+fn rustls_connector(cfg: &TlsCfg) -> anyhow::Result<tokio_rustls::TlsConnector> {
+    use anyhow::Context as _;
+    use rustls_pki_types::pem::PemObject;
+    use rustls_pki_types::CertificateDer;
+    use std::sync::Arc;
+    use tokio_rustls::rustls::{self, client::Resumption, RootCertStore};
+
+    let mut roots = RootCertStore::empty();
+    let certs = CertificateDer::pem_file_iter(&cfg.ca_file)
+        .with_context(|| format!("Failed to read CA file {}", cfg.ca_file))?;
+    let (added, ignored) = roots.add_parsable_certificates(certs.flatten());
+    info!(
+        "Loaded {} CA certificates from {} ({} ignored)",
+        added, cfg.ca_file, ignored
+    );
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    // 会话票据缓存在共享的ClientConfig中，重连同一host时复用
+    config.resumption = Resumption::in_memory_sessions(SESSION_CACHE_SIZE);
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config)))
+}
@@ -0,0 +1,309 @@
+use crate::cfg::ProxyEndpointCfg;
+use crate::connection::rate_limit::RateLimiter;
+use crate::connection::resolver::HostResolver;
+use crate::connection::rx_timestamp::TimestampedStream;
+use crate::connection::tls::{TlsClient, WsTransport};
+use anyhow::{bail, Context};
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use url::Url;
+
+// 出口传输层
+// 部分主机只能通过出口代理访问交易所，websocket与REST客户端都从这里取连接参数
+// websocket: 先建立到目标的TCP隧道（HTTP CONNECT 或 SOCKS5），再在隧道上做TLS与握手
+// REST: 转换为reqwest::Proxy
+// 多网卡主机可以按连接分组绑定本地地址，配置了代理时绑定的是到代理的连接
+
+const MAX_CONNECT_RESPONSE: usize = 8192; // CONNECT响应头的最大长度
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScheme {
+    Http,
+    Socks5,
+}
+
+/// 解析后的出口代理
+#[derive(Debug, Clone)]
+pub struct EgressProxy {
+    pub scheme: ProxyScheme,
+    pub host: String,
+    pub port: u16,
+    pub auth: Option<(String, String)>,
+}
+
+impl EgressProxy {
+    pub fn from_cfg(cfg: &ProxyEndpointCfg) -> anyhow::Result<Self> {
+        let url =
+            Url::parse(&cfg.url).with_context(|| format!("Invalid proxy url: {}", cfg.url))?;
+        let (scheme, default_port) = match url.scheme() {
+            "http" => (ProxyScheme::Http, 80),
+            "socks5" | "socks5h" => (ProxyScheme::Socks5, 1080),
+            other => bail!("Unsupported proxy scheme: {}", other),
+        };
+        let host = url
+            .host_str()
+            .with_context(|| format!("Proxy url without host: {}", cfg.url))?
+            .to_string();
+        let auth = match (&cfg.username, &cfg.password) {
+            (Some(username), password) => {
+                Some((username.clone(), password.clone().unwrap_or_default()))
+            }
+            (None, _) => None,
+        };
+        Ok(Self {
+            scheme,
+            host,
+            port: url.port().unwrap_or(default_port),
+            auth,
+        })
+    }
+
+    /// 建立经由代理到 target_host:target_port 的TCP隧道，目标域名由代理解析
+    pub async fn connect(
+        &self,
+        target_host: &str,
+        target_port: u16,
+        local_addr: Option<IpAddr>,
+    ) -> io::Result<TcpStream> {
+        let stream = tcp_connect(&self.host, self.port, local_addr).await?;
+        match self.scheme {
+            ProxyScheme::Http => self.http_connect(stream, target_host, target_port).await,
+            ProxyScheme::Socks5 => {
+                let target = (target_host, target_port);
+                let stream = match &self.auth {
+                    Some((username, password)) => {
+                        Socks5Stream::connect_with_password_and_socket(
+                            stream, target, username, password,
+                        )
+                        .await
+                    }
+                    None => Socks5Stream::connect_with_socket(stream, target).await,
+                }
+                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))?;
+                Ok(stream.into_inner())
+            }
+        }
+    }
+
+    async fn http_connect(
+        &self,
+        mut stream: TcpStream,
+        target_host: &str,
+        target_port: u16,
+    ) -> io::Result<TcpStream> {
+        // IPv6地址（IP优选经由代理连接时）需要加方括号
+        let authority = if target_host.contains(':') {
+            format!("[{}]:{}", target_host, target_port)
+        } else {
+            format!("{}:{}", target_host, target_port)
+        };
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some((username, password)) = &self.auth {
+            let token = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // 逐字节读到响应头结束，避免把隧道中的TLS数据读进缓冲区
+        let mut response = Vec::with_capacity(256);
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() >= MAX_CONNECT_RESPONSE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "proxy CONNECT response too large",
+                ));
+            }
+            if stream.read(&mut byte).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "proxy closed connection during CONNECT",
+                ));
+            }
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8_lossy(&response);
+        let status_line = response.lines().next().unwrap_or("");
+        let status = status_line.split_whitespace().nth(1).unwrap_or("");
+        if status != "200" {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("proxy CONNECT to {} failed: {}", authority, status_line),
+            ));
+        }
+        Ok(stream)
+    }
+
+    /// 转换为REST客户端使用的代理，socks5使用socks5h，由代理解析域名
+    pub fn to_reqwest(&self) -> reqwest::Result<reqwest::Proxy> {
+        let url = match self.scheme {
+            ProxyScheme::Http => format!("http://{}:{}", self.host, self.port),
+            ProxyScheme::Socks5 => format!("socks5h://{}:{}", self.host, self.port),
+        };
+        let proxy = reqwest::Proxy::all(url)?;
+        Ok(match &self.auth {
+            Some((username, password)) => proxy.basic_auth(username, password),
+            None => proxy,
+        })
+    }
+}
+
+/// 建立TCP连接，指定本地地址时只连接同一地址族的目标IP
+pub async fn tcp_connect(
+    host: &str,
+    port: u16,
+    local_addr: Option<IpAddr>,
+) -> io::Result<TcpStream> {
+    let Some(local_addr) = local_addr else {
+        return TcpStream::connect((host, port)).await;
+    };
+    let mut last_error = None;
+    for addr in lookup_host((host, port)).await? {
+        if addr.is_ipv4() != local_addr.is_ipv4() {
+            continue;
+        }
+        match tcp_connect_addr(addr, Some(local_addr)).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!(
+                "no address of {} matches local address {}",
+                host, local_addr
+            ),
+        )
+    }))
+}
+
+/// 连接指定的IP，local_addr需要与目标地址族一致
+pub async fn tcp_connect_addr(
+    addr: SocketAddr,
+    local_addr: Option<IpAddr>,
+) -> io::Result<TcpStream> {
+    let Some(local_addr) = local_addr else {
+        return TcpStream::connect(addr).await;
+    };
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.bind(SocketAddr::new(local_addr, 0))?;
+    socket.connect(addr).await
+}
+
+/// 出口路径：代理、绑定的本地地址与TLS客户端
+/// IP优选按出口路径分别探测，探测与实际连接走同一路径
+#[derive(Debug, Clone, Default)]
+pub struct EgressPath {
+    pub proxy: Option<EgressProxy>,
+    pub local_addr: Option<IpAddr>,
+    pub tls: Option<Arc<TlsClient>>,
+}
+
+impl EgressPath {
+    /// 区分不同出口路径的描述，用于缓存探测结果与日志
+    pub fn describe(&self) -> String {
+        let local = self
+            .local_addr
+            .map_or("default".to_string(), |addr| addr.to_string());
+        match &self.proxy {
+            Some(proxy) => format!("local {} via {}:{}", local, proxy.host, proxy.port),
+            None => format!("local {}", local),
+        }
+    }
+
+    /// 连接指定的目标IP，配置了代理时经由代理连接该IP
+    pub async fn connect_ip(&self, ip: IpAddr, port: u16) -> io::Result<TcpStream> {
+        match &self.proxy {
+            Some(proxy) => proxy.connect(&ip.to_string(), port, self.local_addr).await,
+            None => tcp_connect_addr(SocketAddr::new(ip, port), self.local_addr).await,
+        }
+    }
+
+    /// 在TCP连接上完成TLS握手，未设置共享客户端时新建native-tls连接器
+    pub async fn tls_connect(
+        &self,
+        host: &str,
+        stream: TimestampedStream,
+    ) -> io::Result<WsTransport> {
+        match &self.tls {
+            Some(client) => client.connect(host, stream).await,
+            None => {
+                TlsClient::from_cfg(&Default::default())
+                    .map_err(io::Error::other)?
+                    .connect(host, stream)
+                    .await
+            }
+        }
+    }
+}
+
+/// 建立连接时使用的传输参数，由 Config::connect_options 按交易所与连接分组生成
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    pub proxy: Option<EgressProxy>,
+    pub local_addr: Option<IpAddr>, // 绑定的本地地址，None时走默认路由
+    pub rate_limiter: Arc<RateLimiter>, // 进程内共享的建连/消息限频
+    pub resolver: Option<Arc<HostResolver>>, // 域名解析与IP优选，None时使用系统解析
+    pub tls: Option<Arc<TlsClient>>, // 共享的TLS客户端，None时每次新建native-tls连接器
+    pub kernel_timestamps: bool,    // socket开启内核接收时间戳
+    pub ws_config: WebSocketConfig, // websocket消息/帧大小与写缓冲限制
+}
+
+impl ConnectOptions {
+    pub fn egress_path(&self) -> EgressPath {
+        EgressPath {
+            proxy: self.proxy.clone(),
+            local_addr: self.local_addr,
+            tls: self.tls.clone(),
+        }
+    }
+
+    /// 建立到目标的TCP连接
+    /// 开启IP优选时连接该出口路径上最快的IP（配置了代理时经由代理连接该IP），否则由代理或系统解析域名
+    pub async fn open_tcp(&self, host: &str, port: u16, tls: bool) -> io::Result<TcpStream> {
+        match (&self.resolver, &self.proxy) {
+            (Some(resolver), _) => resolver.connect(host, port, tls, &self.egress_path()).await,
+            (None, Some(proxy)) => proxy.connect(host, port, self.local_addr).await,
+            (None, None) => tcp_connect(host, port, self.local_addr).await,
+        }
+    }
+
+    /// 建立到目标的连接，wss时在TCP连接上完成TLS握手
+    pub async fn open_stream(&self, host: &str, port: u16, tls: bool) -> io::Result<WsTransport> {
+        let stream = self.open_tcp(host, port, tls).await?;
+        let stream = TimestampedStream::new(stream, self.kernel_timestamps);
+        if !tls {
+            return Ok(WsTransport::Plain(stream));
+        }
+        self.egress_path().tls_connect(host, stream).await
+    }
+
+    /// REST客户端使用的代理
+    pub fn reqwest_proxy(&self) -> reqwest::Result<Option<reqwest::Proxy>> {
+        self.proxy.as_ref().map(EgressProxy::to_reqwest).transpose()
+    }
+
+    /// 为REST客户端应用相同的出口设置
+    pub fn apply_to_client(
+        &self,
+        builder: reqwest::ClientBuilder,
+    ) -> reqwest::Result<reqwest::ClientBuilder> {
+        match self.reqwest_proxy()? {
+            Some(proxy) => Ok(builder.proxy(proxy)),
+            None => Ok(builder),
+        }
+    }
+}
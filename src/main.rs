@@ -76,14 +76,19 @@ async fn main() -> anyhow::Result<()> {
 
     match exchange {
         Exchange::Binance | Exchange::BinanceSpot | Exchange::BinanceFutures => {
-            let spot_url = binance_url.ok_or_else(|| {
-                anyhow::anyhow!("--binance-url must be provided for binance exchanges")
-            })?;
-            let futures_url = binance_futures_url.ok_or_else(|| {
-                anyhow::anyhow!("--binance-futures-url must be provided for binance exchanges")
-            })?;
-            config.binance_rest.binance_url = spot_url;
-            config.binance_rest.binance_futures_url = futures_url;
+            // 命令行参数优先，其次是配置文件中的 binance_rest / endpoints
+            if let Some(url) = binance_url {
+                config.binance_rest.binance_url = url;
+            }
+            if let Some(url) = binance_futures_url {
+                config.binance_rest.binance_futures_url = url;
+            }
+            if config.binance_rest.binance_url.is_empty() {
+                anyhow::bail!("--binance-url or a configured spot rest endpoint is required");
+            }
+            if config.binance_rest.binance_futures_url.is_empty() {
+                anyhow::bail!("--binance-futures-url or a configured futures rest endpoint is required");
+            }
         }
         _ => {
             if let Some(url) = binance_url {
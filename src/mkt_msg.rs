@@ -20,6 +20,11 @@ pub enum MktMsgType {
     BinanceMarginBorrowRepay = 1021,
     BinanceMarginAvailableInventory = 1022,
     BinanceMktStatus = 1023,
+    OrderBookReset = 1024, // 订单簿序号断档，下游丢弃该symbol的本地订单簿，等待重新订阅后的快照
+    // 带接收时间的inc/trade，布局与 OrderBookInc/TradeInfo 相同，末尾多8字节recv_ts（纳秒）
+    // 只在开启 kernel_timestamps 且取到了接收时间时使用，未开启时输出与原来完全一致
+    OrderBookIncTs = 1025,
+    TradeInfoTs = 1026,
     Error = 2222,
 }
 
@@ -182,6 +187,61 @@ impl BinanceIncSeqNoMsg {
         buf.freeze()
     }
 }
+/// 订单簿重置的原因
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookResetReason {
+    SequenceGap = 1,      // 增量序号不连续
+    ChecksumMismatch = 2, // 本地订单簿与交易所校验和不一致
+}
+
+pub struct BookResetMsg {
+    pub msg_type: MktMsgType,
+    pub symbol_length: u32,
+    pub symbol: String,
+    pub last_update_id: i64, // 最后一条连续的update id，没有时为0
+    pub update_id: i64,      // 触发重置的update id
+    pub timestamp: i64,
+    pub reason: BookResetReason,
+}
+
+impl BookResetMsg {
+    pub fn create(
+        symbol: String,
+        last_update_id: i64,
+        update_id: i64,
+        timestamp: i64,
+        reason: BookResetReason,
+    ) -> Self {
+        let symbol_length = symbol.len() as u32;
+        Self {
+            msg_type: MktMsgType::OrderBookReset,
+            symbol_length,
+            symbol,
+            last_update_id,
+            update_id,
+            timestamp,
+            reason,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        // msg_type(4) + symbol_length(4) + symbol + last_update_id(8) + update_id(8) + timestamp(8) + reason(4)
+        let total_size = 4 + 4 + self.symbol_length as usize + 8 + 8 + 8 + 4;
+        let mut buf = BytesMut::with_capacity(total_size);
+
+        buf.put_u32_le(self.msg_type as u32);
+        buf.put_u32_le(self.symbol_length);
+        buf.put(self.symbol.as_bytes());
+        buf.put_i64_le(self.last_update_id);
+        buf.put_i64_le(self.update_id);
+        buf.put_i64_le(self.timestamp);
+        buf.put_u32_le(self.reason as u32);
+
+        buf.freeze()
+    }
+}
+
 /// 对永续合约来说, 币安的预估结算没有意义，不需要考虑Estimated Settle Price字段
 
 #[repr(C)]
@@ -209,6 +269,10 @@ pub struct IncMsg {
     pub msg_type: MktMsgType,
     pub symbol_length: u32,
     pub symbol: String,
+    // 本条包含的第一个与最后一个update id，快照两者相同
+    // 币安为 U/u：现货连续增量满足 U == 上一条u+1；合约的连续性由 pu == 上一条u 判断，U 不一定等于上一条u+1
+    // okex为 prevSeqId+1/seqId，连续增量满足 first == 上一条final+1；心跳与序号重置时 first > final
+    // bybit两者都为u
     pub first_update_id: i64,
     pub final_update_id: i64,
     pub timestamp: i64,
@@ -220,6 +284,8 @@ pub struct IncMsg {
     pub asks_count: u32,
     // 存储所有档位数据，bids在前，asks在后
     pub levels: Vec<Level>,
+    // 内核接收时间（纳秒），为0时不输出；非0时消息类型为 OrderBookIncTs，写在档位之后
+    pub recv_ts: i64,
 }
 
 impl IncMsg {
@@ -249,9 +315,54 @@ impl IncMsg {
             bids_count,
             asks_count,
             levels,
+            recv_ts: 0,
+        }
+    }
+
+    /// Create an incremental orderbook message from already parsed levels
+    pub fn from_levels(
+        symbol: String,
+        first_update_id: i64,
+        final_update_id: i64,
+        timestamp: i64,
+        is_snapshot: bool,
+        bids: Vec<Level>,
+        asks: Vec<Level>,
+    ) -> Self {
+        let bids_count = bids.len() as u32;
+        let asks_count = asks.len() as u32;
+        let mut levels = bids;
+        levels.extend(asks);
+
+        Self {
+            msg_type: MktMsgType::OrderBookInc,
+            symbol_length: symbol.len() as u32,
+            symbol,
+            first_update_id,
+            final_update_id,
+            timestamp,
+            is_snapshot,
+            padding: [0u8; 7],
+            bids_count,
+            asks_count,
+            levels,
+            recv_ts: 0,
         }
     }
 
+    /// Set the kernel receive timestamp of the source frame, switching to the OrderBookIncTs layout
+    pub fn with_recv_ts(mut self, recv_ts: i64) -> Self {
+        self.recv_ts = recv_ts;
+        if recv_ts != 0 {
+            self.msg_type = MktMsgType::OrderBookIncTs;
+        }
+        self
+    }
+
+    fn has_recv_ts(&self) -> bool {
+        self.msg_type == MktMsgType::OrderBookIncTs
+    }
+
     /// Set a bid level
     pub fn set_bid_level(&mut self, index: usize, level: Level) {
         if index < self.bids_count as usize && index < self.levels.len() {
@@ -273,10 +384,8 @@ impl IncMsg {
         // Calculate total size:
         // msg_type(4) + symbol_length(4) + symbol + first_update_id(8) + final_update_id(8) + timestamp(8) +
         // is_snapshot(1) + padding(7) + bids_count(4) + asks_count(4) + levels(levels.len() * 16)
-        let levels_size = self.levels.len() * std::mem::size_of::<Level>();
-        let total_size =
-            4 + 4 + self.symbol_length as usize + 8 + 8 + 8 + 1 + 7 + 4 + 4 + levels_size;
-        let mut buf = BytesMut::with_capacity(total_size);
+        // OrderBookIncTs 末尾再加 recv_ts(8)
+        let mut buf = BytesMut::with_capacity(self.size());
 
         // Write header
         buf.put_u32_le(self.msg_type as u32);
@@ -304,13 +413,27 @@ impl IncMsg {
             buf.put_f64_le(level.amount);
         }
 
+        // Write kernel receive timestamp
+        if self.has_recv_ts() {
+            buf.put_i64_le(self.recv_ts);
+        }
+
         buf.freeze()
     }
 
     /// Get the total size of the message
-    #[allow(dead_code)]
     pub fn size(&self) -> usize {
-        4 + 4 + self.symbol_length as usize + 8 + 8 + 8 + 8 + 4 + 4 + (self.levels.len() * 16)
+        let recv_ts_size = if self.has_recv_ts() { 8 } else { 0 };
+        4 + 4
+            + self.symbol_length as usize
+            + 8
+            + 8
+            + 8
+            + 8
+            + 4
+            + 4
+            + (self.levels.len() * 16)
+            + recv_ts_size
     }
 }
 
@@ -328,6 +451,7 @@ pub struct TradeMsg {
     pub padding: [u8; 7],
     pub price: f64,
     pub amount: f64,
+    pub recv_ts: i64, // 内核接收时间（纳秒），为0时不输出；非0时消息类型为 TradeInfoTs，写在amount之后
 }
 
 pub struct LiquidationMsg {
@@ -361,7 +485,21 @@ impl TradeMsg {
             padding: [0u8; 7], // 7字节填充，确保8字节对齐
             price,
             amount,
+            recv_ts: 0,
+        }
+    }
+
+    /// Set the kernel receive timestamp of the source frame, switching to the TradeInfoTs layout
+    pub fn with_recv_ts(mut self, recv_ts: i64) -> Self {
+        self.recv_ts = recv_ts;
+        if recv_ts != 0 {
+            self.msg_type = MktMsgType::TradeInfoTs;
         }
+        self
+    }
+
+    fn has_recv_ts(&self) -> bool {
+        self.msg_type == MktMsgType::TradeInfoTs
     }
 
     /// Convert message to bytes with proper alignment
@@ -369,7 +507,9 @@ impl TradeMsg {
         // Calculate total size:
         // msg_type(4) + symbol_length(4) + symbol + id(8) + timestamp(8) +
         // side(1) + padding(7) + price(8) + amount(8)
-        let total_size = 4 + 4 + self.symbol_length as usize + 8 + 8 + 1 + 7 + 8 + 8;
+        // TradeInfoTs 末尾再加 recv_ts(8)
+        let recv_ts_size = if self.has_recv_ts() { 8 } else { 0 };
+        let total_size = 4 + 4 + self.symbol_length as usize + 8 + 8 + 1 + 7 + 8 + 8 + recv_ts_size;
         let mut buf = BytesMut::with_capacity(total_size);
 
         // Write header
@@ -391,13 +531,19 @@ impl TradeMsg {
         buf.put_f64_le(self.price);
         buf.put_f64_le(self.amount);
 
+        // Write kernel receive timestamp
+        if self.has_recv_ts() {
+            buf.put_i64_le(self.recv_ts);
+        }
+
         buf.freeze()
     }
 
     /// Get the total aligned size of the message
     #[allow(dead_code)]
     pub fn aligned_size(&self) -> usize {
-        4 + 4 + self.symbol_length as usize + 8 + 8 + 8 + 8 + 8 // Last 8 includes side+padding as one 8-byte unit
+        let recv_ts_size = if self.has_recv_ts() { 8 } else { 0 };
+        4 + 4 + self.symbol_length as usize + 8 + 8 + 8 + 8 + 8 + recv_ts_size // Third 8 is side+padding as one 8-byte unit
     }
 }
 
@@ -847,3 +993,41 @@ impl MktMsg {
         buf.freeze()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_ts_layout() {
+        // 没有接收时间时保持原布局与消息类型
+        let trade = TradeMsg::create("BTCUSDT".into(), 1, 2, 'b', 3.0, 4.0);
+        let bytes = trade.to_bytes();
+        assert_eq!(bytes.len(), 4 + 4 + 7 + 40);
+        assert_eq!(&bytes[..4], &(MktMsgType::TradeInfo as u32).to_le_bytes());
+
+        let trade = trade.with_recv_ts(123);
+        let bytes = trade.to_bytes();
+        assert_eq!(bytes.len(), 4 + 4 + 7 + 48);
+        assert_eq!(&bytes[..4], &(MktMsgType::TradeInfoTs as u32).to_le_bytes());
+        assert_eq!(&bytes[bytes.len() - 8..], &123i64.to_le_bytes());
+
+        let bids = vec![Level::from_values(1.0, 2.0)];
+        let inc = IncMsg::from_levels("BTCUSDT".into(), 1, 1, 2, false, bids, vec![]);
+        assert_eq!(inc.to_bytes().len(), inc.size());
+        assert_eq!(
+            &inc.to_bytes()[..4],
+            &(MktMsgType::OrderBookInc as u32).to_le_bytes()
+        );
+        let inc = inc.with_recv_ts(0);
+        assert_eq!(inc.msg_type, MktMsgType::OrderBookInc);
+        let inc = inc.with_recv_ts(456);
+        let bytes = inc.to_bytes();
+        assert_eq!(bytes.len(), inc.size());
+        assert_eq!(
+            &bytes[..4],
+            &(MktMsgType::OrderBookIncTs as u32).to_le_bytes()
+        );
+        assert_eq!(&bytes[bytes.len() - 8..], &456i64.to_le_bytes());
+    }
+}
@@ -0,0 +1,158 @@
+// 热路径parser基准：类型化sonic-rs反序列化 与 serde_json::Value树逐字段取值 的对比
+// 运行：cargo test --release parse_bench -- --ignored --nocapture
+
+use crate::mkt_msg::{IncMsg, Level};
+use crate::parser::binance_parser::BinanceIncParser;
+use crate::parser::bybit_parser::BybitIncParser;
+use crate::parser::default_parser::{ParseOutcome, Parser};
+use crate::parser::okex_parser::OkexIncParser;
+use bytes::Bytes;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const ITERATIONS: u32 = 20_000;
+const DEPTH: usize = 50; // 每侧档位数
+
+fn levels_json(n: usize, base: f64, step: f64) -> String {
+    let levels: Vec<String> = (0..n)
+        .map(|i| {
+            format!(
+                r#"["{:.2}","{:.4}"]"#,
+                base + step * i as f64,
+                0.5 + i as f64
+            )
+        })
+        .collect();
+    format!("[{}]", levels.join(","))
+}
+
+fn binance_depth() -> Bytes {
+    Bytes::from(format!(
+        r#"{{"e":"depthUpdate","E":1700000000001,"T":1700000000000,"s":"BTCUSDT","U":100,"u":120,"pu":99,"b":{},"a":{}}}"#,
+        levels_json(DEPTH, 43000.0, -0.1),
+        levels_json(DEPTH, 43000.1, 0.1)
+    ))
+}
+
+fn okex_books() -> Bytes {
+    Bytes::from(format!(
+        r#"{{"arg":{{"channel":"books","instId":"BTC-USDT-SWAP"}},"action":"snapshot","data":[{{"asks":{},"bids":{},"ts":"1700000000000","checksum":-855196043,"prevSeqId":123456,"seqId":123457}}]}}"#,
+        levels_json(DEPTH, 43000.1, 0.1).replace("\"]", "\",\"0\",\"3\"]"),
+        levels_json(DEPTH, 43000.0, -0.1).replace("\"]", "\",\"0\",\"3\"]")
+    ))
+}
+
+fn bybit_orderbook() -> Bytes {
+    Bytes::from(format!(
+        r#"{{"topic":"orderbook.500.BTCUSDT","type":"snapshot","ts":1700000000001,"data":{{"s":"BTCUSDT","b":{},"a":{},"u":400001,"seq":7961638724}},"cts":1700000000000}}"#,
+        levels_json(DEPTH, 43000.0, -0.1),
+        levels_json(DEPTH, 43000.1, 0.1)
+    ))
+}
+
+/// 原先的做法：解析为Value后按路径取字段，档位逐个as_array/as_str
+fn value_baseline(msg: &Bytes, symbol: &str, id: &str, ts: &str, bids: &str, asks: &str) -> usize {
+    let Ok(value) = serde_json::from_slice::<Value>(msg) else {
+        return 0;
+    };
+    let levels = |pointer: &str| -> Vec<Level> {
+        value
+            .pointer(pointer)
+            .and_then(Value::as_array)
+            .map(|levels| {
+                levels
+                    .iter()
+                    .filter_map(|level| {
+                        let level = level.as_array()?;
+                        Some(Level::new(
+                            level.first()?.as_str()?,
+                            level.get(1)?.as_str()?,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let timestamp = match value.pointer(ts) {
+        Some(Value::String(s)) => s.parse().unwrap_or(0),
+        Some(v) => v.as_i64().unwrap_or(0),
+        None => 0,
+    };
+    let (Some(symbol), Some(id)) = (
+        value.pointer(symbol).and_then(Value::as_str),
+        value.pointer(id).and_then(Value::as_i64),
+    ) else {
+        return 0;
+    };
+    let inc_msg = IncMsg::from_levels(
+        symbol.to_string(),
+        id,
+        id,
+        timestamp,
+        false,
+        levels(bids),
+        levels(asks),
+    );
+    inc_msg.to_bytes().len()
+}
+
+fn emitted(outcome: ParseOutcome) -> usize {
+    match outcome {
+        ParseOutcome::Emitted(count) => count,
+        _ => 0,
+    }
+}
+
+fn time(mut f: impl FnMut() -> usize) -> Duration {
+    let started = Instant::now();
+    let mut sink = 0;
+    for _ in 0..ITERATIONS {
+        sink += std::hint::black_box(f());
+    }
+    assert!(sink > 0);
+    started.elapsed() / ITERATIONS
+}
+
+fn report(name: &str, typed: Duration, value: Duration) {
+    println!(
+        "{:<16} typed {:>8.2?}/msg  value {:>8.2?}/msg  x{:.2}",
+        name,
+        typed,
+        value,
+        value.as_secs_f64() / typed.as_secs_f64()
+    );
+}
+
+#[test]
+#[ignore]
+fn parse_bench() {
+    let (tx, _rx) = broadcast::channel(16);
+
+    let msg = binance_depth();
+    let parser = BinanceIncParser::new(true);
+    let typed = time(|| emitted(parser.parse(msg.clone(), 0, &tx)));
+    let value = time(|| value_baseline(&msg, "/s", "/u", "/T", "/b", "/a"));
+    report("binance depth", typed, value);
+
+    let msg = okex_books();
+    let parser = OkexIncParser::new(false);
+    let typed = time(|| emitted(parser.parse(msg.clone(), 0, &tx)));
+    let value = time(|| {
+        value_baseline(
+            &msg,
+            "/arg/instId",
+            "/data/0/seqId",
+            "/data/0/ts",
+            "/data/0/bids",
+            "/data/0/asks",
+        )
+    });
+    report("okex books", typed, value);
+
+    let msg = bybit_orderbook();
+    let parser = BybitIncParser::new();
+    let typed = time(|| emitted(parser.parse(msg.clone(), 0, &tx)));
+    let value = time(|| value_baseline(&msg, "/data/s", "/data/u", "/cts", "/data/b", "/data/a"));
+    report("bybit orderbook", typed, value);
+}
@@ -2,12 +2,106 @@ use crate::mkt_msg::{
     BinanceIncSeqNoMsg, FundingRateMsg, IncMsg, IndexPriceMsg, KlineMsg, Level, LiquidationMsg,
     MarkPriceMsg, SignalMsg, SignalSource, TradeMsg,
 };
-use crate::parser::default_parser::Parser;
+use crate::parser::default_parser::{ParseOutcome, Parser};
+use crate::parser::wire::{self, Levels};
 use bytes::Bytes;
 use log::{error, info};
+use serde::Deserialize;
 use std::collections::HashSet;
 use tokio::sync::broadcast;
 
+/// 组合stream模式下消息包在 {"stream":..,"data":..} 中，取出data；普通 /ws 消息原样返回
+fn unwrap_combined(json_value: serde_json::Value) -> serde_json::Value {
+    match json_value {
+        serde_json::Value::Object(mut map) if map.contains_key("stream") => {
+            map.remove("data").unwrap_or(serde_json::Value::Null)
+        }
+        other => other,
+    }
+}
+
+/// 组合stream的外层 {"stream":..,"data":..}
+#[derive(Deserialize)]
+struct Combined<T> {
+    data: T,
+}
+
+/// 类型化反序列化币安消息，组合stream时取出data
+fn from_binance<'a, T: Deserialize<'a>>(msg: &'a [u8]) -> sonic_rs::Result<T> {
+    if msg.starts_with(b"{\"stream\"") {
+        wire::from_slice::<Combined<T>>(msg).map(|combined| combined.data)
+    } else {
+        wire::from_slice(msg)
+    }
+}
+
+/// 解析失败时判断帧的类型：订阅回执、其他事件，或者确实是解析错误
+/// expected为该parser处理的事件名
+fn classify(msg: &[u8], expected: &[&str], reason: impl std::fmt::Display) -> ParseOutcome {
+    let value = match serde_json::from_slice::<serde_json::Value>(msg) {
+        Ok(value) => unwrap_combined(value),
+        Err(e) => return ParseOutcome::error(format!("invalid json: {}", e)),
+    };
+    // 请求错误 {"code":2,"msg":"Invalid request","id":1}
+    if let (Some(code), Some(message)) = (value.get("code"), value.get("msg")) {
+        return ParseOutcome::error(format!("exchange error {}: {}", code, message));
+    }
+    // 订阅/取消订阅回执 {"result":null,"id":1}
+    if value.get("id").is_some() && value.get("e").is_none() {
+        return ParseOutcome::Ignored;
+    }
+    let event = match &value {
+        serde_json::Value::Array(items) => items.first().and_then(|item| item.get("e")),
+        _ => value.get("e"),
+    };
+    match event.and_then(|v| v.as_str()) {
+        Some(event) if !expected.contains(&event) => ParseOutcome::UnknownEvent,
+        _ => ParseOutcome::error(reason.to_string()),
+    }
+}
+
+/// 增量深度推送 depthUpdate
+#[derive(Deserialize)]
+struct DepthUpdate<'a> {
+    #[serde(rename = "e")]
+    event: &'a str,
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+    #[serde(rename = "T")]
+    transaction_time: Option<i64>, // 仅合约有
+    #[serde(rename = "s")]
+    symbol: &'a str,
+    #[serde(rename = "U")]
+    first_update_id: i64,
+    #[serde(rename = "u")]
+    final_update_id: i64,
+    #[serde(rename = "pu")]
+    prev_update_id: Option<i64>, // 仅合约有
+    #[serde(rename = "b")]
+    bids: Levels,
+    #[serde(rename = "a")]
+    asks: Levels,
+}
+
+/// 逐笔成交推送 trade
+#[derive(Deserialize)]
+struct TradeEvent<'a> {
+    #[serde(rename = "e")]
+    event: &'a str,
+    #[serde(rename = "s")]
+    symbol: &'a str, // 交易对
+    #[serde(rename = "t")]
+    trade_id: i64, // 交易ID
+    #[serde(rename = "p")]
+    price: &'a str, // 成交价格
+    #[serde(rename = "q")]
+    qty: &'a str, // 成交数量
+    #[serde(rename = "T")]
+    trade_time: i64, // 成交时间
+    #[serde(rename = "m")]
+    is_maker: bool, // 买方是否是做市方
+}
+
 pub struct BinanceSignalParser {
     source: SignalSource,
 }
@@ -25,10 +119,11 @@ impl BinanceSignalParser {
 }
 
 impl Parser for BinanceSignalParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Binance depth message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
+                let json_value = unwrap_combined(json_value);
                 // Extract Binance timestamp field "E"
                 if let Some(timestamp) = json_value.get("E").and_then(|v| v.as_i64()) {
                     // Create signal message
@@ -37,14 +132,14 @@ impl Parser for BinanceSignalParser {
 
                     // Send signal
                     if let Err(_) = sender.send(signal_bytes) {
-                        return 0;
+                        return ParseOutcome::Emitted(0);
                     }
 
-                    return 1;
+                    return ParseOutcome::Emitted(1);
                 }
             }
         }
-        0
+        classify(&msg, &["depthUpdate"], "missing field E")
     }
 }
 
@@ -57,10 +152,11 @@ impl BinanceKlineParser {
 }
 
 impl Parser for BinanceKlineParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Binance kline message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
+                let json_value = unwrap_combined(json_value);
                 // 从顶层s字段直接获取symbol
                 if let Some(symbol) = json_value.get("s").and_then(|v| v.as_str()) {
                     let event_time = json_value.get("E").and_then(|v| v.as_i64()).unwrap_or(0);
@@ -153,16 +249,16 @@ impl Parser for BinanceKlineParser {
                                 );
 
                                 // 发送K线消息（无论是否 closed）
-                                if sender.send(kline_msg.to_bytes()).is_ok() {
-                                    return 1;
-                                }
+                                return ParseOutcome::Emitted(usize::from(
+                                    sender.send(kline_msg.to_bytes()).is_ok(),
+                                ));
                             }
                         }
                     }
                 }
             }
         }
-        0
+        classify(&msg, &["kline"], "missing or invalid kline fields")
     }
 }
 
@@ -179,10 +275,11 @@ impl BinanceDerivativesMetricsParser {
 }
 
 impl Parser for BinanceDerivativesMetricsParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Binance derivatives metrics messages (liquidations + mark price)
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
+                let json_value = unwrap_combined(json_value);
                 // Handle mark price array format: [{e: "markPriceUpdate", ...}, ...]
                 if let Some(data_array) = json_value.as_array() {
                     return self.parse_mark_price_array(data_array, sender);
@@ -195,12 +292,12 @@ impl Parser for BinanceDerivativesMetricsParser {
                         "markPriceUpdate" => {
                             return self.parse_single_mark_price(&json_value, sender)
                         }
-                        _ => return 0,
+                        _ => return ParseOutcome::UnknownEvent,
                     }
                 }
             }
         }
-        0
+        classify(&msg, &["forceOrder", "markPriceUpdate"], "missing event type")
     }
 }
 
@@ -209,7 +306,7 @@ impl BinanceDerivativesMetricsParser {
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse liquidation order data
         if let Some(order_data) = json_value.get("o") {
             if let (
@@ -228,7 +325,7 @@ impl BinanceDerivativesMetricsParser {
                 // Check if symbol is in the allowed list (case-insensitive)
                 let symbol_lower = symbol.to_lowercase();
                 if !self.symbols.contains(&symbol_lower) {
-                    return 0;
+                    return ParseOutcome::Emitted(0);
                 }
                 // Parse quantity and price
                 if let (Ok(quantity), Ok(price)) =
@@ -238,7 +335,7 @@ impl BinanceDerivativesMetricsParser {
                     let liquidation_side = match side {
                         "BUY" => 'B',  // 买入强平
                         "SELL" => 'S', // 卖出强平
-                        _ => return 0,
+                        _ => return ParseOutcome::error(format!("unknown side {}", side)),
                     };
 
                     // Create liquidation message
@@ -251,33 +348,41 @@ impl BinanceDerivativesMetricsParser {
                     );
 
                     // Send liquidation message
-                    if sender.send(liquidation_msg.to_bytes()).is_ok() {
-                        return 1;
-                    }
+                    return ParseOutcome::Emitted(usize::from(
+                        sender.send(liquidation_msg.to_bytes()).is_ok(),
+                    ));
                 }
             }
         }
-        0
+        ParseOutcome::error("missing or invalid forceOrder fields")
     }
 
     fn parse_mark_price_array(
         &self,
         data_array: &Vec<serde_json::Value>,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         let mut total_parsed = 0;
+        let mut last_error = None;
 
         for item in data_array {
-            total_parsed += self.parse_single_mark_price(item, sender);
+            match self.parse_single_mark_price(item, sender) {
+                ParseOutcome::Emitted(count) => total_parsed += count,
+                outcome => last_error = Some(outcome),
+            }
+        }
+        // 整组都没有解析成功时报告最后一个失败原因
+        match last_error {
+            Some(outcome) if total_parsed == 0 => outcome,
+            _ => ParseOutcome::Emitted(total_parsed),
         }
-        total_parsed
     }
 
     fn parse_single_mark_price(
         &self,
         item: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Check if this is a markPriceUpdate event
         if let Some(event_type) = item.get("e").and_then(|v| v.as_str()) {
             if event_type == "markPriceUpdate" {
@@ -299,7 +404,7 @@ impl BinanceDerivativesMetricsParser {
                     // Check if symbol is in the allowed list (case-insensitive)
                     let symbol_lower = symbol.to_lowercase();
                     if !self.symbols.contains(&symbol_lower) {
-                        return 0;
+                        return ParseOutcome::Emitted(0);
                     }
                     // Parse price values
                     if let (Ok(mark_price), Ok(index_price), Ok(funding_rate)) = (
@@ -334,12 +439,13 @@ impl BinanceDerivativesMetricsParser {
                             parsed_count += 1;
                         }
 
-                        return parsed_count;
+                        return ParseOutcome::Emitted(parsed_count);
                     }
                 }
+                return ParseOutcome::error("missing or invalid markPriceUpdate fields");
             }
         }
-        0
+        ParseOutcome::UnknownEvent
     }
 }
 
@@ -352,14 +458,12 @@ impl BinanceSnapshotParser {
 }
 
 impl Parser for BinanceSnapshotParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // 解析币安快照消息
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                return self.parse_snapshot_event(&json_value, sender);
-            }
+        match serde_json::from_slice::<serde_json::Value>(&msg) {
+            Ok(json_value) => self.parse_snapshot_event(&json_value, sender),
+            Err(e) => ParseOutcome::error(format!("invalid json: {}", e)),
         }
-        0
     }
 }
 
@@ -403,7 +507,7 @@ impl BinanceSnapshotParser {
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // 从快照数据中提取信息
         if let (Some(symbol), Some(last_update_id), Some(bids_array), Some(asks_array)) = (
             json_value.get("s").and_then(|v| v.as_str()),
@@ -429,11 +533,9 @@ impl BinanceSnapshotParser {
             parse_order_book_levels(bids_array, asks_array, &mut inc_msg);
 
             // 发送快照消息
-            if sender.send(inc_msg.to_bytes()).is_ok() {
-                return 1;
-            }
+            return ParseOutcome::Emitted(usize::from(sender.send(inc_msg.to_bytes()).is_ok()));
         }
-        0
+        ParseOutcome::error("missing snapshot fields")
     }
 }
 
@@ -448,105 +550,77 @@ impl BinanceIncParser {
 }
 
 impl Parser for BinanceIncParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
-        // 解析币安增量消息
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // 检查是否是增量更新事件
-                if let Some(event_type) = json_value.get("e").and_then(|v| v.as_str()) {
-                    if event_type == "depthUpdate" {
-                        return self.parse_inc_event(&json_value, sender);
-                    }
-                }
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
+        // 解析币安增量消息，只处理增量更新事件
+        match from_binance::<DepthUpdate>(&msg) {
+            Ok(update) if update.event == "depthUpdate" => {
+                self.parse_inc_event(update, recv_ts, sender)
             }
+            Ok(_) => ParseOutcome::UnknownEvent,
+            Err(e) => classify(&msg, &["depthUpdate"], e),
         }
-        0
     }
 }
 
 impl BinanceIncParser {
     fn parse_inc_event(
         &self,
-        json_value: &serde_json::Value,
+        update: DepthUpdate,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // 币安现货用E字段，币安合约用T字段
-        // 判断json是否包含T字段，如果有，timestamp用T，否则用E
-        let timestamp_field = if json_value.get("T").is_some() {
-            "T"
-        } else {
-            "E"
+        let timestamp = match update.transaction_time.or(update.event_time) {
+            Some(timestamp) => timestamp,
+            None => return ParseOutcome::error("missing fields T and E"),
         };
-        // 从增量数据中提取信息
-        if let (
-            Some(symbol),
-            Some(first_update_id),
-            Some(final_update_id),
-            Some(timestamp),
-            Some(bids_array),
-            Some(asks_array),
-        ) = (
-            json_value.get("s").and_then(|v| v.as_str()),
-            json_value.get("U").and_then(|v| v.as_i64()), // first update id
-            json_value.get("u").and_then(|v| v.as_i64()), // final update id
-            json_value.get(timestamp_field).and_then(|v| v.as_i64()), // timestamp
-            json_value.get("b").and_then(|v| v.as_array()), // bids
-            json_value.get("a").and_then(|v| v.as_array()), // asks
-        ) {
-            let mut parsed_count = 0;
-            let symbol_string = symbol.to_string();
-
-            let prev_update_id = if self.is_futures {
-                match json_value.get("pu").and_then(|v| v.as_i64()) {
-                    Some(value) => value,
-                    None => {
-                        error!(
-                            "Missing 'pu' field in futures depthUpdate for symbol {}",
-                            symbol
-                        );
-                        return 0;
-                    }
+        let mut parsed_count = 0;
+        let symbol_string = update.symbol.to_string();
+
+        let prev_update_id = if self.is_futures {
+            match update.prev_update_id {
+                Some(value) => value,
+                None => {
+                    error!(
+                        "Missing 'pu' field in futures depthUpdate for symbol {}",
+                        update.symbol
+                    );
+                    return ParseOutcome::error("missing field pu");
                 }
-            } else {
-                0
-            };
-
-            let seq_msg = BinanceIncSeqNoMsg::create(
-                symbol_string.clone(),
-                prev_update_id,
-                final_update_id,
-                first_update_id,
-                timestamp,
-            );
-            if sender.send(seq_msg.to_bytes()).is_ok() {
-                parsed_count += 1;
             }
+        } else {
+            0
+        };
 
-            let bids_count = bids_array.len() as u32;
-            let asks_count = asks_array.len() as u32;
-
-            // 创建增量消息
-            let mut inc_msg = IncMsg::create(
-                symbol_string,
-                first_update_id,
-                final_update_id,
-                timestamp,
-                false, // is_snapshot = false
-                bids_count,
-                asks_count,
-            );
-
-            // 使用公共函数解析订单簿层级
-            parse_order_book_levels(bids_array, asks_array, &mut inc_msg);
+        let seq_msg = BinanceIncSeqNoMsg::create(
+            symbol_string.clone(),
+            prev_update_id,
+            update.final_update_id,
+            update.first_update_id,
+            timestamp,
+        );
+        if sender.send(seq_msg.to_bytes()).is_ok() {
+            parsed_count += 1;
+        }
 
-            // 发送增量消息
-            if sender.send(inc_msg.to_bytes()).is_ok() {
-                parsed_count += 1;
-            }
+        // 创建增量消息，档位在反序列化时已经解析
+        let inc_msg = IncMsg::from_levels(
+            symbol_string,
+            update.first_update_id,
+            update.final_update_id,
+            timestamp,
+            false, // is_snapshot = false
+            update.bids.0,
+            update.asks.0,
+        )
+        .with_recv_ts(recv_ts);
 
-            return parsed_count;
+        // 发送增量消息
+        if sender.send(inc_msg.to_bytes()).is_ok() {
+            parsed_count += 1;
         }
-        0
+
+        ParseOutcome::Emitted(parsed_count)
     }
 }
 
@@ -565,60 +639,36 @@ impl BinanceSbeIncParser {
         Self
     }
 
-    fn parse_depth_diff(&self, msg: &[u8], sender: &broadcast::Sender<Bytes>) -> usize {
-        let header = match read_sbe_header(msg) {
-            Some(h) => h,
-            None => return 0,
-        };
+    fn parse_depth_diff(
+        &self,
+        msg: &[u8],
+        recv_ts: i64,
+        sender: &broadcast::Sender<Bytes>,
+    ) -> Option<usize> {
+        let header = read_sbe_header(msg)?;
         if header.template_id != 10003 {
-            return 0;
+            return None;
         }
 
         let base = header.body_offset;
         if msg.len() < base + header.block_length {
-            return 0;
+            return None;
         }
 
         // Use eventTime (field id=1) for depth diff timestamp.
-        let event_time = match read_i64_le(msg, base) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let first_update_id = match read_i64_le(msg, base + 8) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let last_update_id = match read_i64_le(msg, base + 16) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let price_exponent = match read_i8(msg, base + 24) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let qty_exponent = match read_i8(msg, base + 25) {
-            Some(v) => v,
-            None => return 0,
-        };
+        let event_time = read_i64_le(msg, base)?;
+        let first_update_id = read_i64_le(msg, base + 8)?;
+        let last_update_id = read_i64_le(msg, base + 16)?;
+        let price_exponent = read_i8(msg, base + 24)?;
+        let qty_exponent = read_i8(msg, base + 25)?;
 
         let mut offset = base + header.block_length;
-        let (bids, next_offset) =
-            match read_group_levels(msg, offset, price_exponent, qty_exponent) {
-                Some(v) => v,
-                None => return 0,
-            };
+        let (bids, next_offset) = read_group_levels(msg, offset, price_exponent, qty_exponent)?;
         offset = next_offset;
-        let (asks, next_offset) =
-            match read_group_levels(msg, offset, price_exponent, qty_exponent) {
-                Some(v) => v,
-                None => return 0,
-            };
+        let (asks, next_offset) = read_group_levels(msg, offset, price_exponent, qty_exponent)?;
         offset = next_offset;
 
-        let symbol = match read_var_string8(msg, offset) {
-            Some((s, _)) => s.to_uppercase(),
-            None => return 0,
-        };
+        let symbol = read_var_string8(msg, offset)?.0.to_uppercase();
 
         let timestamp = event_time / 1000;
         let mut parsed_count = 0;
@@ -644,22 +694,30 @@ impl BinanceSbeIncParser {
             false,
             bids_count,
             asks_count,
-        );
+        )
+        .with_recv_ts(recv_ts);
         parse_order_book_levels_from_pairs(&bids, &asks, &mut inc_msg);
         if sender.send(inc_msg.to_bytes()).is_ok() {
             parsed_count += 1;
         }
 
-        parsed_count
+        Some(parsed_count)
     }
 }
 
 impl Parser for BinanceSbeIncParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         if msg.is_empty() || msg[0] == b'{' || msg[0] == b'[' {
-            return 0;
+            return ParseOutcome::Ignored; // JSON回执
+        }
+        match read_sbe_header(&msg) {
+            Some(header) if header.template_id != 10003 => ParseOutcome::UnknownEvent,
+            Some(_) => match self.parse_depth_diff(&msg, recv_ts, sender) {
+                Some(count) => ParseOutcome::Emitted(count),
+                None => ParseOutcome::error("truncated sbe depth diff"),
+            },
+            None => ParseOutcome::error("truncated sbe header"),
         }
-        self.parse_depth_diff(&msg, sender)
     }
 }
 
@@ -670,49 +728,33 @@ impl BinanceSbeTradeParser {
         Self
     }
 
-    fn parse_trades(&self, msg: &[u8], sender: &broadcast::Sender<Bytes>) -> usize {
-        let header = match read_sbe_header(msg) {
-            Some(h) => h,
-            None => return 0,
-        };
+    fn parse_trades(
+        &self,
+        msg: &[u8],
+        recv_ts: i64,
+        sender: &broadcast::Sender<Bytes>,
+    ) -> Option<usize> {
+        let header = read_sbe_header(msg)?;
         if header.template_id != 10000 {
-            return 0;
+            return None;
         }
 
         let base = header.body_offset;
         if msg.len() < base + header.block_length {
-            return 0;
+            return None;
         }
 
-        let event_time = match read_i64_le(msg, base) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let transact_time = match read_i64_le(msg, base + 8) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let price_exponent = match read_i8(msg, base + 16) {
-            Some(v) => v,
-            None => return 0,
-        };
-        let qty_exponent = match read_i8(msg, base + 17) {
-            Some(v) => v,
-            None => return 0,
-        };
+        let event_time = read_i64_le(msg, base)?;
+        let transact_time = read_i64_le(msg, base + 8)?;
+        let price_exponent = read_i8(msg, base + 16)?;
+        let qty_exponent = read_i8(msg, base + 17)?;
 
         let mut offset = base + header.block_length;
         if msg.len() < offset + 6 {
-            return 0;
+            return None;
         }
-        let block_length = match read_u16_le(msg, offset) {
-            Some(v) => v as usize,
-            None => return 0,
-        };
-        let num_in_group = match read_u32_le(msg, offset + 2) {
-            Some(v) => v as usize,
-            None => return 0,
-        };
+        let block_length = read_u16_le(msg, offset)? as usize;
+        let num_in_group = read_u32_le(msg, offset + 2)? as usize;
         offset += 6;
 
         let mut trades = Vec::with_capacity(num_in_group);
@@ -737,10 +779,7 @@ impl BinanceSbeTradeParser {
             offset += block_length;
         }
 
-        let symbol = match read_var_string8(msg, offset) {
-            Some((s, _)) => s.to_uppercase(),
-            None => return 0,
-        };
+        let symbol = read_var_string8(msg, offset)?.0.to_uppercase();
 
         let _event_time = event_time; // keep for potential diagnostics
         let timestamp = transact_time / 1000;
@@ -760,22 +799,30 @@ impl BinanceSbeTradeParser {
                 side,
                 price,
                 amount,
-            );
+            )
+            .with_recv_ts(recv_ts);
             if sender.send(trade_msg.to_bytes()).is_ok() {
                 parsed_count += 1;
             }
         }
 
-        parsed_count
+        Some(parsed_count)
     }
 }
 
 impl Parser for BinanceSbeTradeParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         if msg.is_empty() || msg[0] == b'{' || msg[0] == b'[' {
-            return 0;
+            return ParseOutcome::Ignored; // JSON回执
+        }
+        match read_sbe_header(&msg) {
+            Some(header) if header.template_id != 10000 => ParseOutcome::UnknownEvent,
+            Some(_) => match self.parse_trades(&msg, recv_ts, sender) {
+                Some(count) => ParseOutcome::Emitted(count),
+                None => ParseOutcome::error("truncated sbe trades"),
+            },
+            None => ParseOutcome::error("truncated sbe header"),
         }
-        self.parse_trades(&msg, sender)
     }
 }
 
@@ -895,72 +942,49 @@ fn parse_order_book_levels_from_pairs(
 }
 
 impl Parser for BinanceTradeParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
-        // Parse Binance trade message
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // Check if this is a trade event
-                if let Some(event_type) = json_value.get("e").and_then(|v| v.as_str()) {
-                    if event_type == "trade" {
-                        return self.parse_trade_event(&json_value, sender);
-                    }
-                }
-            }
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
+        // Parse Binance trade message, only trade events
+        match from_binance::<TradeEvent>(&msg) {
+            Ok(trade) if trade.event == "trade" => self.parse_trade_event(&trade, recv_ts, sender),
+            Ok(_) => ParseOutcome::UnknownEvent,
+            Err(e) => classify(&msg, &["trade"], e),
         }
-        0
     }
 }
 
 impl BinanceTradeParser {
     fn parse_trade_event(
         &self,
-        json_value: &serde_json::Value,
+        trade: &TradeEvent,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
-        // Extract trade data from Binance trade message
-        if let (
-            Some(symbol),
-            Some(trade_id),
-            Some(price_str),
-            Some(qty_str),
-            Some(trade_time),
-            Some(is_maker),
-        ) = (
-            json_value.get("s").and_then(|v| v.as_str()),  // 交易对
-            json_value.get("t").and_then(|v| v.as_i64()),  // 交易ID
-            json_value.get("p").and_then(|v| v.as_str()),  // 成交价格
-            json_value.get("q").and_then(|v| v.as_str()),  // 成交数量
-            json_value.get("T").and_then(|v| v.as_i64()),  // 事件时间
-            json_value.get("m").and_then(|v| v.as_bool()), // 买方是否是做市方
-        ) {
-            // Parse price and quantity
-            if let (Ok(price), Ok(amount)) = (price_str.parse::<f64>(), qty_str.parse::<f64>()) {
-                // Filter out zero values - 币安有时候price和amount会是0，过滤掉不发送
-                if price <= 0.0 || amount <= 0.0 {
-                    return 0;
-                }
-
-                // Determine side: 买方是否是做市方，'S'表示卖出，'B'表示买入
-                // 如果买方是做市方(true)，那么这是一个主动卖出单，标记为'S'
-                // 如果买方不是做市方(false)，那么这是一个主动买入单，标记为'B'
-                let side = if is_maker { 'S' } else { 'B' };
-
-                // Create trade message
-                let trade_msg = TradeMsg::create(
-                    symbol.to_string(),
-                    trade_id,
-                    trade_time,
-                    side,
-                    price,
-                    amount,
-                );
-
-                // Send trade message
-                if sender.send(trade_msg.to_bytes()).is_ok() {
-                    return 1;
-                }
+    ) -> ParseOutcome {
+        // Parse price and quantity
+        if let (Ok(price), Ok(amount)) = (trade.price.parse::<f64>(), trade.qty.parse::<f64>()) {
+            // Filter out zero values - 币安有时候price和amount会是0，过滤掉不发送
+            if price <= 0.0 || amount <= 0.0 {
+                return ParseOutcome::Emitted(0);
             }
+
+            // Determine side: 买方是否是做市方，'S'表示卖出，'B'表示买入
+            // 如果买方是做市方(true)，那么这是一个主动卖出单，标记为'S'
+            // 如果买方不是做市方(false)，那么这是一个主动买入单，标记为'B'
+            let side = if trade.is_maker { 'S' } else { 'B' };
+
+            // Create trade message
+            let trade_msg = TradeMsg::create(
+                trade.symbol.to_string(),
+                trade.trade_id,
+                trade.trade_time,
+                side,
+                price,
+                amount,
+            )
+            .with_recv_ts(recv_ts);
+
+            // Send trade message
+            return ParseOutcome::Emitted(usize::from(sender.send(trade_msg.to_bytes()).is_ok()));
         }
-        0
+        ParseOutcome::error(format!("invalid price {} or quantity {}", trade.price, trade.qty))
     }
 }
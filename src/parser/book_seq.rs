@@ -0,0 +1,309 @@
+use crate::mkt_msg::{BookResetMsg, BookResetReason};
+use crate::parser::default_parser::ParseOutcome;
+use bytes::Bytes;
+use log::{info, warn};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+// 订单簿序号跟踪
+// 按symbol记录最后一条连续增量的id，增量不连续时视为断档：
+// 向下游发送 BookResetMsg，丢弃该symbol后续增量，并请求在本连接上重新订阅以获取新快照
+// 已经应用过的增量（A/B冗余另一条腿、换连接重叠期超出去重窗口的重复帧）直接丢弃，不视为断档
+// okex维护导致序号重置（seqId < prevSeqId）后，重置前的旧增量按重置消息的时间戳识别
+// 收到快照时重置该symbol的状态；parser单任务持有，使用RefCell
+
+const RESYNC_RETRY: Duration = Duration::from_secs(5); // 重新订阅后等待快照的超时，超时后再次请求
+
+/// 单个symbol订单簿的序号状态
+#[derive(Debug)]
+struct BookSeq {
+    last_id: i64,
+    seq: Option<i64>,              // 辅助序号（bybit seq），只要求递增
+    resync_at: Option<Instant>,    // 断档后请求重新订阅的时间，收到快照前丢弃增量
+    seq_reset: Option<(i64, i64)>, // 最近一次序号重置前的id与重置消息的时间戳
+}
+
+impl BookSeq {
+    /// 是否为已经应用过的增量
+    /// prev_id为None时（bybit）id不大于last_id即为重复；
+    /// 有prev_id时（okex）prevSeqId不等于last_id、且整条都不晚于last_id的普通增量为重复，
+    /// 序号重置后，不晚于重置消息、id不大于重置前id的增量属于重置之前
+    fn is_stale(&self, prev_id: Option<i64>, id: i64, timestamp: i64) -> bool {
+        let Some(prev) = prev_id else {
+            return id <= self.last_id;
+        };
+        if prev == self.last_id {
+            return false;
+        }
+        if prev <= id && id <= self.last_id {
+            return true;
+        }
+        self.seq_reset
+            .is_some_and(|(reset_from, reset_ts)| id <= reset_from && timestamp <= reset_ts)
+    }
+}
+
+pub struct BookSequencer {
+    exchange: &'static str,
+    books: RefCell<HashMap<String, BookSeq>>,
+    resync: RefCell<Vec<String>>,
+}
+
+impl BookSequencer {
+    pub fn new(exchange: &'static str) -> Self {
+        Self {
+            exchange,
+            books: RefCell::new(HashMap::new()),
+            resync: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 收到快照，重置该symbol的序号
+    pub fn on_snapshot(&self, symbol: &str, id: i64, seq: Option<i64>) {
+        let state = BookSeq {
+            last_id: id,
+            seq,
+            resync_at: None,
+            seq_reset: None,
+        };
+        if let Some(prev) = self.books.borrow_mut().insert(symbol.to_string(), state) {
+            if prev.resync_at.is_some() {
+                info!("{} orderbook {} resynced at {}", self.exchange, symbol, id);
+            }
+        }
+    }
+
+    /// 检查增量的连续性，连续时更新状态并返回None；需要丢弃时返回该帧的解析结果
+    /// prev_id为交易所给出的上一条id（okex prevSeqId），None时要求 id 为上一条加1（bybit u）
+    pub fn check_delta(
+        &self,
+        symbol: &str,
+        prev_id: Option<i64>,
+        id: i64,
+        seq: Option<i64>,
+        timestamp: i64,
+        sender: &broadcast::Sender<Bytes>,
+    ) -> Option<ParseOutcome> {
+        let last_id = match self.books.borrow_mut().get_mut(symbol) {
+            Some(state) => {
+                if let Some(resync_at) = state.resync_at {
+                    // 等待快照期间丢弃增量，超时仍未收到快照时再次请求
+                    if resync_at.elapsed() >= RESYNC_RETRY {
+                        state.resync_at = Some(Instant::now());
+                        self.request_resync(symbol);
+                    }
+                    return Some(ParseOutcome::Emitted(0));
+                }
+                if state.is_stale(prev_id, id, timestamp) {
+                    // 重复或过期的增量
+                    return Some(ParseOutcome::Emitted(0));
+                }
+                let seq_ok = match (state.seq, seq) {
+                    (Some(last), Some(seq)) => seq > last,
+                    _ => true,
+                };
+                if prev_id.unwrap_or(id - 1) == state.last_id && seq_ok {
+                    if let Some(prev) = prev_id.filter(|prev| id < *prev) {
+                        info!(
+                            "{} orderbook {} sequence reset from {} to {}",
+                            self.exchange, symbol, prev, id
+                        );
+                        state.seq_reset = Some((prev, timestamp));
+                    }
+                    state.last_id = id;
+                    state.seq = seq.or(state.seq);
+                    return None;
+                }
+                state.last_id
+            }
+            None => 0, // 没有收到过快照
+        };
+
+        warn!(
+            "{} orderbook gap for {}: last id {} got prev {:?} id {}, resubscribing",
+            self.exchange, symbol, last_id, prev_id, id
+        );
+        Some(self.reset(symbol, id, timestamp, BookResetReason::SequenceGap, sender))
+    }
+
+    /// 重置该symbol：向下游发送 BookResetMsg，丢弃后续增量直到收到快照，并请求重新订阅
+    pub fn reset(
+        &self,
+        symbol: &str,
+        id: i64,
+        timestamp: i64,
+        reason: BookResetReason,
+        sender: &broadcast::Sender<Bytes>,
+    ) -> ParseOutcome {
+        let last_id = {
+            let mut books = self.books.borrow_mut();
+            let state = books.entry(symbol.to_string()).or_insert(BookSeq {
+                last_id: 0,
+                seq: None,
+                resync_at: None,
+                seq_reset: None,
+            });
+            state.resync_at = Some(Instant::now());
+            state.last_id
+        };
+        self.request_resync(symbol);
+        let reset_msg = BookResetMsg::create(symbol.to_string(), last_id, id, timestamp, reason);
+        ParseOutcome::Emitted(usize::from(sender.send(reset_msg.to_bytes()).is_ok()))
+    }
+
+    /// 取出需要重新订阅的symbol
+    pub fn take_resync(&self) -> Vec<String> {
+        std::mem::take(&mut *self.resync.borrow_mut())
+    }
+
+    fn request_resync(&self, symbol: &str) {
+        let mut resync = self.resync.borrow_mut();
+        if !resync.iter().any(|s| s == symbol) {
+            resync.push(symbol.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(
+        sequencer: &BookSequencer,
+        sender: &broadcast::Sender<Bytes>,
+        prev: i64,
+        id: i64,
+        ts: i64,
+    ) -> Option<ParseOutcome> {
+        sequencer.check_delta("BTC-USDT", Some(prev), id, None, ts, sender)
+    }
+
+    #[test]
+    fn okex_duplicates_are_dropped_without_reset() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("OKX");
+        sequencer.on_snapshot("BTC-USDT", 10, None);
+        assert!(check(&sequencer, &sender, 10, 12, 1).is_none());
+        assert!(check(&sequencer, &sender, 12, 12, 2).is_none()); // 心跳
+        assert!(check(&sequencer, &sender, 12, 15, 3).is_none());
+        // 另一条腿晚到的重复增量与心跳
+        assert!(matches!(
+            check(&sequencer, &sender, 10, 12, 1),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(matches!(
+            check(&sequencer, &sender, 12, 12, 2),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(sequencer.take_resync().is_empty());
+        assert!(check(&sequencer, &sender, 15, 16, 4).is_none());
+    }
+
+    #[test]
+    fn okex_sequence_reset_is_continuous() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("OKX");
+        sequencer.on_snapshot("BTC-USDT", 100, None);
+        assert!(check(&sequencer, &sender, 100, 5, 10).is_none());
+        // 重置消息本身与重置前的增量重复到达
+        assert!(matches!(
+            check(&sequencer, &sender, 100, 5, 10),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(matches!(
+            check(&sequencer, &sender, 99, 100, 9),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(check(&sequencer, &sender, 5, 6, 11).is_none());
+        assert!(sequencer.take_resync().is_empty());
+        // 重置之后的断档仍然触发重新订阅
+        assert!(check(&sequencer, &sender, 7, 8, 12).is_some());
+        assert_eq!(sequencer.take_resync(), vec!["BTC-USDT".to_string()]);
+    }
+
+    fn check_bybit(
+        sequencer: &BookSequencer,
+        sender: &broadcast::Sender<Bytes>,
+        u: i64,
+        seq: i64,
+    ) -> Option<ParseOutcome> {
+        sequencer.check_delta("BTCUSDT", None, u, Some(seq), u, sender)
+    }
+
+    #[test]
+    fn bybit_update_ids_must_be_consecutive() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("Bybit");
+        sequencer.on_snapshot("BTCUSDT", 100, Some(1000));
+        assert!(check_bybit(&sequencer, &sender, 101, 1001).is_none());
+        assert!(check_bybit(&sequencer, &sender, 102, 1005).is_none());
+        // 重复或过期的u直接丢弃
+        assert!(matches!(
+            check_bybit(&sequencer, &sender, 101, 1001),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(sequencer.take_resync().is_empty());
+        // u跳号为断档
+        assert!(matches!(
+            check_bybit(&sequencer, &sender, 104, 1006),
+            Some(ParseOutcome::Emitted(1))
+        ));
+        assert_eq!(sequencer.take_resync(), vec!["BTCUSDT".to_string()]);
+    }
+
+    #[test]
+    fn bybit_seq_must_increase() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("Bybit");
+        sequencer.on_snapshot("BTCUSDT", 100, Some(1000));
+        assert!(check_bybit(&sequencer, &sender, 101, 1001).is_none());
+        // u连续但seq回退
+        assert!(check_bybit(&sequencer, &sender, 102, 1001).is_some());
+        assert_eq!(sequencer.take_resync(), vec!["BTCUSDT".to_string()]);
+    }
+
+    #[test]
+    fn bybit_restart_snapshot_resets_state() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("Bybit");
+        sequencer.on_snapshot("BTCUSDT", 5000, Some(9000));
+        assert!(check_bybit(&sequencer, &sender, 5001, 9001).is_none());
+        // 服务重启后推送u=1的快照，序号从头开始
+        sequencer.on_snapshot("BTCUSDT", 1, Some(10));
+        assert!(check_bybit(&sequencer, &sender, 2, 11).is_none());
+        assert!(check_bybit(&sequencer, &sender, 3, 12).is_none());
+        assert!(sequencer.take_resync().is_empty());
+    }
+
+    #[test]
+    fn bybit_resync_is_retried() {
+        let (sender, _rx) = broadcast::channel(16);
+        let sequencer = BookSequencer::new("Bybit");
+        sequencer.on_snapshot("BTCUSDT", 100, Some(1000));
+        assert!(check_bybit(&sequencer, &sender, 105, 1001).is_some());
+        assert_eq!(sequencer.take_resync(), vec!["BTCUSDT".to_string()]);
+        // 等待快照期间丢弃增量，未超时不重复请求
+        assert!(matches!(
+            check_bybit(&sequencer, &sender, 106, 1002),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert!(sequencer.take_resync().is_empty());
+        // 超过 RESYNC_RETRY 仍未收到快照，再次请求
+        sequencer
+            .books
+            .borrow_mut()
+            .get_mut("BTCUSDT")
+            .unwrap()
+            .resync_at = Some(Instant::now() - RESYNC_RETRY);
+        assert!(matches!(
+            check_bybit(&sequencer, &sender, 107, 1003),
+            Some(ParseOutcome::Emitted(0))
+        ));
+        assert_eq!(sequencer.take_resync(), vec!["BTCUSDT".to_string()]);
+        // 收到快照后恢复
+        sequencer.on_snapshot("BTCUSDT", 110, Some(1010));
+        assert!(check_bybit(&sequencer, &sender, 111, 1011).is_none());
+    }
+}
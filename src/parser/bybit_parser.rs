@@ -1,12 +1,78 @@
 use crate::mkt_msg::{
-    FundingRateMsg, IncMsg, IndexPriceMsg, KlineMsg, Level, LiquidationMsg, MarkPriceMsg,
-    SignalMsg, SignalSource, TradeMsg,
+    FundingRateMsg, IncMsg, IndexPriceMsg, KlineMsg, LiquidationMsg, MarkPriceMsg, SignalMsg,
+    SignalSource, TradeMsg,
 };
-use crate::parser::default_parser::Parser;
+use crate::parser::book_seq::BookSequencer;
+use crate::parser::default_parser::{ParseOutcome, Parser};
+use crate::parser::wire::{self, Levels};
 use bytes::Bytes;
-// use log::info;
+use serde::Deserialize;
 use tokio::sync::broadcast;
 
+/// 解析失败时判断帧的类型：订阅回执/pong、其他topic，或者确实是解析错误
+/// topic为该parser处理的topic前缀
+fn classify(msg: &[u8], topic: &str, reason: impl std::fmt::Display) -> ParseOutcome {
+    let value = match serde_json::from_slice::<serde_json::Value>(msg) {
+        Ok(value) => value,
+        Err(e) => return ParseOutcome::error(format!("invalid json: {}", e)),
+    };
+    // 请求回执 {"success":true,"ret_msg":"","op":"subscribe",..}
+    if value.get("op").is_some() || value.get("success").is_some() {
+        return match value.get("success").and_then(|v| v.as_bool()) {
+            Some(false) => ParseOutcome::error(format!(
+                "exchange error: {}",
+                value.get("ret_msg").unwrap_or(&serde_json::Value::Null)
+            )),
+            _ => ParseOutcome::Ignored,
+        };
+    }
+    match value.get("topic").and_then(|v| v.as_str()) {
+        Some(other) if !other.starts_with(topic) => ParseOutcome::UnknownEvent,
+        _ => ParseOutcome::error(reason.to_string()),
+    }
+}
+
+/// 推送消息 {"topic":..,"type":..,"data":..,"cts":..}
+#[derive(Deserialize)]
+struct BybitPush<'a, T> {
+    topic: &'a str,
+    #[serde(rename = "type")]
+    msg_type: Option<&'a str>, // snapshot/delta
+    cts: Option<i64>, // 撮合时间，仅订单簿有
+    data: T,
+}
+
+/// 订单簿 orderbook 的数据
+#[derive(Deserialize)]
+struct BybitBook<'a> {
+    #[serde(rename = "s")]
+    symbol: &'a str,
+    #[serde(rename = "u")]
+    update_id: i64,
+    seq: Option<i64>, // 跨深度的撮合序号，只保证递增
+    #[serde(rename = "b")]
+    bids: Levels,
+    #[serde(rename = "a")]
+    asks: Levels,
+}
+
+/// 逐笔成交 publicTrade 的一条数据
+#[derive(Deserialize)]
+struct BybitTrade<'a> {
+    #[serde(rename = "s")]
+    symbol: &'a str, // 交易对
+    #[serde(rename = "S")]
+    side: &'a str, // 买卖方向
+    #[serde(rename = "p")]
+    price: &'a str, // 成交价格
+    #[serde(rename = "v")]
+    volume: &'a str, // 成交数量
+    #[serde(rename = "T")]
+    trade_time: i64, // 成交时间
+    #[serde(rename = "i")]
+    id: &'a str, // 交易ID
+}
+
 pub struct BybitSignalParser {
     source: SignalSource,
 }
@@ -24,7 +90,7 @@ impl BybitSignalParser {
 }
 
 impl Parser for BybitSignalParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Bybit depth message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -35,13 +101,11 @@ impl Parser for BybitSignalParser {
                     let signal_bytes = signal_msg.to_bytes();
 
                     // Send signal
-                    if sender.send(signal_bytes).is_ok() {
-                        return 1;
-                    }
+                    return ParseOutcome::Emitted(usize::from(sender.send(signal_bytes).is_ok()));
                 }
             }
         }
-        0
+        classify(&msg, "orderbook.", "missing field cts")
     }
 }
 
@@ -54,7 +118,7 @@ impl BybitKlineParser {
 }
 
 impl Parser for BybitKlineParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Bybit kline message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -70,10 +134,11 @@ impl Parser for BybitKlineParser {
                                     kline_data.get("confirm").and_then(|v| v.as_bool())
                                 {
                                     if confirm == false {
-                                        return 0; // 未确认的K线，不处理
+                                        return ParseOutcome::Emitted(0); // 未确认的K线，不处理
                                     }
                                 } else {
-                                    return 0; // confirm字段无效或缺失
+                                    return ParseOutcome::error("invalid kline confirm field");
+                                    // confirm字段无效或缺失
                                 }
 
                                 // 从topic字段提取symbol
@@ -129,9 +194,9 @@ impl Parser for BybitKlineParser {
                                             );
 
                                             // 发送K线消息
-                                            if sender.send(kline_msg.to_bytes()).is_ok() {
-                                                return 1;
-                                            }
+                                            return ParseOutcome::Emitted(usize::from(
+                                                sender.send(kline_msg.to_bytes()).is_ok(),
+                                            ));
                                         }
                                     }
                                 }
@@ -141,7 +206,7 @@ impl Parser for BybitKlineParser {
                 }
             }
         }
-        0
+        classify(&msg, "kline.", "missing or invalid kline fields")
     }
 }
 
@@ -154,7 +219,7 @@ impl BybitDerivativesMetricsParser {
 }
 
 impl Parser for BybitDerivativesMetricsParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse Bybit derivatives metrics messages (liquidations + tickers)
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -165,10 +230,11 @@ impl Parser for BybitDerivativesMetricsParser {
                     } else if topic.starts_with("tickers.") {
                         return self.parse_ticker_data(&json_value, sender);
                     }
+                    return ParseOutcome::UnknownEvent;
                 }
             }
         }
-        0
+        classify(&msg, "", "missing field topic")
     }
 }
 
@@ -177,7 +243,7 @@ impl BybitDerivativesMetricsParser {
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse liquidation data array
         if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
             let mut parsed_count = 0;
@@ -225,16 +291,16 @@ impl BybitDerivativesMetricsParser {
                 }
             }
 
-            return parsed_count;
+            return ParseOutcome::Emitted(parsed_count);
         }
-        0
+        ParseOutcome::error("missing data array")
     }
 
     fn parse_ticker_data(
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse ticker data - contains mark price, index price, and funding rate
         if let Some(data) = json_value.get("data") {
             if let (Some(symbol), Some(timestamp)) = (
@@ -289,10 +355,10 @@ impl BybitDerivativesMetricsParser {
                     }
                 }
 
-                return parsed_count;
+                return ParseOutcome::Emitted(parsed_count);
             }
         }
-        0
+        ParseOutcome::error("missing ticker symbol or ts")
     }
 }
 
@@ -359,218 +425,161 @@ impl BybitTradeParser {
 }
 
 impl Parser for BybitTradeParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
-        // Parse Bybit trade message
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // Check if this is a trade topic
-                if let Some(topic) = json_value.get("topic").and_then(|v| v.as_str()) {
-                    if topic.starts_with("publicTrade.") {
-                        return self.parse_trade_event(&json_value, sender);
-                    }
-                }
-            }
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
+        // Parse Bybit trade message, data is an array with trade objects
+        match wire::from_slice::<BybitPush<Vec<BybitTrade>>>(&msg) {
+            Ok(push) if push.topic.starts_with("publicTrade.") => match push.data.first() {
+                Some(trade) => self.parse_trade_event(trade, recv_ts, sender),
+                None => ParseOutcome::error("empty data array"),
+            },
+            Ok(_) => ParseOutcome::UnknownEvent,
+            Err(e) => classify(&msg, "publicTrade.", e),
         }
-        0
     }
 }
 
 impl BybitTradeParser {
     fn parse_trade_event(
         &self,
-        json_value: &serde_json::Value,
+        trade: &BybitTrade,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
-        // Extract trade data from Bybit trade message
-        // Bybit trade message format: data is an array with trade objects
-        if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
-            if let Some(trade_data) = data_array.first() {
-                if let (
-                    Some(symbol),
-                    Some(side_str),
-                    Some(price_str),
-                    Some(volume_str),
-                    Some(timestamp),
-                    Some(id_str),
-                ) = (
-                    trade_data.get("s").and_then(|v| v.as_str()), // 交易对
-                    trade_data.get("S").and_then(|v| v.as_str()), // 买卖方向
-                    trade_data.get("p").and_then(|v| v.as_str()), // 成交价格
-                    trade_data.get("v").and_then(|v| v.as_str()), // 成交数量
-                    trade_data.get("T").and_then(|v| v.as_i64()), // 成交时间
-                    trade_data.get("i").and_then(|v| v.as_str()), // 交易ID
-                ) {
-                    // Parse price and volume
-                    if let (Ok(price), Ok(amount)) =
-                        (price_str.parse::<f64>(), volume_str.parse::<f64>())
-                    {
-                        // Filter out zero values
-                        if price <= 0.0 || amount <= 0.0 {
-                            return 0;
-                        }
-
-                        // Convert Bybit side to char
-                        let side = match side_str {
-                            "Sell" => 'S',
-                            "Buy" => 'B',
-                            _ => {
-                                eprintln!("Unknown side: {}", side_str);
-                                return 0;
-                            }
-                        };
-
-                        // Parse ID - could be UUID or numeric
-                        let trade_id = if is_uuid_fast(id_str) {
-                            match uuid_to_int64_mixed(id_str) {
-                                Ok(id) => id,
-                                Err(e) => {
-                                    eprintln!("Failed to parse UUID {}: {}", id_str, e);
-                                    return 0;
-                                }
-                            }
-                        } else if is_numeric(id_str) {
-                            match id_str.parse::<i64>() {
-                                Ok(id) => id,
-                                Err(e) => {
-                                    eprintln!("Failed to parse numeric ID {}: {}", id_str, e);
-                                    return 0;
-                                }
-                            }
-                        } else {
-                            eprintln!("Unknown ID format: {}", id_str);
-                            return 0;
-                        };
-
-                        // Create trade message
-                        let trade_msg = TradeMsg::create(
-                            symbol.to_string(),
-                            trade_id,
-                            timestamp,
-                            side,
-                            price,
-                            amount,
-                        );
-
-                        // Send trade message
-                        if sender.send(trade_msg.to_bytes()).is_ok() {
-                            return 1;
-                        }
-                    }
-                }
+    ) -> ParseOutcome {
+        // Parse price and volume
+        if let (Ok(price), Ok(amount)) = (trade.price.parse::<f64>(), trade.volume.parse::<f64>()) {
+            // Filter out zero values
+            if price <= 0.0 || amount <= 0.0 {
+                return ParseOutcome::Emitted(0);
             }
-        }
-        0
-    }
-}
 
-// 公共函数：解析Bybit订单簿层级数据
-fn parse_bybit_order_book_levels(
-    bids_array: &Vec<serde_json::Value>,
-    asks_array: &Vec<serde_json::Value>,
-    inc_msg: &mut IncMsg,
-) {
-    // 解析bids
-    for (i, bid_item) in bids_array.iter().enumerate() {
-        if let Some(bid_array) = bid_item.as_array() {
-            if bid_array.len() >= 2 {
-                if let (Some(price_str), Some(amount_str)) =
-                    (bid_array[0].as_str(), bid_array[1].as_str())
-                {
-                    let level = Level::new(price_str, amount_str);
-                    inc_msg.set_bid_level(i, level);
+            // Convert Bybit side to char
+            let side = match trade.side {
+                "Sell" => 'S',
+                "Buy" => 'B',
+                _ => return ParseOutcome::error(format!("unknown side {}", trade.side)),
+            };
+
+            // Parse ID - could be UUID or numeric
+            let id_str = trade.id;
+            let trade_id = if is_uuid_fast(id_str) {
+                match uuid_to_int64_mixed(id_str) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        return ParseOutcome::error(format!(
+                            "failed to parse UUID {}: {}",
+                            id_str, e
+                        ))
+                    }
                 }
-            }
-        }
-    }
-
-    // 解析asks
-    for (i, ask_item) in asks_array.iter().enumerate() {
-        if let Some(ask_array) = ask_item.as_array() {
-            if ask_array.len() >= 2 {
-                if let (Some(price_str), Some(amount_str)) =
-                    (ask_array[0].as_str(), ask_array[1].as_str())
-                {
-                    let level = Level::new(price_str, amount_str);
-                    inc_msg.set_ask_level(i, level);
+            } else if is_numeric(id_str) {
+                match id_str.parse::<i64>() {
+                    Ok(id) => id,
+                    Err(e) => {
+                        return ParseOutcome::error(format!(
+                            "failed to parse numeric ID {}: {}",
+                            id_str, e
+                        ))
+                    }
                 }
-            }
+            } else {
+                return ParseOutcome::error(format!("unknown ID format {}", id_str));
+            };
+
+            // Create trade message
+            let trade_msg = TradeMsg::create(
+                trade.symbol.to_string(),
+                trade_id,
+                trade.trade_time,
+                side,
+                price,
+                amount,
+            )
+            .with_recv_ts(recv_ts);
+
+            // Send trade message
+            return ParseOutcome::Emitted(usize::from(sender.send(trade_msg.to_bytes()).is_ok()));
         }
+        ParseOutcome::error("invalid trade price or volume")
     }
 }
 
-pub struct BybitIncParser;
+/// 订单簿增量/快照
+/// 增量的 u 必须是上一条加1、seq 必须递增，否则视为断档，由BookSequencer发送重置事件并请求重新订阅
+/// 收到快照（包括服务重启后 u=1 的快照）时重置序号状态
+pub struct BybitIncParser {
+    sequencer: BookSequencer,
+}
 
 impl BybitIncParser {
     pub fn new() -> Self {
-        Self
+        Self {
+            sequencer: BookSequencer::new("Bybit"),
+        }
     }
 }
 
 impl Parser for BybitIncParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // 解析Bybit增量/快照消息
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // 检查是否是订单簿数据
-                if let Some(topic) = json_value.get("topic").and_then(|v| v.as_str()) {
-                    if topic.starts_with("orderbook.") {
-                        return self.parse_orderbook_event(&json_value, sender);
-                    }
-                }
+        match wire::from_slice::<BybitPush<BybitBook>>(&msg) {
+            Ok(push) if push.topic.starts_with("orderbook.") => {
+                self.parse_orderbook_event(push, recv_ts, sender)
             }
+            Ok(_) => ParseOutcome::UnknownEvent,
+            Err(e) => classify(&msg, "orderbook.", e),
         }
-        0
+    }
+
+    fn take_resync(&self) -> Vec<String> {
+        self.sequencer.take_resync()
     }
 }
 
 impl BybitIncParser {
     fn parse_orderbook_event(
         &self,
-        json_value: &serde_json::Value,
+        push: BybitPush<BybitBook>,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
-        // 从Bybit订单簿数据中提取信息
-        if let (Some(msg_type), Some(timestamp), Some(data)) = (
-            json_value.get("type").and_then(|v| v.as_str()),
-            json_value.get("cts").and_then(|v| v.as_i64()),
-            json_value.get("data"),
+    ) -> ParseOutcome {
+        let (Some(msg_type), Some(timestamp)) = (push.msg_type, push.cts) else {
+            return ParseOutcome::error("missing type or cts");
+        };
+        // 判断是否为快照消息
+        let is_snapshot = match msg_type {
+            "snapshot" => true,
+            "delta" => false,
+            other => return ParseOutcome::error(format!("unknown type {}", other)),
+        };
+        let book = push.data;
+
+        if is_snapshot {
+            self.sequencer
+                .on_snapshot(book.symbol, book.update_id, book.seq);
+        } else if let Some(outcome) = self.sequencer.check_delta(
+            book.symbol,
+            None,
+            book.update_id,
+            book.seq,
+            timestamp,
+            sender,
         ) {
-            if let (Some(symbol), Some(update_id), Some(bids_array), Some(asks_array)) = (
-                data.get("s").and_then(|v| v.as_str()),
-                data.get("u").and_then(|v| v.as_i64()),
-                data.get("b").and_then(|v| v.as_array()),
-                data.get("a").and_then(|v| v.as_array()),
-            ) {
-                let bids_count = bids_array.len() as u32;
-                let asks_count = asks_array.len() as u32;
-
-                // 判断是否为快照消息
-                let is_snapshot = match msg_type {
-                    "snapshot" => true,
-                    "delta" => false,
-                    _ => return 0,
-                };
-
-                // 创建增量/快照消息
-                let mut inc_msg = IncMsg::create(
-                    symbol.to_string(),
-                    update_id,   // first_update_id
-                    update_id,   // final_update_id (Bybit两者相同)
-                    timestamp,   // 使用cts时间戳
-                    is_snapshot, // 根据type字段确定
-                    bids_count,
-                    asks_count,
-                );
-
-                // 使用公共函数解析订单簿层级
-                parse_bybit_order_book_levels(bids_array, asks_array, &mut inc_msg);
-
-                // 发送消息
-                if sender.send(inc_msg.to_bytes()).is_ok() {
-                    return 1;
-                }
-            }
+            return outcome;
         }
-        0
+
+        // 创建增量/快照消息，档位在反序列化时已经解析
+        let inc_msg = IncMsg::from_levels(
+            book.symbol.to_string(),
+            book.update_id, // first_update_id
+            book.update_id, // final_update_id (Bybit每条推送对应一个u，两者相同)
+            timestamp,      // 使用cts时间戳
+            is_snapshot,    // 根据type字段确定
+            book.bids.0,
+            book.asks.0,
+        )
+        .with_recv_ts(recv_ts);
+
+        // 发送消息
+        ParseOutcome::Emitted(usize::from(sender.send(inc_msg.to_bytes()).is_ok()))
     }
 }
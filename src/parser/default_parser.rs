@@ -2,11 +2,37 @@ use crate::mkt_msg::{MktMsg, MktMsgType};
 use bytes::Bytes;
 use tokio::sync::broadcast;
 
+/// 一帧消息的解析结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseOutcome {
+    /// 产生并发送了count条消息，0表示该帧被过滤（如数量为0的成交、不在列表中的symbol）
+    Emitted(usize),
+    /// 订阅回执、pong等控制消息
+    Ignored,
+    /// 格式正确但不是该parser处理的事件
+    UnknownEvent,
+    /// 解析失败，附带原因
+    Error(String),
+}
+
+impl ParseOutcome {
+    pub fn error(reason: impl Into<String>) -> Self {
+        ParseOutcome::Error(reason.into())
+    }
+}
+
 ///需要根据不同交易所实现，因此是一个纯粹的trait
 pub trait Parser: Send {
-    ///有可能消息被drop，也可能一个消息产生多个消息，所以返回一个count, 表示产生了多少消息
+    ///有可能消息被drop，也可能一个消息产生多个消息，成功时返回Emitted(count)，表示产生了多少消息
+    ///控制消息、未知事件与解析失败分别返回对应的结果，由调用方计数与采样
     ///参数需要输入一个broadcast::Sender<Bytes>，表示输出到这个
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize;
+    ///recv_ts为该帧的内核接收时间（纳秒），没有时为0，inc/trade消息原样带出
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome;
+
+    ///取出需要重新订阅的symbol（如订单簿序号断档），parser任务在解析后调用，在本batch的连接上重新订阅
+    fn take_resync(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub struct DefaultTradeParser;
@@ -19,15 +45,15 @@ impl DefaultTradeParser {
 }
 
 impl Parser for DefaultTradeParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         //不做任何行为，直接转发，仅标记msg的type
         let mkt_msg = MktMsg::create(MktMsgType::TradeInfo, msg);
         let msg_bytes = mkt_msg.to_bytes();
 
         if let Err(_) = sender.send(msg_bytes) {
-            return 0;
+            return ParseOutcome::Emitted(0);
         }
-        1
+        ParseOutcome::Emitted(1)
     }
 }
 
@@ -41,14 +67,14 @@ impl DefaultIncParser {
 }
 
 impl Parser for DefaultIncParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         //不做任何行为，直接转发，仅标记msg的type
         let mkt_msg = MktMsg::create(MktMsgType::OrderBookInc, msg);
         let msg_bytes = mkt_msg.to_bytes();
 
         if let Err(_) = sender.send(msg_bytes) {
-            return 0;
+            return ParseOutcome::Emitted(0);
         }
-        1
+        ParseOutcome::Emitted(1)
     }
 }
@@ -0,0 +1,121 @@
+use crate::parser::wire::RawLevel;
+use std::collections::BTreeMap;
+
+// 本地订单簿
+// 保留交易所推送的原始价格/数量字符串，用于按交易所规则计算校验和
+// 按价格排序：价格为正数，f64的位表示与数值顺序一致，直接用作BTreeMap的key
+
+const OKEX_CHECKSUM_DEPTH: usize = 25; // okex校验和覆盖的档位数
+
+#[derive(Debug)]
+struct BookLevel {
+    price: String,
+    amount: String,
+}
+
+#[derive(Debug, Default)]
+pub struct LocalBook {
+    bids: BTreeMap<u64, BookLevel>,
+    asks: BTreeMap<u64, BookLevel>,
+}
+
+impl LocalBook {
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    /// 应用一组档位，数量为0的档位删除；价格或数量不是合法数字时返回错误
+    pub fn apply(&mut self, bids: &[RawLevel], asks: &[RawLevel]) -> Result<(), String> {
+        apply_side(&mut self.bids, bids)?;
+        apply_side(&mut self.asks, asks)
+    }
+
+    /// okex校验和：买卖各取前25档，按 bid价:bid量:ask价:ask量 交替拼接（一侧不足时只拼另一侧），取CRC32的有符号值
+    pub fn okex_checksum(&self) -> i32 {
+        let mut bids = self.bids.values().rev().take(OKEX_CHECKSUM_DEPTH);
+        let mut asks = self.asks.values().take(OKEX_CHECKSUM_DEPTH);
+        let mut parts: Vec<&str> = Vec::with_capacity(OKEX_CHECKSUM_DEPTH * 4);
+        loop {
+            let (bid, ask) = (bids.next(), asks.next());
+            if bid.is_none() && ask.is_none() {
+                break;
+            }
+            for level in [bid, ask].into_iter().flatten() {
+                parts.push(&level.price);
+                parts.push(&level.amount);
+            }
+        }
+        crc32fast::hash(parts.join(":").as_bytes()) as i32
+    }
+}
+
+fn apply_side(side: &mut BTreeMap<u64, BookLevel>, levels: &[RawLevel]) -> Result<(), String> {
+    for level in levels {
+        let price = level
+            .price
+            .parse::<f64>()
+            .map_err(|_| format!("invalid price {}", level.price))?;
+        let amount = level
+            .amount
+            .parse::<f64>()
+            .map_err(|_| format!("invalid amount {}", level.amount))?;
+        let key = price.to_bits();
+        if amount == 0.0 {
+            side.remove(&key);
+        } else {
+            side.insert(
+                key,
+                BookLevel {
+                    price: level.price.to_string(),
+                    amount: level.amount.to_string(),
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels<'a>(levels: &[(&'a str, &'a str)]) -> Vec<RawLevel<'a>> {
+        levels
+            .iter()
+            .map(|&(price, amount)| RawLevel { price, amount })
+            .collect()
+    }
+
+    #[test]
+    fn okex_checksum_matches_documented_example() {
+        // okex文档中的示例，拼接结果为 3366.1:7:3366.8:9:3366:6:3368:8
+        let mut book = LocalBook::default();
+        book.apply(
+            &levels(&[("3366.1", "7"), ("3366", "6")]),
+            &levels(&[("3366.8", "9"), ("3368", "8")]),
+        )
+        .unwrap();
+        assert_eq!(book.okex_checksum(), -1881014294);
+    }
+
+    #[test]
+    fn okex_checksum_follows_updates() {
+        let mut book = LocalBook::default();
+        book.apply(
+            &levels(&[("3366.1", "7"), ("3366", "6"), ("3365", "1")]),
+            &levels(&[("3366.8", "9"), ("3368", "8")]),
+        )
+        .unwrap();
+        // 删除一档、修改一档后与直接给出结果的订单簿一致
+        book.apply(&levels(&[("3365", "0")]), &levels(&[("3368", "8")]))
+            .unwrap();
+        assert_eq!(book.okex_checksum(), -1881014294);
+
+        // 一侧档位不足时只拼接另一侧
+        book.apply(&levels(&[("3366.1", "0"), ("3366", "0")]), &[])
+            .unwrap();
+        let expected = crc32fast::hash(b"3366.8:9:3368:8") as i32;
+        assert_eq!(book.okex_checksum(), expected);
+    }
+}
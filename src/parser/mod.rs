@@ -1,4 +1,10 @@
+#[cfg(test)]
+mod bench;
 pub mod binance_parser;
+pub mod book_seq;
 pub mod bybit_parser;
 pub mod default_parser;
+pub mod local_book;
+pub mod monitor;
 pub mod okex_parser;
+pub mod wire;
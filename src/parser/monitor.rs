@@ -0,0 +1,143 @@
+use crate::cfg::ParseMonitorCfg;
+use crate::parser::default_parser::ParseOutcome;
+use bytes::Bytes;
+use chrono::Utc;
+use log::{info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// parser解析结果监控
+// 每个parser任务持有一份，按解析结果累加计数，每隔 stats_interval 输出一行日志后清零
+// 解析失败与未知事件的原始帧按parser限频写入 <sample_dir>/bad_frames-YYYYMMDD.jsonl，交易所改了格式当天就能看到样本
+// 采样在parser任务中同步写文件，限频后每个parser每个间隔最多一次
+
+/// 一个统计间隔内的计数
+#[derive(Debug, Default)]
+struct ParseCounts {
+    frames: u64,
+    emitted: u64,  // 产出的消息数
+    filtered: u64, // Emitted(0)的帧
+    ignored: u64,
+    unknown: u64,
+    errors: u64,
+}
+
+pub struct ParseMonitor {
+    name: String,
+    stats_interval: Option<Duration>,
+    sample_dir: Option<PathBuf>,
+    sample_interval: Duration,
+    max_sample_bytes: usize,
+    window_start: Instant,
+    counts: ParseCounts,
+    last_error: Option<String>,
+    last_sample: Option<Instant>,
+}
+
+impl ParseMonitor {
+    pub fn new(name: impl Into<String>, cfg: &ParseMonitorCfg) -> Self {
+        Self {
+            name: name.into(),
+            stats_interval: (cfg.stats_interval_secs > 0)
+                .then(|| Duration::from_secs(cfg.stats_interval_secs)),
+            sample_dir: cfg.sample_dir.as_ref().map(PathBuf::from),
+            sample_interval: Duration::from_secs(cfg.sample_interval_secs),
+            max_sample_bytes: cfg.max_sample_bytes,
+            window_start: Instant::now(),
+            counts: ParseCounts::default(),
+            last_error: None,
+            last_sample: None,
+        }
+    }
+
+    /// 记录一帧的解析结果，frame为parser收到的原始帧
+    pub fn record(&mut self, outcome: &ParseOutcome, frame: &Bytes) {
+        self.counts.frames += 1;
+        match outcome {
+            ParseOutcome::Emitted(0) => self.counts.filtered += 1,
+            ParseOutcome::Emitted(count) => self.counts.emitted += *count as u64,
+            ParseOutcome::Ignored => self.counts.ignored += 1,
+            ParseOutcome::UnknownEvent => {
+                self.counts.unknown += 1;
+                self.sample(outcome, frame);
+            }
+            ParseOutcome::Error(reason) => {
+                self.counts.errors += 1;
+                self.last_error = Some(reason.clone());
+                self.sample(outcome, frame);
+            }
+        }
+        self.maybe_report();
+    }
+
+    fn maybe_report(&mut self) {
+        let Some(interval) = self.stats_interval else {
+            return;
+        };
+        if self.window_start.elapsed() < interval {
+            return;
+        }
+        let counts = std::mem::take(&mut self.counts);
+        let last_error = self.last_error.take();
+        self.window_start = Instant::now();
+        let line = format!(
+            "[{}] parse stats: frames {} emitted {} filtered {} ignored {} unknown {} errors {}",
+            self.name,
+            counts.frames,
+            counts.emitted,
+            counts.filtered,
+            counts.ignored,
+            counts.unknown,
+            counts.errors
+        );
+        match last_error {
+            Some(reason) => warn!("{}, last error: {}", line, reason),
+            None if counts.unknown > 0 => warn!("{}", line),
+            None => info!("{}", line),
+        }
+    }
+
+    /// 限频保存一帧原始数据，写入失败只打日志
+    fn sample(&mut self, outcome: &ParseOutcome, frame: &Bytes) {
+        let Some(dir) = &self.sample_dir else {
+            return;
+        };
+        if self
+            .last_sample
+            .is_some_and(|at| at.elapsed() < self.sample_interval)
+        {
+            return;
+        }
+        self.last_sample = Some(Instant::now());
+
+        let now = Utc::now();
+        let (kind, reason) = match outcome {
+            ParseOutcome::Error(reason) => ("error", reason.as_str()),
+            _ => ("unknown_event", ""),
+        };
+        let truncated = &frame[..frame.len().min(self.max_sample_bytes)];
+        let line = serde_json::json!({
+            "ts": now.to_rfc3339(),
+            "parser": self.name,
+            "outcome": kind,
+            "reason": reason,
+            "len": frame.len(),
+            "frame": String::from_utf8_lossy(truncated),
+        });
+        let path = dir.join(format!("bad_frames-{}.jsonl", now.format("%Y%m%d")));
+        let result = fs::create_dir_all(dir).and_then(|_| {
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            writeln!(file, "{}", line)
+        });
+        if let Err(e) = result {
+            warn!(
+                "[{}] Failed to write bad frame sample to {}: {}",
+                self.name,
+                path.display(),
+                e
+            );
+        }
+    }
+}
@@ -1,12 +1,87 @@
 use crate::mkt_msg::{
-    FundingRateMsg, IncMsg, IndexPriceMsg, KlineMsg, Level, LiquidationMsg, MarkPriceMsg,
+    BookResetReason, FundingRateMsg, IncMsg, IndexPriceMsg, KlineMsg, LiquidationMsg, MarkPriceMsg,
     SignalMsg, SignalSource, TradeMsg,
 };
-use crate::parser::default_parser::Parser;
+use crate::parser::book_seq::BookSequencer;
+use crate::parser::default_parser::{ParseOutcome, Parser};
+use crate::parser::local_book::LocalBook;
+use crate::parser::wire::{self, RawLevels};
 use bytes::Bytes;
-use std::collections::HashSet;
+use log::warn;
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use tokio::sync::broadcast;
 
+/// 解析失败时判断帧的类型：订阅回执等事件、其他频道，或者确实是解析错误
+/// channel为该parser处理的频道前缀
+fn classify(msg: &[u8], channel: &str, reason: impl std::fmt::Display) -> ParseOutcome {
+    let value = match serde_json::from_slice::<serde_json::Value>(msg) {
+        Ok(value) => value,
+        Err(e) => return ParseOutcome::error(format!("invalid json: {}", e)),
+    };
+    // 事件消息 {"event":"subscribe",..}，请求错误 {"event":"error","code":"60012","msg":".."}
+    match value.get("event").and_then(|v| v.as_str()) {
+        Some("error") => {
+            return ParseOutcome::error(format!(
+                "exchange error {}: {}",
+                value.get("code").unwrap_or(&serde_json::Value::Null),
+                value.get("msg").unwrap_or(&serde_json::Value::Null)
+            ))
+        }
+        Some(_) => return ParseOutcome::Ignored,
+        None => {}
+    }
+    match value.pointer("/arg/channel").and_then(|v| v.as_str()) {
+        Some(other) if !other.starts_with(channel) => ParseOutcome::UnknownEvent,
+        _ => ParseOutcome::error(reason.to_string()),
+    }
+}
+
+/// 推送消息 {"arg":{..},"action":..,"data":[..]}
+#[derive(Deserialize)]
+struct OkexPush<'a, T> {
+    #[serde(borrow)]
+    arg: OkexArg<'a>,
+    action: Option<&'a str>, // 仅订单簿有：snapshot/update
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct OkexArg<'a> {
+    channel: &'a str,
+    #[serde(rename = "instId")]
+    inst_id: Option<&'a str>,
+}
+
+/// 订单簿 books 的一条数据
+#[derive(Deserialize)]
+struct OkexBook<'a> {
+    #[serde(borrow)]
+    bids: RawLevels<'a>, // [price, amount, deprecated, order_count]，保留原始字符串用于checksum
+    #[serde(borrow)]
+    asks: RawLevels<'a>,
+    #[serde(rename = "seqId")]
+    seq_id: i64,
+    #[serde(rename = "prevSeqId")]
+    prev_seq_id: i64,
+    ts: &'a str,
+    checksum: Option<i64>, // 前25档的CRC32（有符号32位）
+}
+
+/// 逐笔成交 trades 的一条数据
+#[derive(Deserialize)]
+struct OkexTrade<'a> {
+    #[serde(rename = "instId")]
+    inst_id: &'a str, // 交易对
+    #[serde(rename = "tradeId")]
+    trade_id: &'a str, // 交易ID
+    px: &'a str,   // 成交价格
+    sz: &'a str,   // 成交数量
+    side: &'a str, // 买卖方向
+    ts: &'a str,   // 时间戳
+}
+
 pub struct OkexSignalParser {
     source: SignalSource,
 }
@@ -24,7 +99,7 @@ impl OkexSignalParser {
 }
 
 impl Parser for OkexSignalParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse OKEx depth message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -41,13 +116,11 @@ impl Parser for OkexSignalParser {
                     let signal_bytes = signal_msg.to_bytes();
 
                     // Send signal
-                    if sender.send(signal_bytes).is_ok() {
-                        return 1;
-                    }
+                    return ParseOutcome::Emitted(usize::from(sender.send(signal_bytes).is_ok()));
                 }
             }
         }
-        0
+        classify(&msg, "books", "missing field data[0].ts")
     }
 }
 
@@ -60,7 +133,7 @@ impl OkexKlineParser {
 }
 
 impl Parser for OkexKlineParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse OKEx kline message
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -77,10 +150,11 @@ impl Parser for OkexKlineParser {
                                 // 检查K线状态 - 只处理已完结的K线（状态为"1"）
                                 if let Some(status) = kline_data[8].as_str() {
                                     if status != "1" {
-                                        return 0; // 未完结的K线，不处理
+                                        return ParseOutcome::Emitted(0); // 未完结的K线，不处理
                                     }
                                 } else {
-                                    return 0; // 状态字段无效
+                                    return ParseOutcome::error("invalid kline confirm field");
+                                    // 状态字段无效
                                 }
 
                                 // Parse kline data: [ts, o, h, l, c, vol, volCcy, volCcyQuote, confirm]
@@ -133,9 +207,9 @@ impl Parser for OkexKlineParser {
                                         );
 
                                         // Send kline message
-                                        if sender.send(kline_msg.to_bytes()).is_ok() {
-                                            return 1;
-                                        }
+                                        return ParseOutcome::Emitted(usize::from(
+                                            sender.send(kline_msg.to_bytes()).is_ok(),
+                                        ));
                                     }
                                 }
                             }
@@ -144,7 +218,7 @@ impl Parser for OkexKlineParser {
                 }
             }
         }
-        0
+        classify(&msg, "candle", "missing or invalid kline fields")
     }
 }
 
@@ -161,7 +235,7 @@ impl OkexDerivativesMetricsParser {
 }
 
 impl Parser for OkexDerivativesMetricsParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, _recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse OKEx derivatives metrics messages (liquidations + mark price + funding rate + index price)
         if let Ok(json_str) = std::str::from_utf8(&msg) {
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
@@ -178,13 +252,13 @@ impl Parser for OkexDerivativesMetricsParser {
                             "index-tickers" => {
                                 return self.parse_index_price_data(&json_value, sender)
                             }
-                            _ => return 0,
+                            _ => return ParseOutcome::UnknownEvent,
                         }
                     }
                 }
             }
         }
-        0
+        classify(&msg, "", "missing field arg.channel")
     }
 }
 
@@ -193,7 +267,7 @@ impl OkexDerivativesMetricsParser {
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse liquidation data array
         if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
             let mut parsed_count = 0;
@@ -252,16 +326,16 @@ impl OkexDerivativesMetricsParser {
                 }
             }
 
-            return parsed_count;
+            return ParseOutcome::Emitted(parsed_count);
         }
-        0
+        ParseOutcome::error("missing data array")
     }
 
     fn parse_mark_price_data(
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse mark price data array
         if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
             let mut parsed_count = 0;
@@ -288,16 +362,16 @@ impl OkexDerivativesMetricsParser {
                 }
             }
 
-            return parsed_count;
+            return ParseOutcome::Emitted(parsed_count);
         }
-        0
+        ParseOutcome::error("missing data array")
     }
 
     fn parse_funding_rate_data(
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse funding rate data array
         if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
             let mut parsed_count = 0;
@@ -336,16 +410,16 @@ impl OkexDerivativesMetricsParser {
                 }
             }
 
-            return parsed_count;
+            return ParseOutcome::Emitted(parsed_count);
         }
-        0
+        ParseOutcome::error("missing data array")
     }
 
     fn parse_index_price_data(
         &self,
         json_value: &serde_json::Value,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
+    ) -> ParseOutcome {
         // Parse index price data array
         if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
             let mut parsed_count = 0;
@@ -372,9 +446,9 @@ impl OkexDerivativesMetricsParser {
                 }
             }
 
-            return parsed_count;
+            return ParseOutcome::Emitted(parsed_count);
         }
-        0
+        ParseOutcome::error("missing data array")
     }
 }
 
@@ -387,210 +461,202 @@ impl OkexTradeParser {
 }
 
 impl Parser for OkexTradeParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
         // Parse OKEx trade message
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // Check if this has data array
-                if let Some(data_array) = json_value.get("data").and_then(|v| v.as_array()) {
-                    if !data_array.is_empty() {
-                        return self.parse_trade_event(&data_array[0], sender);
-                    }
-                }
-            }
+        match wire::from_slice::<OkexPush<OkexTrade>>(&msg) {
+            Ok(push) => match push.data.first() {
+                Some(trade) => self.parse_trade_event(trade, recv_ts, sender),
+                None => ParseOutcome::error("empty data array"),
+            },
+            Err(e) => classify(&msg, "trades", e),
         }
-        0
     }
 }
 
 impl OkexTradeParser {
     fn parse_trade_event(
         &self,
-        trade_data: &serde_json::Value,
+        trade: &OkexTrade,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
-        // Extract trade data from OKEx trade message
-        if let (
-            Some(symbol),
-            Some(trade_id_str),
-            Some(price_str),
-            Some(size_str),
-            Some(side_str),
-            Some(timestamp_str),
-        ) = (
-            trade_data.get("instId").and_then(|v| v.as_str()), // 交易对
-            trade_data.get("tradeId").and_then(|v| v.as_str()), // 交易ID
-            trade_data.get("px").and_then(|v| v.as_str()),     // 成交价格
-            trade_data.get("sz").and_then(|v| v.as_str()),     // 成交数量
-            trade_data.get("side").and_then(|v| v.as_str()),   // 买卖方向
-            trade_data.get("ts").and_then(|v| v.as_str()),     // 时间戳
+    ) -> ParseOutcome {
+        // Parse price, size, trade_id and timestamp
+        if let (Ok(price), Ok(amount), Ok(trade_id), Ok(timestamp)) = (
+            trade.px.parse::<f64>(),
+            trade.sz.parse::<f64>(),
+            trade.trade_id.parse::<i64>(),
+            trade.ts.parse::<i64>(),
         ) {
-            // Parse price, size, trade_id and timestamp
-            if let (Ok(price), Ok(amount), Ok(trade_id), Ok(timestamp)) = (
-                price_str.parse::<f64>(),
-                size_str.parse::<f64>(),
-                trade_id_str.parse::<i64>(),
-                timestamp_str.parse::<i64>(),
-            ) {
-                // Filter out zero values
-                if price <= 0.0 || amount <= 0.0 {
-                    return 0;
-                }
-
-                // Convert OKEx side to char
-                let side = match side_str {
-                    "sell" => 'S',
-                    "buy" => 'B',
-                    _ => {
-                        eprintln!("Unknown side: {}", side_str);
-                        return 0;
-                    }
-                };
-
-                // Create trade message
-                let trade_msg =
-                    TradeMsg::create(symbol.to_string(), trade_id, timestamp, side, price, amount);
-
-                // Send trade message
-                if sender.send(trade_msg.to_bytes()).is_ok() {
-                    return 1;
-                }
+            // Filter out zero values
+            if price <= 0.0 || amount <= 0.0 {
+                return ParseOutcome::Emitted(0);
             }
-        }
-        0
-    }
-}
-
-// 公共函数：解析OKEx订单簿层级数据
-fn parse_okex_order_book_levels(
-    bids_array: &Vec<serde_json::Value>,
-    asks_array: &Vec<serde_json::Value>,
-    inc_msg: &mut IncMsg,
-) {
-    // 解析bids - OKEx格式：[price, amount, deprecated, order_count]
-    for (i, bid_item) in bids_array.iter().enumerate() {
-        if let Some(bid_array) = bid_item.as_array() {
-            if bid_array.len() >= 2 {
-                if let (Some(price_str), Some(amount_str)) =
-                    (bid_array[0].as_str(), bid_array[1].as_str())
-                {
-                    let level = Level::new(price_str, amount_str);
-                    inc_msg.set_bid_level(i, level);
-                }
-            }
-        }
-    }
 
-    // 解析asks - OKEx格式：[price, amount, deprecated, order_count]
-    for (i, ask_item) in asks_array.iter().enumerate() {
-        if let Some(ask_array) = ask_item.as_array() {
-            if ask_array.len() >= 2 {
-                if let (Some(price_str), Some(amount_str)) =
-                    (ask_array[0].as_str(), ask_array[1].as_str())
-                {
-                    let level = Level::new(price_str, amount_str);
-                    inc_msg.set_ask_level(i, level);
-                }
-            }
+            // Convert OKEx side to char
+            let side = match trade.side {
+                "sell" => 'S',
+                "buy" => 'B',
+                _ => return ParseOutcome::error(format!("unknown side {}", trade.side)),
+            };
+
+            // Create trade message
+            let trade_msg = TradeMsg::create(
+                trade.inst_id.to_string(),
+                trade_id,
+                timestamp,
+                side,
+                price,
+                amount,
+            )
+            .with_recv_ts(recv_ts);
+
+            // Send trade message
+            return ParseOutcome::Emitted(usize::from(sender.send(trade_msg.to_bytes()).is_ok()));
         }
+        ParseOutcome::error("invalid trade fields")
     }
 }
 
-pub struct OkexIncParser;
+/// 订单簿增量/快照
+/// 增量的 prevSeqId 必须等于上一条的 seqId，否则视为断档，由BookSequencer发送重置事件并请求重新订阅
+/// 无变化时的心跳消息 prevSeqId == seqId，维护导致的序号重置 seqId < prevSeqId，两者都满足该规则
+/// 开启checksum时按symbol维护本地订单簿，每条消息校验前25档的checksum，不一致时同样重置并重新订阅
+pub struct OkexIncParser {
+    sequencer: BookSequencer,
+    books: Option<RefCell<HashMap<String, LocalBook>>>, // 开启checksum校验时维护
+    checksum_mismatches: Cell<u64>,
+}
 
 impl OkexIncParser {
-    pub fn new() -> Self {
-        Self
+    pub fn new(checksum: bool) -> Self {
+        Self {
+            sequencer: BookSequencer::new("OKX"),
+            books: checksum.then(|| RefCell::new(HashMap::new())),
+            checksum_mismatches: Cell::new(0),
+        }
     }
 }
 
 impl Parser for OkexIncParser {
-    fn parse(&self, msg: Bytes, sender: &broadcast::Sender<Bytes>) -> usize {
-        // 解析OKEx增量/快照消息
-        if let Ok(json_str) = std::str::from_utf8(&msg) {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(json_str) {
-                // 检查是否是订单簿数据 - 通过arg.channel判断
-                if let Some(arg) = json_value.get("arg") {
-                    if let Some(channel) = arg.get("channel").and_then(|v| v.as_str()) {
-                        if channel.starts_with("books") {
-                            return self.parse_orderbook_event(&json_value, sender);
-                        }
-                    }
-                }
+    fn parse(&self, msg: Bytes, recv_ts: i64, sender: &broadcast::Sender<Bytes>) -> ParseOutcome {
+        // 解析OKEx增量/快照消息，通过arg.channel判断是否是订单簿数据
+        match wire::from_slice::<OkexPush<OkexBook>>(&msg) {
+            Ok(push) if push.arg.channel.starts_with("books") => {
+                self.parse_orderbook_event(push, recv_ts, sender)
             }
+            Ok(_) => ParseOutcome::UnknownEvent,
+            Err(e) => classify(&msg, "books", e),
         }
-        0
+    }
+
+    fn take_resync(&self) -> Vec<String> {
+        self.sequencer.take_resync()
     }
 }
 
 impl OkexIncParser {
     fn parse_orderbook_event(
         &self,
-        json_value: &serde_json::Value,
+        push: OkexPush<OkexBook>,
+        recv_ts: i64,
         sender: &broadcast::Sender<Bytes>,
-    ) -> usize {
-        // 从OKEx订单簿数据中提取信息
-        if let (Some(action), Some(data_array)) = (
-            json_value.get("action").and_then(|v| v.as_str()),
-            json_value.get("data").and_then(|v| v.as_array()),
-        ) {
-            if let Some(data) = data_array.first() {
-                if let (
-                    Some(bids_array),
-                    Some(asks_array),
-                    Some(seq_id),
-                    Some(prev_seq_id),
-                    Some(timestamp_str),
-                ) = (
-                    data.get("bids").and_then(|v| v.as_array()),
-                    data.get("asks").and_then(|v| v.as_array()),
-                    data.get("seqId").and_then(|v| v.as_i64()),
-                    data.get("prevSeqId").and_then(|v| v.as_i64()),
-                    data.get("ts").and_then(|v| v.as_str()),
-                ) {
-                    // 解析时间戳
-                    let timestamp = match timestamp_str.parse::<i64>() {
-                        Ok(ts) => ts,
-                        Err(_) => return 0,
-                    };
-
-                    // 从arg中获取symbol
-                    let symbol = match json_value
-                        .get("arg")
-                        .and_then(|arg| arg.get("instId"))
-                        .and_then(|v| v.as_str())
-                    {
-                        Some(s) => s.to_string(),
-                        None => return 0,
-                    };
-
-                    let bids_count = bids_array.len() as u32;
-                    let asks_count = asks_array.len() as u32;
-
-                    // 判断是否为快照消息
-                    let is_snapshot = action == "snapshot";
-
-                    // 创建增量/快照消息
-                    let mut inc_msg = IncMsg::create(
-                        symbol,
-                        seq_id,      // first_update_id
-                        prev_seq_id, // final_update_id
-                        timestamp,   // 使用ts时间戳
-                        is_snapshot, // 根据action字段确定
-                        bids_count,
-                        asks_count,
-                    );
-
-                    // 使用公共函数解析订单簿层级
-                    parse_okex_order_book_levels(bids_array, asks_array, &mut inc_msg);
-
-                    // 发送消息
-                    if sender.send(inc_msg.to_bytes()).is_ok() {
-                        return 1;
-                    }
-                }
+    ) -> ParseOutcome {
+        let (Some(action), Some(symbol)) = (push.action, push.arg.inst_id) else {
+            return ParseOutcome::error("missing action or arg.instId");
+        };
+        let Some(book) = push.data.into_iter().next() else {
+            return ParseOutcome::error("empty data array");
+        };
+        // 解析时间戳
+        let timestamp = match book.ts.parse::<i64>() {
+            Ok(ts) => ts,
+            Err(_) => return ParseOutcome::error(format!("invalid ts {}", book.ts)),
+        };
+
+        // 判断是否为快照消息
+        let is_snapshot = action == "snapshot";
+
+        // 与币安现货一致：first_update_id为本条包含的第一个id，final_update_id为最后一个id
+        // 快照两者都为seqId，增量为 (prevSeqId+1, seqId)，连续时下一条的first是上一条的final+1
+        let first_update_id = if is_snapshot {
+            self.sequencer.on_snapshot(symbol, book.seq_id, None);
+            book.seq_id
+        } else {
+            if let Some(outcome) = self.sequencer.check_delta(
+                symbol,
+                Some(book.prev_seq_id),
+                book.seq_id,
+                None,
+                timestamp,
+                sender,
+            ) {
+                return outcome;
             }
+            book.prev_seq_id + 1
+        };
+        if let Some(outcome) = self.verify_checksum(symbol, &book, is_snapshot, timestamp, sender) {
+            return outcome;
         }
-        0
+
+        // 创建增量/快照消息，档位在反序列化时已经解析
+        let inc_msg = IncMsg::from_levels(
+            symbol.to_string(),
+            first_update_id,
+            book.seq_id, // final_update_id
+            timestamp,   // 使用ts时间戳
+            is_snapshot, // 根据action字段确定
+            book.bids.to_levels(),
+            book.asks.to_levels(),
+        )
+        .with_recv_ts(recv_ts);
+
+        // 发送消息
+        ParseOutcome::Emitted(usize::from(sender.send(inc_msg.to_bytes()).is_ok()))
+    }
+
+    /// 把消息应用到本地订单簿并校验checksum，一致或未开启时返回None；不一致时重置该symbol
+    fn verify_checksum(
+        &self,
+        symbol: &str,
+        book: &OkexBook,
+        is_snapshot: bool,
+        timestamp: i64,
+        sender: &broadcast::Sender<Bytes>,
+    ) -> Option<ParseOutcome> {
+        let (Some(books), Some(expected)) = (&self.books, book.checksum) else {
+            return None;
+        };
+        let checksum = {
+            let mut books = books.borrow_mut();
+            let local = match books.get_mut(symbol) {
+                Some(local) => local,
+                None => books.entry(symbol.to_string()).or_default(),
+            };
+            if is_snapshot {
+                local.clear();
+            }
+            if let Err(e) = local.apply(&book.bids.0, &book.asks.0) {
+                return Some(ParseOutcome::error(e));
+            }
+            local.okex_checksum()
+        };
+        if i64::from(checksum) == expected {
+            return None;
+        }
+
+        books.borrow_mut().remove(symbol);
+        let mismatches = self.checksum_mismatches.get() + 1;
+        self.checksum_mismatches.set(mismatches);
+        warn!(
+            "OKX checksum mismatch for {} at seqId {}: expected {} got {} ({} mismatches), resubscribing",
+            symbol, book.seq_id, expected, checksum, mismatches
+        );
+        Some(self.sequencer.reset(
+            symbol,
+            book.seq_id,
+            timestamp,
+            BookResetReason::ChecksumMismatch,
+            sender,
+        ))
     }
 }
@@ -0,0 +1,91 @@
+use crate::mkt_msg::Level;
+use serde::de::{self, Deserializer, IgnoredAny, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+// 热路径消息的类型化反序列化
+// inc/trade消息用sonic-rs直接反序列化为借用原始帧的结构体，不构建Value树
+// 档位 ["price","amount",...] 中的数字字符串直接解析为Level，之后的元素跳过
+
+/// 反序列化一帧消息，结构体中的字符串借用原始帧
+pub fn from_slice<'a, T: Deserialize<'a>>(msg: &'a [u8]) -> sonic_rs::Result<T> {
+    sonic_rs::from_slice(msg)
+}
+
+/// 一组档位，按推送顺序
+#[derive(Debug, Default)]
+pub struct Levels(pub Vec<Level>);
+
+impl<'de> Deserialize<'de> for Levels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LevelsVisitor;
+
+        impl<'de> Visitor<'de> for LevelsVisitor {
+            type Value = Levels;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of price levels")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Levels, A::Error> {
+                let mut levels = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(level) = seq.next_element::<RawLevel>()? {
+                    levels.push(level.to_level());
+                }
+                Ok(Levels(levels))
+            }
+        }
+
+        deserializer.deserialize_seq(LevelsVisitor)
+    }
+}
+
+/// 保留原始字符串的档位 ["price","amount",...]，用于需要原样拼接档位的校验（okex checksum）
+#[derive(Debug, Clone, Copy)]
+pub struct RawLevel<'a> {
+    pub price: &'a str,
+    pub amount: &'a str,
+}
+
+impl<'a> RawLevel<'a> {
+    pub fn to_level(self) -> Level {
+        Level::new(self.price, self.amount)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawLevel<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawLevelVisitor;
+
+        impl<'de> Visitor<'de> for RawLevelVisitor {
+            type Value = RawLevel<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a [price, amount, ...] level")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawLevel<'de>, A::Error> {
+                let price: &'de str = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let amount: &'de str = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(RawLevel { price, amount })
+            }
+        }
+
+        deserializer.deserialize_seq(RawLevelVisitor)
+    }
+}
+
+/// 一组保留原始字符串的档位，按推送顺序
+#[derive(Debug, Default, Deserialize)]
+pub struct RawLevels<'a>(#[serde(borrow)] pub Vec<RawLevel<'a>>);
+
+impl RawLevels<'_> {
+    pub fn to_levels(&self) -> Vec<Level> {
+        self.0.iter().map(|level| level.to_level()).collect()
+    }
+}
@@ -16,7 +16,7 @@ use pkcs8::DecodePrivateKey;
 use prost::Message;
 use reqwest::{
     header::{CONTENT_ENCODING, CONTENT_TYPE},
-    Client,
+    Client, ClientBuilder, Proxy,
 };
 use serde::de::{self, Deserializer, Visitor};
 use serde::Deserialize;
@@ -45,7 +45,6 @@ const ONE_MINUTE_MILLIS: i64 = 60_000;
 const FIVE_MINUTE_MILLIS: i64 = 5 * ONE_MINUTE_MILLIS;
 const REST_MONITOR_TAG: &str = "[REST-MON]";
 const BAPI_BORROW_REPAY_PATH: &str = "bapi/margin/v1/public/margin/statistics/24h-borrow-and-repay";
-const SAPI_BASE_URL: &str = "https://api.binance.com";
 const SAPI_AVAILABLE_INVENTORY_PATH: &str = "sapi/v1/margin/available-inventory";
 
 /// 请求超时时间
@@ -358,12 +357,23 @@ where
     deserializer.deserialize_any(I64Visitor)
 }
 
-/// 从 Binance exchangeInfo 获取 USDT 永续合约 symbol 列表
-pub async fn fetch_futures_symbols(base_url: &str) -> Result<Vec<String>, FetchError> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(10))
+/// 构建 HTTP 客户端，配置了出口代理时经由代理
+fn build_client(builder: ClientBuilder, proxy: Option<&Proxy>) -> Result<Client, FetchError> {
+    let builder = match proxy {
+        Some(proxy) => builder.proxy(proxy.clone()),
+        None => builder,
+    };
+    builder
         .build()
-        .map_err(|e| FetchError::Request(e.to_string()))?;
+        .map_err(|e| FetchError::Request(e.to_string()))
+}
+
+/// 从 Binance exchangeInfo 获取 USDT 永续合约 symbol 列表
+pub async fn fetch_futures_symbols(
+    base_url: &str,
+    proxy: Option<&Proxy>,
+) -> Result<Vec<String>, FetchError> {
+    let client = build_client(Client::builder().timeout(Duration::from_secs(10)), proxy)?;
 
     let url = format!("{}/fapi/v1/exchangeInfo", base_url);
 
@@ -539,7 +549,10 @@ async fn fetch_bapi_endpoint(
     fetch_with_retry(client, &url, &[], &[], label, "bapi").await
 }
 
-async fn fetch_sapi_available_inventory(client: &Client) -> Result<String, FetchError> {
+async fn fetch_sapi_available_inventory(
+    client: &Client,
+    base_url: &str,
+) -> Result<String, FetchError> {
     let api_key = std::env::var("BINANCE_API_KEY")
         .map_err(|_| FetchError::Request("BINANCE_API_KEY not set".to_string()))?;
     let signing_key = load_binance_ed25519_key()?;
@@ -565,7 +578,7 @@ async fn fetch_sapi_available_inventory(client: &Client) -> Result<String, Fetch
         ("signature", signature.as_str()),
     ];
 
-    let url = join_url(SAPI_BASE_URL, SAPI_AVAILABLE_INVENTORY_PATH);
+    let url = join_url(base_url, SAPI_AVAILABLE_INVENTORY_PATH);
     fetch_with_retry(
         client,
         &url,
@@ -851,25 +864,33 @@ async fn fetch_open_interest_hist(
 pub struct BinanceRestFetcher {
     spot_base_url: String,
     futures_base_url: String,
+    sapi_base_url: String,
     client: Client,
     symbols: Vec<String>,
+    proxy: Option<Proxy>, // 出口代理，由app按配置生成
 }
 
 impl BinanceRestFetcher {
     /// 创建新的 REST Fetcher
-    pub async fn new(spot_base_url: String, futures_base_url: String) -> Result<Self, FetchError> {
-        let client = Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .pool_max_idle_per_host(100)
-            .build()
-            .map_err(|e| FetchError::Request(e.to_string()))?;
+    pub async fn new(
+        spot_base_url: String,
+        futures_base_url: String,
+        sapi_base_url: String,
+        proxy: Option<Proxy>,
+    ) -> Result<Self, FetchError> {
+        let client = build_client(
+            Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .pool_max_idle_per_host(100),
+            proxy.as_ref(),
+        )?;
 
         // 获取 symbol 列表
         info!(
             "{REST_MONITOR_TAG} Fetching futures symbols from {}",
             futures_base_url
         );
-        let symbols = fetch_futures_symbols(&futures_base_url).await?;
+        let symbols = fetch_futures_symbols(&futures_base_url, proxy.as_ref()).await?;
         info!(
             "{REST_MONITOR_TAG} Fetched {} futures symbols",
             symbols.len()
@@ -878,8 +899,10 @@ impl BinanceRestFetcher {
         Ok(Self {
             spot_base_url,
             futures_base_url,
+            sapi_base_url,
             client,
             symbols,
+            proxy,
         })
     }
 
@@ -890,7 +913,7 @@ impl BinanceRestFetcher {
 
     /// 刷新 symbol 列表
     pub async fn refresh_symbols(&mut self) -> Result<(), FetchError> {
-        let symbols = fetch_futures_symbols(&self.futures_base_url).await?;
+        let symbols = fetch_futures_symbols(&self.futures_base_url, self.proxy.as_ref()).await?;
         info!(
             "{REST_MONITOR_TAG} Refreshed symbols: {} -> {}",
             self.symbols.len(),
@@ -942,7 +965,8 @@ impl BinanceRestFetcher {
             BAPI_BORROW_REPAY_PATH,
             "BapiBorrowRepay",
         );
-        let bapi_inventory_future = fetch_sapi_available_inventory(&bapi_client);
+        let bapi_inventory_future =
+            fetch_sapi_available_inventory(&bapi_client, &self.sapi_base_url);
 
         // 等待所有请求完成
         let (premium_results, oi_results, bapi_borrow_repay, bapi_available_inventory) = tokio::join!(
@@ -1364,14 +1388,16 @@ fn build_binance_mkt_status_payload(
 pub async fn run_rest_fetcher_with_sender(
     spot_base_url: String,
     futures_base_url: String,
+    sapi_base_url: String,
     sender: broadcast::Sender<Bytes>,
+    proxy: Option<Proxy>,
 ) {
     info!(
-        "{REST_MONITOR_TAG} Starting BinanceRestFetcher | spot_base_url={} | futures_base_url={} (with message sender)",
-        spot_base_url, futures_base_url
+        "{REST_MONITOR_TAG} Starting BinanceRestFetcher | spot_base_url={} | futures_base_url={} | sapi_base_url={} (with message sender)",
+        spot_base_url, futures_base_url, sapi_base_url
     );
 
-    let mut fetcher = match BinanceRestFetcher::new(spot_base_url, futures_base_url).await {
+    let mut fetcher = match BinanceRestFetcher::new(spot_base_url, futures_base_url, sapi_base_url, proxy).await {
 
         Ok(f) => f,
         Err(e) => {
@@ -1736,7 +1762,7 @@ mod tests {
     #[tokio::test]
     async fn test_fetch_symbols() {
         let base_url = "https://fapi.binance.com";
-        let result = fetch_futures_symbols(base_url).await;
+        let result = fetch_futures_symbols(base_url, None).await;
         assert!(result.is_ok());
         let symbols = result.unwrap();
         assert!(!symbols.is_empty());
@@ -3,6 +3,16 @@ use serde_json::Value;
 use std::collections::HashSet;
 
 fn construct_subscribe_message(exchange: &str, symbols: &[String], channel: &str) -> Value {
+    construct_op_message(exchange, symbols, channel, true)
+}
+
+/// 构造订阅/取消订阅消息，两者除操作名外格式相同
+pub fn construct_op_message(
+    exchange: &str,
+    symbols: &[String],
+    channel: &str,
+    subscribe: bool,
+) -> Value {
     match exchange {
         "binance-futures" | "binance" | "binance-spot" => {
             let params: Vec<String> = symbols
@@ -10,7 +20,7 @@ fn construct_subscribe_message(exchange: &str, symbols: &[String], channel: &str
                 .map(|symbol| format!("{}@{}", symbol.to_lowercase(), channel))
                 .collect();
             serde_json::json!({
-                "method": "SUBSCRIBE",
+                "method": if subscribe { "SUBSCRIBE" } else { "UNSUBSCRIBE" },
                 "params": params,
                 "id": 1,
             })
@@ -26,7 +36,7 @@ fn construct_subscribe_message(exchange: &str, symbols: &[String], channel: &str
                 })
                 .collect();
             serde_json::json!({
-                "op": "subscribe",
+                "op": if subscribe { "subscribe" } else { "unsubscribe" },
                 "args": args
             })
         }
@@ -36,7 +46,7 @@ fn construct_subscribe_message(exchange: &str, symbols: &[String], channel: &str
                 .map(|symbol| format!("{}.{}", channel, symbol))
                 .collect();
             serde_json::json!({
-                "op": "subscribe",
+                "op": if subscribe { "subscribe" } else { "unsubscribe" },
                 "args": args
             })
         }
@@ -44,11 +54,49 @@ fn construct_subscribe_message(exchange: &str, symbols: &[String], channel: &str
     }
 }
 
+/// 币安组合stream的URL：订阅项写在URL中，推送的消息包在 {"stream":..,"data":..} 中
+pub fn binance_combined_stream_url(ws_url: &str, sub_msg: &Value) -> String {
+    let base = ws_url.trim_end_matches('/').trim_end_matches("/ws");
+    let streams: Vec<&str> = sub_msg["params"]
+        .as_array()
+        .map(|params| params.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    format!("{}/stream?streams={}", base, streams.join("/"))
+}
+
+/// 两次symbol集合之间的差异
+#[derive(Debug, Clone, Default)]
+pub struct SymbolDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl SymbolDiff {
+    /// 按条件把差异分为两部分，第一部分满足条件
+    pub fn partition(&self, pred: impl Fn(&str) -> bool) -> (SymbolDiff, SymbolDiff) {
+        let (added_a, added_b) = self.added.iter().cloned().partition(|s| pred(s));
+        let (removed_a, removed_b) = self.removed.iter().cloned().partition(|s| pred(s));
+        (
+            SymbolDiff {
+                added: added_a,
+                removed: removed_a,
+            },
+            SymbolDiff {
+                added: added_b,
+                removed: removed_b,
+            },
+        )
+    }
+}
+
 //市场高频数据的订阅消息
 //包含一个信号，用于切分数据
 //其次是增量行情快照数据和逐笔成交数据
 pub struct SubscribeMsgs {
     active_symbols: HashSet<String>,              //当前所有u本位符号
+    symbol_batches: Vec<Vec<String>>,             //每个inc/trade batch包含的symbol，与订阅消息一一对应
+    priority_batches: usize,                      //symbol_batches中前priority_batches个为重点batch
+    kline_symbol_batches: Vec<Vec<String>>,       //每个kline batch包含的symbol
     inc_subscribe_msgs: Vec<serde_json::Value>,   //增量orderbook
     trade_subscribe_msgs: Vec<serde_json::Value>, //逐笔成交
     kline_subscribe_msgs: Vec<serde_json::Value>, //k线
@@ -202,6 +250,22 @@ impl SubscribeMsgs {
         self.active_symbols.clone()
     }
 
+    pub fn set_active_symbols(&mut self, symbols: HashSet<String>) {
+        self.active_symbols = symbols;
+    }
+
+    pub fn get_symbol_batch(&self, index: usize) -> &[String] {
+        &self.symbol_batches[index]
+    }
+
+    pub fn is_priority_batch(&self, index: usize) -> bool {
+        index < self.priority_batches
+    }
+
+    pub fn get_kline_symbol_batch(&self, index: usize) -> &[String] {
+        &self.kline_symbol_batches[index]
+    }
+
     pub fn get_inc_subscribe_msg_len(&self) -> usize {
         self.inc_subscribe_msgs.len()
     }
@@ -218,7 +282,10 @@ impl SubscribeMsgs {
         &self.trade_subscribe_msgs[index]
     }
 
-    pub fn compare_symbol_set(prev_symbols: &HashSet<String>, new_symbols: &HashSet<String>) {
+    pub fn compare_symbol_set(
+        prev_symbols: &HashSet<String>,
+        new_symbols: &HashSet<String>,
+    ) -> SymbolDiff {
         println!("Updating symbols (current: {} symbols)", prev_symbols.len());
 
         let new_set: HashSet<String> = new_symbols.iter().map(|s| s.clone()).collect();
@@ -250,9 +317,15 @@ impl SubscribeMsgs {
         } else {
             println!("No symbol changes");
         }
+
+        let mut added: Vec<String> = new_set.difference(old_set).cloned().collect();
+        let mut removed: Vec<String> = old_set.difference(&new_set).cloned().collect();
+        added.sort();
+        removed.sort();
+        SymbolDiff { added, removed }
     }
 
-    fn get_inc_channel(exchange: &str) -> String {
+    pub fn get_inc_channel(exchange: &str) -> String {
         match exchange {
             "binance-futures" => "depth@0ms".to_string(),
             "binance" => "depth@100ms".to_string(),
@@ -264,7 +337,7 @@ impl SubscribeMsgs {
             _ => panic!("Unsupported exchange: {}", exchange),
         }
     }
-    fn get_kline_channel(exchange: &str) -> String {
+    pub fn get_kline_channel(exchange: &str) -> String {
         match exchange {
             "binance-futures" | "binance" | "binance-spot" => "kline_1m".to_string(),
             "okex-swap" | "okex" => "candle1m".to_string(),
@@ -273,7 +346,7 @@ impl SubscribeMsgs {
         }
     }
 
-    fn get_trade_channel(exchange: &str) -> String {
+    pub fn get_trade_channel(exchange: &str) -> String {
         match exchange {
             "binance-futures" | "binance" | "binance-spot" => "trade".to_string(),
             "okex-swap" | "okex" => "trades".to_string(),
@@ -324,6 +397,23 @@ impl SubscribeMsgs {
         }
     }
 
+    pub fn get_exchange_sbe_data_url(exchange: &str) -> &'static str {
+        match exchange {
+            //币安现货 SBE inc/trade
+            "binance-spot" => "wss://stream-sbe.binance.com:9443/ws",
+            _ => panic!("Unsupported exchange for SBE: {}", exchange),
+        }
+    }
+
+    pub fn get_exchange_derivatives_data_url(exchange: &str) -> &'static str {
+        match exchange {
+            "binance-futures" | "binance" | "binance-spot" => BinancePerpsSubscribeMsgs::WS_URL,
+            "okex-swap" | "okex" => OkexPerpsSubscribeMsgs::WS_URL,
+            "bybit" | "bybit-spot" => BybitPerpsSubscribeMsgs::WS_URL,
+            _ => panic!("Unsupported exchange: {}", exchange),
+        }
+    }
+
     fn get_signal_subscribe_message(exchange: &str) -> serde_json::Value {
         match exchange {
             "binance-futures" => {
@@ -378,13 +468,25 @@ impl SubscribeMsgs {
         let inc_channel = SubscribeMsgs::get_inc_channel(&exchange);
         let trade_channel = SubscribeMsgs::get_trade_channel(&exchange);
         let kline_channel = SubscribeMsgs::get_kline_channel(&exchange);
-        for chunk in symbols.chunks(batch_size) {
+        // 重点symbol单独切分成小batch排在前面，其余symbol按batch_size切分
+        let (priority, regular) = cfg.priority.split(&symbols);
+        let priority_chunks: Vec<&[String]> =
+            priority.chunks(cfg.priority.batch_size.max(1)).collect();
+        let priority_batches = priority_chunks.len();
+        let mut symbol_batches = Vec::new();
+        for chunk in priority_chunks.into_iter().chain(regular.chunks(batch_size)) {
+            symbol_batches.push(chunk.to_vec());
             inc_subscribe_msgs.push(construct_subscribe_message(&exchange, chunk, &inc_channel));
             trade_subscribe_msgs.push(construct_subscribe_message(
                 &exchange,
                 chunk,
                 &trade_channel,
             ));
+        }
+        // k线不区分重点symbol
+        let mut kline_symbol_batches = Vec::new();
+        for chunk in symbols.chunks(batch_size) {
+            kline_symbol_batches.push(chunk.to_vec());
             kline_subscribe_msgs.push(construct_subscribe_message(
                 &exchange,
                 chunk,
@@ -393,6 +495,9 @@ impl SubscribeMsgs {
         }
         Self {
             active_symbols: symbols.iter().map(|s| s.clone()).collect(),
+            symbol_batches,
+            priority_batches,
+            kline_symbol_batches,
             inc_subscribe_msgs,
             trade_subscribe_msgs,
             kline_subscribe_msgs,